@@ -1,52 +1,122 @@
-use aoclib::{parse, CommaSep};
-use intcode::{Computer, Word};
-use std::{ops::Deref, path::Path};
-
-fn execute(program: &[Word], noun: Word, verb: Word) -> Result<Word, Error> {
-    let mut program = program.to_owned();
-    program[1] = noun;
-    program[2] = verb;
-    let mut computer = Computer::<0>::new(program);
+use intcode::{
+    sweep_find,
+    symbolic::{Executor, Expr, Outcome},
+    Computer, Memory, Word,
+};
+
+const WANT_VALUE: Word = 19690720;
+
+fn execute(program: &Memory, noun: Word, verb: Word) -> Result<Word, Error> {
+    let mut computer = Computer::<0>::new(program.clone());
+    computer.patch(1, noun)?;
+    computer.patch(2, verb)?;
     computer.run()?;
-    Ok(computer.into_memory()[0])
+    Ok(computer.peek(0)?)
 }
 
-pub fn part1(input: &Path) -> Result<(), Error> {
-    for (idx, program) in parse::<CommaSep<Word>>(input)?.enumerate() {
-        let program = program.deref();
-        let value = execute(program, 12, 2)?;
-        println!("pgm {idx} pt 1: {value}");
-    }
-    Ok(())
+/// The final value of `mem[0]` in terms of `noun` and `verb`, if the program always halts
+/// the same way whatever they are, and the value does not depend on memory read through them.
+fn closed_form(program: &Memory) -> Option<Expr> {
+    let mut executor = Executor::new(program);
+    executor.make_symbol(1, "noun");
+    executor.make_symbol(2, "verb");
+    let [path] = <[_; 1]>::try_from(executor.explore()).ok()?;
+    let expr = path.memory(0);
+    (matches!(path.outcome(), Outcome::Halted) && !expr.reads_memory()).then_some(expr)
 }
 
-fn find_noun_verb(program: &[Word]) -> Result<Word, Error> {
-    const WANT_VALUE: Word = 19690720;
-    for noun in 0..100 {
-        for verb in 0..100 {
-            if execute(program, noun, verb)? == WANT_VALUE {
-                return Ok(100 * noun + verb);
-            }
-        }
-    }
-    Err(Error::NoSolution)
+/// The noun and verb for which `expr` is `want`, trying nouns in order.
+fn solve_closed_form(expr: &Expr, want: Word) -> Option<(Word, Word)> {
+    let solution = expr.solve(want, &[("noun", 0..100), ("verb", 0..100)])?;
+    let [noun, verb] = solution[..] else {
+        return None;
+    };
+    Some((noun, verb))
+}
+
+/// The noun and verb for which the program leaves `want` in `mem[0]`, trying every pair in order.
+fn solve_by_sweep(program: &Memory, want: Word) -> Option<(Word, Word)> {
+    let variants = (0..100)
+        .flat_map(|noun| (0..100).map(move |verb| [(1, noun), (2, verb)]))
+        .collect::<Vec<_>>();
+    let (idx, ()) = sweep_find(program, &variants, |computer| {
+        (computer.peek(0).ok()? == want).then_some(())
+    })?;
+    let [(_, noun), (_, verb)] = variants[idx];
+    Some((noun, verb))
 }
 
-pub fn part2(input: &Path) -> Result<(), Error> {
-    for (idx, program) in parse::<CommaSep<Word>>(input)?.enumerate() {
-        let program = program.deref();
-        let noun_verb = find_noun_verb(program)?;
-        println!("pgm {idx} pt 2: {noun_verb}");
+fn find_noun_verb(program: &Memory, want: Word) -> Result<Word, Error> {
+    let solution = match closed_form(program) {
+        Some(expr) => solve_closed_form(&expr, want),
+        // without a closed form, try every noun and verb
+        None => solve_by_sweep(program, want),
+    };
+    let (noun, verb) = solution.ok_or(Error::NoSolution)?;
+    Ok(100 * noun + verb)
+}
+
+pub struct Day02;
+
+impl runner::Day for Day02 {
+    const DAY: u8 = 2;
+
+    type Part1 = Word;
+    type Part2 = Word;
+    type Error = Error;
+
+    fn part1(program: &Memory) -> Result<Word, Error> {
+        execute(program, 12, 2)
+    }
+
+    fn part2(program: &Memory) -> Result<Word, Error> {
+        find_noun_verb(program, WANT_VALUE)
     }
-    Ok(())
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error(transparent)]
-    Io(#[from] std::io::Error),
     #[error(transparent)]
     Intcode(#[from] intcode::Error),
     #[error("no solution found")]
     NoSolution,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_form_agrees_with_sweep() {
+        // day02's shape: the noun and verb are first used as addresses, and then
+        // `mem[0] = 360 * noun + 1234 + verb`
+        let mut words = vec![
+            1, 0, 0, 3, 2, 1, 20, 3, 1, 3, 21, 3, 1, 3, 2, 0, 99, 0, 0, 0, 360, 1234,
+        ];
+        // every noun and verb is a valid address
+        words.resize(100, 0);
+        let program = Memory::from(words);
+
+        let expr = closed_form(&program).unwrap();
+        assert_eq!(expr.to_string(), "360 * noun + verb + 1234");
+        let want = 360 * 42 + 1234 + 17;
+        assert_eq!(solve_closed_form(&expr, want), Some((42, 17)));
+        assert_eq!(solve_by_sweep(&program, want), Some((42, 17)));
+
+        assert_eq!(solve_closed_form(&expr, 1), None);
+        assert_eq!(solve_by_sweep(&program, 1), None);
+    }
+
+    #[test]
+    fn reads_through_noun_and_verb_are_swept() {
+        // `mem[0] = mem[noun] + mem[verb]`, where `mem[i] = i * i` beyond the program
+        let mut words = vec![1, 0, 0, 0, 99];
+        words.extend((5..100).map(|i| i * i));
+        let program = Memory::from(words);
+
+        assert!(closed_form(&program).is_none());
+        let want = 42 * 42 + 17 * 17;
+        assert_eq!(solve_by_sweep(&program, want), Some((17, 42)));
+        assert_eq!(find_noun_verb(&program, want).unwrap(), 1742);
+    }
+}
@@ -1,9 +1,7 @@
-use aoclib::{parse, CommaSep};
-use intcode::{Computer, Word};
-use std::{ops::Deref, path::Path};
+use intcode::{Computer, Memory, Word};
 
-fn get_diagnostic_code(program: Vec<Word>, device_id: Word) -> Result<Word, Error> {
-    let mut computer = Computer::<0>::new(program);
+fn get_diagnostic_code(program: &Memory, device_id: Word) -> Result<Word, Error> {
+    let mut computer = Computer::<0>::new(program.clone());
 
     computer.provide_input([device_id]);
     let outputs = computer.collect_outputs::<Vec<_>>()?;
@@ -24,26 +22,26 @@ fn get_diagnostic_code(program: Vec<Word>, device_id: Word) -> Result<Word, Erro
     Ok(*diagnostic_code)
 }
 
-fn run_programs(input: &Path, part: u8, device_id: Word) -> Result<(), Error> {
-    for (idx, program) in parse::<CommaSep<Word>>(input)?.enumerate() {
-        let diagnostic_code = get_diagnostic_code(program.deref().clone(), device_id)?;
-        println!("pgm {idx} pt {part}: {diagnostic_code}");
-    }
-    Ok(())
-}
+pub struct Day05;
 
-pub fn part1(input: &Path) -> Result<(), Error> {
-    run_programs(input, 1, 1)
-}
+impl runner::Day for Day05 {
+    const DAY: u8 = 5;
+
+    type Part1 = Word;
+    type Part2 = Word;
+    type Error = Error;
 
-pub fn part2(input: &Path) -> Result<(), Error> {
-    run_programs(input, 2, 5)
+    fn part1(program: &Memory) -> Result<Word, Error> {
+        get_diagnostic_code(program, 1)
+    }
+
+    fn part2(program: &Memory) -> Result<Word, Error> {
+        get_diagnostic_code(program, 5)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error(transparent)]
-    Io(#[from] std::io::Error),
     #[error(transparent)]
     Intcode(#[from] intcode::Error),
     #[error("test code at idx {idx} produced a non-0 value {value}")]
@@ -1,133 +1,84 @@
-use aoclib::{parse, CommaSep};
-use intcode::{Computer, Word};
-use std::{ops::Deref as _, path::Path};
+use intcode::{Circuit, Computer, Memory, Word};
+use std::fmt;
 
 const N_AMPS: usize = 5;
 
 type PhaseSettings = [Word; N_AMPS];
 
-type Amp = Computer<1>;
-
-struct AmplificationCircuit {
-    amplifiers: [Amp; N_AMPS],
-}
-
-impl AmplificationCircuit {
-    fn new(phase_settings: PhaseSettings, program: &[Word]) -> Self {
-        let amplifiers = [
-            Amp::new(program),
-            Amp::new(program),
-            Amp::new(program),
-            Amp::new(program),
-            Amp::new(program),
-        ];
-        for (phase, computer) in itertools::zip_eq(phase_settings, amplifiers.iter()) {
-            computer
-                .input()
-                .try_send(phase)
-                .expect("fresh computer with a buffer can receive phase without blocking");
-        }
-        Self { amplifiers }
+/// Run one amplifier per phase setting in a line, or in a feedback loop if `recycle` is set.
+///
+/// Returns the final signal produced by the last amplifier.
+fn amplify(phase_settings: PhaseSettings, program: &Memory, recycle: bool) -> Result<Word, Error> {
+    let mut circuit = Circuit::new();
+    let amplifiers = phase_settings.map(|phase| {
+        let amp = circuit.add_node(Computer::new(program.clone()));
+        circuit.seed(amp, [phase]);
+        amp
+    });
+    if recycle {
+        circuit.ring(&amplifiers);
+    } else {
+        circuit.chain(&amplifiers);
     }
+    circuit.seed(amplifiers[0], [0]);
 
-    fn run(&mut self, recycle: bool) -> Result<Word, Error> {
-        std::thread::scope(|scope| {
-            // get the transmitter for the first amp, and the receiver for the last
-            let circuit_in = self.amplifiers[0].input();
-            let circuit_out = self.amplifiers[4].output();
-
-            // hook the amplifiers into each other
-            // if either end of the channel closes, that's just the signal to kill the thread
-            for window in self.amplifiers.windows(2) {
-                let l = &window[0];
-                let r = &window[1];
-
-                let rx = l.output();
-                let tx = r.input();
-
-                scope.spawn(move || {
-                    for msg in rx.iter() {
-                        if tx.send(msg).is_err() {
-                            return;
-                        }
-                    }
-                });
-            }
-
-            // now run each amplifier
-            for amp in self.amplifiers.iter_mut() {
-                scope.spawn(|| amp.run());
-            }
-
-            // kick everything off
-            let mut msg = 0;
-            let mut first = true;
-
-            // run the loop
-            while first || recycle {
-                first = false;
-
-                if circuit_in.send(msg).is_err() {
-                    // can't send; amp 0 has finished its run
-                    break;
-                }
+    let outputs = circuit.run()?;
+    outputs[amplifiers[N_AMPS - 1]]
+        .last()
+        .copied()
+        .ok_or(Error::NoSolution)
+}
 
-                let Ok(msg_r) = circuit_out.recv() else {
-                    // can't recv; amp 4 has finished its run
-                    break;
-                };
-                msg = msg_r;
-            }
+/// The strongest signal a circuit can produce, and the phase settings which produce it.
+pub struct MaxSignal {
+    value: Word,
+    phase_settings: PhaseSettings,
+}
 
-            Ok(msg)
-        })
+impl fmt::Display for MaxSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "max value {} with {:?}", self.value, self.phase_settings)
     }
 }
 
 fn find_max_value(
-    idx: usize,
-    part: u8,
-    program: &[Word],
+    program: &Memory,
     phase_settings: &mut PhaseSettings,
     recycle: bool,
-) -> Result<(), Error> {
+) -> Result<MaxSignal, Error> {
     let heap = permutohedron::Heap::new(phase_settings);
-    let Some((max_value, phase_settings)) = heap
-        .map(|phase_settings| {
-            let mut circuit = AmplificationCircuit::new(phase_settings, program);
-            let value = circuit
-                .run(recycle)
-                .expect("does not fail to compute a value");
-            (value, phase_settings)
-        })
-        .max()
-    else {
-        return Err(Error::NoSolution);
-    };
-    println!("pgm {idx} pt {part}: max value {max_value} with {phase_settings:?}");
-    Ok(())
+    let mut best = None;
+    for phase_settings in heap {
+        let value = amplify(phase_settings, program, recycle)?;
+        best = best.max(Some((value, phase_settings)));
+    }
+    let (value, phase_settings) = best.ok_or(Error::NoSolution)?;
+    Ok(MaxSignal {
+        value,
+        phase_settings,
+    })
 }
 
-pub fn part1(input: &Path) -> Result<(), Error> {
-    for (idx, program) in parse::<CommaSep<Word>>(input)?.enumerate() {
-        let program = program.deref().as_ref();
-        find_max_value(idx, 1, program, &mut [0, 1, 2, 3, 4], false)?;
+pub struct Day07;
+
+impl runner::Day for Day07 {
+    const DAY: u8 = 7;
+
+    type Part1 = MaxSignal;
+    type Part2 = MaxSignal;
+    type Error = Error;
+
+    fn part1(program: &Memory) -> Result<MaxSignal, Error> {
+        find_max_value(program, &mut [0, 1, 2, 3, 4], false)
     }
-    Ok(())
-}
 
-pub fn part2(input: &Path) -> Result<(), Error> {
-    for (idx, program) in parse::<CommaSep<Word>>(input)?.enumerate() {
-        let program = program.deref().as_ref();
-        find_max_value(idx, 2, program, &mut [5, 6, 7, 8, 9], true)?;
+    fn part2(program: &Memory) -> Result<MaxSignal, Error> {
+        find_max_value(program, &mut [5, 6, 7, 8, 9], true)
     }
-    Ok(())
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error(transparent)]
-    Io(#[from] std::io::Error),
     #[error(transparent)]
     Intcode(#[from] intcode::Error),
     #[error("no solution found")]
@@ -0,0 +1,61 @@
+//! Translate each program in this day's input to Rust ahead of time, if the input is available.
+//!
+//! The translations are written to `$OUT_DIR/compiled.rs`, which defines `PROGRAMS`.
+//! For tests, it also defines `EXAMPLE`: a translation of the puzzle's quine example,
+//! which is available whether or not the input is.
+
+use std::{fmt::Write as _, path::Path};
+
+use intcode::{aot::translate, Memory};
+
+const DAY: u8 = 9;
+
+const EXAMPLE: &str = "../intcode/tests/conformance/relative_base_quine.program";
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={EXAMPLE}");
+    println!("cargo:rerun-if-env-changed={}", puzzle_input::INPUT_DIR_VAR);
+    // picks up an input added after the first build
+    let inputs_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../inputs");
+    if inputs_dir.is_dir() {
+        println!("cargo:rerun-if-changed={}", inputs_dir.display());
+    }
+
+    let programs = match puzzle_input::find(None, DAY) {
+        Ok(path) => {
+            println!("cargo:rerun-if-changed={}", path.display());
+            Memory::from_path(&path).expect("parsing input")
+        }
+        Err(_) => Vec::new(),
+    };
+
+    let mut out = String::new();
+    for (idx, program) in programs.iter().enumerate() {
+        writeln!(out, "pub mod program{idx} {{\n{}}}\n", translate(program)).unwrap();
+    }
+    writeln!(out, "pub const PROGRAMS: &[::intcode::aot::Compiled] = &[").unwrap();
+    for idx in 0..programs.len() {
+        writeln!(out, "    program{idx}::COMPILED,").unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let example = std::fs::read_to_string(EXAMPLE)
+        .expect("reading example")
+        .parse::<Memory>()
+        .expect("parsing example");
+    writeln!(
+        out,
+        "\n#[cfg(test)]\npub mod example {{\n{}}}\n",
+        translate(&example)
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "#[cfg(test)]\npub const EXAMPLE: ::intcode::aot::Compiled = example::COMPILED;"
+    )
+    .unwrap();
+
+    let out_dir = std::env::var_os("OUT_DIR").expect("OUT_DIR is set for build scripts");
+    std::fs::write(Path::new(&out_dir).join("compiled.rs"), out).expect("writing translation");
+}
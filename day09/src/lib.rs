@@ -1,46 +1,85 @@
-use aoclib::{parse, CommaSep};
-use intcode::{Computer, Word};
-use std::{ops::Deref as _, path::Path};
+use intcode::{Computer, Memory, Word};
+use std::{collections::VecDeque, fmt};
 
-fn get_boost_keycode(idx: usize, part: u8, program: &[Word]) -> Result<(), Error> {
+/// Ahead-of-time translations of the input programs which were available at build time.
+#[allow(clippy::all)]
+mod compiled {
+    include!(concat!(env!("OUT_DIR"), "/compiled.rs"));
+}
+
+/// The BOOST keycode, and any opcodes the BOOST program reported as malfunctioning.
+pub struct Boost {
+    malfunctioning_opcodes: Vec<Word>,
+    keycode: Word,
+}
+
+impl fmt::Display for Boost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.malfunctioning_opcodes.is_empty() {
+            write!(
+                f,
+                "malfunctioning opcodes: {:?}; ",
+                self.malfunctioning_opcodes
+            )?;
+        }
+        write!(f, "boost {}", self.keycode)
+    }
+}
+
+fn get_boost_keycode(part: u8, program: &Memory) -> Result<Boost, Error> {
     let input = match part {
         1 => 1,
         2 => 2,
         _ => return Err(Error::UnknownPart(part)),
     };
-    let mut computer = Computer::<0>::new(program);
-    computer.provide_input([input]);
-    let output = computer.collect_outputs::<Vec<_>>()?;
+    let output = match compiled::PROGRAMS
+        .iter()
+        .find(|compiled| compiled.is_for(program))
+    {
+        Some(compiled) => {
+            let mut output = Vec::new();
+            compiled.run(
+                &mut Computer::new(program.clone()),
+                &mut VecDeque::from([input]),
+                &mut output,
+            )?;
+            output
+        }
+        None => {
+            let mut computer = Computer::<0>::new(program.clone());
+            computer.provide_input([input]);
+            computer.collect_outputs::<Vec<_>>()?
+        }
+    };
     let [malfunctioning_opcodes @ .., boost] = output.as_slice() else {
         return Err(Error::NoSolution);
     };
-    for malf in malfunctioning_opcodes {
-        println!("pgm {idx}: malfunctioning opcode: {malf}");
-    }
-    println!("pgm {idx} part {part}: boost {boost}");
-    Ok(())
+    Ok(Boost {
+        malfunctioning_opcodes: malfunctioning_opcodes.to_owned(),
+        keycode: *boost,
+    })
 }
 
-pub fn part1(input: &Path) -> Result<(), Error> {
-    for (idx, program) in parse::<CommaSep<Word>>(input)?.enumerate() {
-        let program = program.deref().as_ref();
-        get_boost_keycode(idx, 1, program)?;
+pub struct Day09;
+
+impl runner::Day for Day09 {
+    const DAY: u8 = 9;
+
+    type Part1 = Boost;
+    type Part2 = Boost;
+    type Error = Error;
+
+    fn part1(program: &Memory) -> Result<Boost, Error> {
+        get_boost_keycode(1, program)
     }
-    Ok(())
-}
 
-pub fn part2(input: &Path) -> Result<(), Error> {
-    for (idx, program) in parse::<CommaSep<Word>>(input)?.enumerate() {
-        let program = program.deref().as_ref();
-        get_boost_keycode(idx, 2, program)?;
+    fn part2(program: &Memory) -> Result<Boost, Error> {
+        get_boost_keycode(2, program)
     }
-    Ok(())
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error(transparent)]
-    Io(#[from] std::io::Error),
     #[error(transparent)]
     Intcode(#[from] intcode::Error),
     #[error("unknown part: {0}")]
@@ -48,3 +87,29 @@ pub enum Error {
     #[error("no solution found")]
     NoSolution,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the input programs when they were available at build time, and the example always.
+    #[test]
+    fn compiled_matches_interpreter() {
+        for compiled in compiled::PROGRAMS.iter().chain([&compiled::EXAMPLE]) {
+            let program = Memory::from(compiled.program);
+            for input in [1, 2] {
+                let mut computer = Computer::new(program.clone());
+                let mut output = Vec::new();
+                compiled
+                    .run(&mut computer, &mut VecDeque::from([input]), &mut output)
+                    .unwrap();
+
+                let mut interpreted = Computer::<0>::new(program.clone());
+                interpreted.provide_input([input]);
+                let interpreted_output = interpreted.collect_outputs::<Vec<_>>().unwrap();
+
+                assert_eq!(output, interpreted_output);
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Rendering a black-and-white picture to the terminal, and to image files.
+
+use std::fmt;
+
+/// A rectangle of pixels, each either lit or dark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major; `true` is lit.
+    pub pixels: Vec<bool>,
+}
+
+/// How many pixels of a PNG each pixel of the image becomes, on each side.
+const PNG_SCALE: usize = 8;
+
+impl Image {
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[y * self.width + x]
+    }
+
+    /// The image as a plain PBM file, with lit pixels in black.
+    pub fn to_pbm(&self) -> String {
+        let mut pbm = format!("P1\n{} {}\n", self.width, self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            let row = row
+                .iter()
+                .map(|lit| if *lit { "1" } else { "0" })
+                .collect::<Vec<_>>();
+            pbm.push_str(&row.join(" "));
+            pbm.push('\n');
+        }
+        pbm
+    }
+
+    /// The image as a greyscale PNG file, with lit pixels in black on a white border.
+    ///
+    /// Each pixel is scaled up so that the result is legible.
+    pub fn to_png(&self) -> Vec<u8> {
+        let width = (self.width + 2) * PNG_SCALE;
+        let height = (self.height + 2) * PNG_SCALE;
+        let mut raw = Vec::with_capacity((width + 1) * height);
+        for y in 0..height {
+            // each scanline begins with its filter type: none
+            raw.push(0);
+            for x in 0..width {
+                let (x, y) = (x / PNG_SCALE, y / PNG_SCALE);
+                let lit = x > 0 && y > 0 && self.get(x - 1, y - 1);
+                raw.push(if lit { 0 } else { 255 });
+            }
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        // bit depth 8, greyscale, default compression and filtering, no interlacing
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+impl fmt::Display for Image {
+    /// Lit pixels as full blocks, two columns wide so that they come out roughly square.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                f.write_str(if self.get(x, y) { "██" } else { "  " })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// `data` as a zlib stream, without compression: the images are tiny.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = u16::MAX as usize;
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(u8::from(last));
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+
+    zlib.extend_from_slice(&adler32(data).to_be_bytes());
+    zlib
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for byte in bytes {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    /// The chunks of `png`, checking each one's CRC.
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        let mut rest = png.strip_prefix(b"\x89PNG\r\n\x1a\n").unwrap();
+        let mut chunks = Vec::new();
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (body, crc) = rest[4..].split_at(4 + len);
+            assert_eq!(crc32(body).to_be_bytes(), crc[..4]);
+            chunks.push((body[..4].try_into().unwrap(), &body[4..]));
+            rest = &crc[4..];
+        }
+        chunks
+    }
+
+    /// Inflate a zlib stream made only of stored blocks, checking its checksum.
+    fn inflate_stored(zlib: &[u8]) -> Vec<u8> {
+        assert_eq!(u16::from_be_bytes([zlib[0], zlib[1]]) % 31, 0);
+        let mut rest = &zlib[2..];
+        let mut data = Vec::new();
+        loop {
+            let header = rest[0];
+            assert_eq!(header >> 1, 0, "only stored blocks are expected");
+            let len = u16::from_le_bytes([rest[1], rest[2]]);
+            let nlen = u16::from_le_bytes([rest[3], rest[4]]);
+            assert_eq!(len, !nlen);
+            let (block, after) = rest[5..].split_at(len.into());
+            data.extend_from_slice(block);
+            rest = after;
+            if header & 1 == 1 {
+                break;
+            }
+        }
+        assert_eq!(rest, adler32(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn png_pixels() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![true, false, false, false, true, true],
+        };
+        let png = image.to_png();
+        let chunks = chunks(&png);
+        let kinds = chunks.iter().map(|(kind, _)| kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+
+        let (width, height) = ((3 + 2) * PNG_SCALE, (2 + 2) * PNG_SCALE);
+        let mut header = Vec::new();
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+        assert_eq!(chunks[0].1, header);
+
+        let raw = inflate_stored(chunks[1].1);
+        let scanlines = raw.chunks(width + 1).collect::<Vec<_>>();
+        assert_eq!(scanlines.len(), height);
+        for (y, scanline) in scanlines.iter().enumerate() {
+            assert_eq!(scanline[0], 0, "filter type of scanline {y}");
+            for (x, grey) in scanline[1..].iter().enumerate() {
+                // the image is surrounded by a border one pixel wide
+                let (x, y) = (x / PNG_SCALE, y / PNG_SCALE);
+                let lit = (1..=3).contains(&x) && (1..=2).contains(&y) && image.get(x - 1, y - 1);
+                assert_eq!(*grey, if lit { 0 } else { 255 }, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn large_images_span_several_blocks() {
+        let data = (0..150_000).map(|n| n as u8).collect::<Vec<_>>();
+        assert_eq!(inflate_stored(&zlib_stored(&data)), data);
+        assert!(inflate_stored(&zlib_stored(&[])).is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+mod image;
+mod ocr;
+
+pub use image::Image;
+pub use ocr::{recognize, render, Unreadable};
+
+use intcode::{Computer, Memory, Word};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+type Point = (i64, i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Color {
+    Black,
+    White,
+}
+
+/// The panels which the robot has painted; any others are black.
+#[derive(Debug, Default)]
+pub struct Hull {
+    panels: HashMap<Point, Color>,
+}
+
+impl Hull {
+    fn color(&self, panel: Point) -> Color {
+        self.panels.get(&panel).copied().unwrap_or(Color::Black)
+    }
+
+    /// How many panels have been painted at least once, in either color.
+    pub fn painted(&self) -> usize {
+        self.panels.len()
+    }
+
+    /// The smallest image holding every white panel, with up towards the top.
+    pub fn image(&self) -> Image {
+        let white = || {
+            self.panels
+                .iter()
+                .filter(|(_, color)| **color == Color::White)
+                .map(|(point, _)| *point)
+        };
+        let (Some(left), Some(top)) = (white().map(|p| p.0).min(), white().map(|p| p.1).min())
+        else {
+            return Image {
+                width: 0,
+                height: 0,
+                pixels: Vec::new(),
+            };
+        };
+        let right = white().map(|p| p.0).max().unwrap_or(left);
+        let bottom = white().map(|p| p.1).max().unwrap_or(top);
+
+        let width = (right - left + 1) as usize;
+        let height = (bottom - top + 1) as usize;
+        let mut pixels = vec![false; width * height];
+        for (x, y) in white() {
+            pixels[(y - top) as usize * width + (x - left) as usize] = true;
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Run the painting robot from a panel of color `start` until its program halts.
+///
+/// The robot starts facing up, with `y` increasing downwards. Whenever the program wants
+/// input, it gets the color under the robot; each pair of outputs paints that panel, then
+/// turns the robot left (`0`) or right (`1`) and moves it forward one panel.
+fn paint(program: &Memory, start: Color) -> Result<Hull, Error> {
+    let mut computer = Computer::<0>::new(program.clone());
+    let mut hull = Hull::default();
+    if start == Color::White {
+        hull.panels.insert((0, 0), start);
+    }
+    let mut position = (0, 0);
+    let mut heading = (0, -1);
+    let mut input = VecDeque::new();
+    let mut output = Vec::new();
+
+    loop {
+        match computer.step_io(&mut input, &mut output) {
+            Ok(()) => {}
+            Err(intcode::Error::Halt(_)) => return Ok(hull),
+            Err(intcode::Error::AwaitingInput) => {
+                input.push_back(match hull.color(position) {
+                    Color::Black => 0,
+                    Color::White => 1,
+                });
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        let [color, turn] = output[..] else {
+            continue;
+        };
+        output.clear();
+        let color = match color {
+            0 => Color::Black,
+            1 => Color::White,
+            _ => return Err(Error::UnknownColor(color)),
+        };
+        hull.panels.insert(position, color);
+        let (dx, dy) = heading;
+        heading = match turn {
+            0 => (dy, -dx),
+            1 => (-dy, dx),
+            _ => return Err(Error::UnknownTurn(turn)),
+        };
+        position = (position.0 + heading.0, position.1 + heading.1);
+    }
+}
+
+/// What the robot painted in part 2.
+///
+/// Displays as the letters it spells.
+pub struct Registration {
+    pub image: Image,
+    pub letters: String,
+}
+
+impl fmt::Display for Registration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.letters)
+    }
+}
+
+pub struct Day11;
+
+impl runner::Day for Day11 {
+    const DAY: u8 = 11;
+
+    type Part1 = usize;
+    type Part2 = Registration;
+    type Error = Error;
+
+    /// How many panels the robot paints, starting on black.
+    fn part1(program: &Memory) -> Result<usize, Error> {
+        paint(program, Color::Black).map(|hull| hull.painted())
+    }
+
+    /// The registration identifier which the robot paints, starting on white.
+    fn part2(program: &Memory) -> Result<Registration, Error> {
+        let image = paint(program, Color::White)?.image();
+        Ok(Registration {
+            letters: recognize(&image)?,
+            image,
+        })
+    }
+
+    /// The painted image as a `png`, a `pbm`, or block art in a `txt` file.
+    fn render(registration: &Registration, extension: &str) -> Option<Vec<u8>> {
+        let image = &registration.image;
+        match extension {
+            "png" => Some(image.to_png()),
+            "pbm" => Some(image.to_pbm().into_bytes()),
+            "txt" => Some(image.to_string().into_bytes()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Intcode(#[from] intcode::Error),
+    #[error("robot painted unknown color {0}")]
+    UnknownColor(Word),
+    #[error("robot made unknown turn {0}")]
+    UnknownTurn(Word),
+    #[error("cannot read the registration identifier: {0}")]
+    Unreadable(#[from] Unreadable),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A program which ignores its input and makes the moves of the puzzle's example.
+    fn scripted(moves: &[(Word, Word)]) -> Memory {
+        let mut source = String::new();
+        for (color, turn) in moves {
+            source.push_str(&format!("in [seen]\nout #{color}\nout #{turn}\n"));
+        }
+        source.push_str("halt\nseen: data 0\n");
+        intcode::assemble(&source).unwrap()
+    }
+
+    #[test]
+    fn example() {
+        let moves = [(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)];
+        let hull = paint(&scripted(&moves), Color::Black).unwrap();
+        assert_eq!(hull.painted(), 6);
+        assert_eq!(hull.image().to_string(), "    ██\n    ██\n████  \n");
+        assert_eq!(hull.image().to_pbm(), "P1\n3 3\n0 0 1\n0 0 1\n1 1 0\n");
+    }
+
+    #[test]
+    fn recognize_rendered_letters() {
+        let alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+        let image = render(alphabet).unwrap();
+        assert_eq!(recognize(&image).unwrap(), alphabet);
+        assert_eq!(render("a"), None);
+    }
+
+    #[test]
+    fn recognize_names_unknown_glyphs() {
+        let mut image = render("HI").unwrap();
+        let blank = Image {
+            pixels: vec![false; image.pixels.len()],
+            ..image.clone()
+        };
+        assert!(matches!(recognize(&blank), Err(Unreadable::Blank)));
+
+        // give the H a second crossbar
+        image.pixels[image.width + 1] = true;
+        image.pixels[image.width + 2] = true;
+        let Err(Unreadable::UnknownGlyph { position, glyph }) = recognize(&image) else {
+            panic!("the glyph should not be recognized");
+        };
+        assert_eq!(position, 1);
+        assert_eq!(
+            glyph.to_string(),
+            "██    ██\n████████\n████████\n██    ██\n██    ██\n██    ██\n"
+        );
+    }
+}
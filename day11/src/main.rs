@@ -0,0 +1 @@
+runner::main!(day11::Day11);
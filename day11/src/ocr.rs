@@ -0,0 +1,145 @@
+//! Reading letters in the font which the puzzles draw them in.
+
+use crate::image::Image;
+
+const HEIGHT: usize = 6;
+
+/// Each capital letter, mostly four pixels wide.
+///
+/// The puzzles have only been seen to draw some of these; the rest are drawn in the same style.
+const FONT: &[(char, [&str; HEIGHT])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('D', ["###.", "#..#", "#..#", "#..#", "#..#", "###."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('M', ["#...#", "##.##", "#.#.#", "#...#", "#...#", "#...#"]),
+    ('N', ["#..#", "##.#", "#.##", "#..#", "#..#", "#..#"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('Q', [".##.", "#..#", "#..#", "#..#", "#.#.", ".#.#"]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('T', ["#####", "..#..", "..#..", "..#..", "..#..", "..#.."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('V', ["#...#", "#...#", "#...#", ".#.#.", ".#.#.", "..#.."]),
+    ('W', ["#...#", "#...#", "#...#", "#.#.#", "##.##", "#...#"]),
+    ('X', ["#...#", ".#.#.", "..#..", "..#..", ".#.#.", "#...#"]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// Why [`recognize`] could not read an image.
+#[derive(Debug, thiserror::Error)]
+pub enum Unreadable {
+    #[error("nothing is drawn")]
+    Blank,
+    #[error("the letters are more than {HEIGHT} pixels tall")]
+    TooTall,
+    /// `position` counts letters from 1, at the left.
+    #[error("letter {position} is not in the font:\n{glyph}")]
+    UnknownGlyph { position: usize, glyph: Image },
+}
+
+/// The columns of a glyph, each as a bitmask with the top row in the lowest bit,
+/// without any blank columns at either side.
+fn columns(column: impl Fn(usize) -> u8, width: usize) -> Vec<u8> {
+    let columns = (0..width).map(column).collect::<Vec<_>>();
+    let Some(first) = columns.iter().position(|column| *column != 0) else {
+        return Vec::new();
+    };
+    let last = columns.iter().rposition(|column| *column != 0).unwrap();
+    columns[first..=last].to_vec()
+}
+
+fn glyph_columns(rows: &[&str; HEIGHT]) -> Vec<u8> {
+    let column = |x: usize| {
+        rows.iter()
+            .enumerate()
+            .filter(|(_, row)| row.as_bytes()[x] == b'#')
+            .fold(0, |mask, (y, _)| mask | 1 << y)
+    };
+    columns(column, rows[0].len())
+}
+
+/// Read the letters in `image`, which must be one line of the puzzles' font.
+///
+/// Letters are separated by blank columns; blank rows and columns around them are ignored.
+pub fn recognize(image: &Image) -> Result<String, Unreadable> {
+    let top = (0..image.height)
+        .find(|y| (0..image.width).any(|x| image.get(x, *y)))
+        .ok_or(Unreadable::Blank)?;
+    if (top + HEIGHT..image.height).any(|y| (0..image.width).any(|x| image.get(x, y))) {
+        return Err(Unreadable::TooTall);
+    }
+    let column = |x: usize| {
+        (0..HEIGHT)
+            .filter(|y| image.get(x, top + y))
+            .fold(0, |mask, y| mask | 1 << y)
+    };
+    let columns = columns(column, image.width);
+
+    columns
+        .split(|column| *column == 0)
+        .filter(|glyph| !glyph.is_empty())
+        .enumerate()
+        .map(|(idx, glyph)| {
+            FONT.iter()
+                .find(|(_, rows)| glyph_columns(rows) == glyph)
+                .map(|(letter, _)| *letter)
+                .ok_or_else(|| Unreadable::UnknownGlyph {
+                    position: idx + 1,
+                    glyph: glyph_image(glyph),
+                })
+        })
+        .collect()
+}
+
+/// A glyph, given as its columns, as an image of its own.
+fn glyph_image(columns: &[u8]) -> Image {
+    let width = columns.len();
+    let mut pixels = vec![false; width * HEIGHT];
+    for (x, column) in columns.iter().enumerate() {
+        for y in 0..HEIGHT {
+            pixels[y * width + x] = column & 1 << y != 0;
+        }
+    }
+    Image {
+        width,
+        height: HEIGHT,
+        pixels,
+    }
+}
+
+/// Draw `text` in the puzzles' font, with a blank column after each letter.
+///
+/// Returns `None` if any letter is not in the font.
+pub fn render(text: &str) -> Option<Image> {
+    let glyphs = text
+        .chars()
+        .map(|letter| FONT.iter().find(|(known, _)| *known == letter))
+        .collect::<Option<Vec<_>>>()?;
+    let width = glyphs.iter().map(|(_, rows)| rows[0].len() + 1).sum();
+    let mut pixels = vec![false; width * HEIGHT];
+    let mut left = 0;
+    for (_, rows) in glyphs {
+        for (y, row) in rows.iter().enumerate() {
+            for (x, pixel) in row.bytes().enumerate() {
+                pixels[y * width + left + x] = pixel == b'#';
+            }
+        }
+        left += rows[0].len() + 1;
+    }
+    Some(Image {
+        width,
+        height: HEIGHT,
+        pixels,
+    })
+}
@@ -0,0 +1,134 @@
+use intcode::{Device, Memory, Network, Packet, Word};
+use std::ops::ControlFlow;
+
+const N_COMPUTERS: usize = 50;
+const NAT_ADDR: Word = 255;
+
+/// Stops the network as soon as anything is sent to the NAT.
+#[derive(Default)]
+struct FirstPacket(Option<Packet>);
+
+impl Device for FirstPacket {
+    fn receive(&mut self, packet: Packet, _send: &mut Vec<Packet>) -> ControlFlow<()> {
+        self.0 = Some(packet);
+        ControlFlow::Break(())
+    }
+}
+
+/// Remembers the last packet it received, and resends it to address 0 when the network is idle.
+///
+/// Stops the network when it would send the same `y` value twice in a row.
+#[derive(Default)]
+struct Nat {
+    last_received: Option<Packet>,
+    last_sent_y: Option<Word>,
+}
+
+impl Device for Nat {
+    fn receive(&mut self, packet: Packet, _send: &mut Vec<Packet>) -> ControlFlow<()> {
+        self.last_received = Some(packet);
+        ControlFlow::Continue(())
+    }
+
+    fn idle(&mut self, send: &mut Vec<Packet>) -> ControlFlow<()> {
+        let Some(packet) = self.last_received else {
+            return ControlFlow::Continue(());
+        };
+        if self.last_sent_y == Some(packet.y) {
+            return ControlFlow::Break(());
+        }
+        self.last_sent_y = Some(packet.y);
+        send.push(Packet { addr: 0, ..packet });
+        ControlFlow::Continue(())
+    }
+}
+
+fn first_nat_y(program: &Memory) -> Result<Word, Error> {
+    let mut nat = FirstPacket::default();
+    let mut network = Network::new(program, N_COMPUTERS);
+    network.attach(NAT_ADDR, &mut nat);
+    network.run()?;
+    nat.0.map(|packet| packet.y).ok_or(Error::NoSolution)
+}
+
+fn repeated_nat_y(program: &Memory) -> Result<Word, Error> {
+    let mut nat = Nat::default();
+    let mut network = Network::new(program, N_COMPUTERS);
+    network.attach(NAT_ADDR, &mut nat);
+    network.run()?;
+    nat.last_sent_y.ok_or(Error::NoSolution)
+}
+
+pub struct Day23;
+
+impl runner::Day for Day23 {
+    const DAY: u8 = 23;
+
+    type Part1 = Word;
+    type Part2 = Word;
+    type Error = Error;
+
+    /// The first `y` value sent to the NAT.
+    fn part1(program: &Memory) -> Result<Word, Error> {
+        first_nat_y(program)
+    }
+
+    /// The first `y` value delivered by the NAT twice in a row.
+    fn part2(program: &Memory) -> Result<Word, Error> {
+        repeated_nat_y(program)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Intcode(#[from] intcode::Error),
+    #[error("no solution found")]
+    NoSolution,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Computer 0 sends `(255, 0, 1)`; then, for each packet `(x, y)` it receives, it sends
+    /// `(255, x + 1, y + 1)` until `y` reaches 3. The other computers only poll.
+    fn counter() -> Memory {
+        intcode::assemble(
+            "
+                    in [addr]
+                    jt [addr], #poll
+                    out #255
+                    out #0
+                    out #1
+            poll:   in [x]
+                    eq [x], #-1, [flag]
+                    jt [flag], #poll
+                    in [y]
+                    add [x], #1, [x]
+                    lt [y], #3, [flag]
+                    add [y], [flag], [y]
+                    out #255
+                    out [x]
+                    out [y]
+                    jt #1, #poll
+            addr:   data 0
+            x:      data 0
+            y:      data 0
+            flag:   data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn first_packet_to_nat() {
+        assert_eq!(first_nat_y(&counter()).unwrap(), 1);
+    }
+
+    #[test]
+    fn nat_stops_on_repeated_y() {
+        // the NAT delivers y = 1, 2, 3, and then would deliver 3 again
+        assert_eq!(repeated_nat_y(&counter()).unwrap(), 3);
+    }
+}
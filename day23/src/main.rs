@@ -0,0 +1 @@
+runner::main!(day23::Day23);
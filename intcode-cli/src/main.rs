@@ -0,0 +1,522 @@
+use color_eyre::eyre::{eyre, Result};
+use intcode::{
+    annotated_listing, assemble_object, assemble_with_source_map, decode, decompile::decompile,
+    disassemble, link::Object, Computer, Coverage, Error, Memory, Opcode, SourceMap, Word,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::File,
+    io::{BufRead, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+
+/// Run, inspect, and disassemble Intcode programs.
+///
+/// Exit codes: 0 when the program halts; 3 when it faults; 4 when it wants input
+/// but none is left; 5 when it runs out of fuel; 6 when it runs out of time;
+/// 1 on any other error.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "intcode")]
+enum Command {
+    /// Run a program.
+    ///
+    /// Inputs come first from `--input`, then from lines of stdin.
+    Run {
+        #[structopt(flatten)]
+        program: ProgramArgs,
+        #[structopt(flatten)]
+        exec: ExecArgs,
+        /// treat input lines and output words as ASCII text
+        #[structopt(long)]
+        ascii: bool,
+        /// write a JSON record of every executed instruction to this file
+        #[structopt(long, parse(from_os_str))]
+        trace: Option<PathBuf>,
+        /// add the coverage of this run to the counts in this file, creating it if need be
+        #[structopt(long, parse(from_os_str))]
+        coverage: Option<PathBuf>,
+    },
+    /// Print a disassembly listing of a program.
+    Disasm {
+        #[structopt(flatten)]
+        program: ProgramArgs,
+    },
+    /// Assemble a source file, printing the program.
+    Asm {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// print a relocatable object for `link` instead
+        #[structopt(long)]
+        object: bool,
+        /// write where each address came from to this file
+        #[structopt(long, parse(from_os_str), conflicts_with = "object")]
+        source_map: Option<PathBuf>,
+    },
+    /// Link modules into a program, printing it.
+    ///
+    /// Execution begins with the first module.
+    Link {
+        /// modules in the order to lay them out: objects ending in `.o`, or assembly source
+        #[structopt(parse(from_os_str), required = true)]
+        modules: Vec<PathBuf>,
+        /// write where each module and symbol was placed to this file
+        #[structopt(long, parse(from_os_str))]
+        map: Option<PathBuf>,
+        /// write where each address came from to this file
+        #[structopt(long, parse(from_os_str))]
+        source_map: Option<PathBuf>,
+    },
+    /// Print a program as structured pseudocode.
+    Decompile {
+        #[structopt(flatten)]
+        program: ProgramArgs,
+    },
+    /// Print the coverage recorded by `run --coverage`, as an annotated disassembly.
+    Coverage {
+        #[structopt(flatten)]
+        program: ProgramArgs,
+        /// coverage file written by `run --coverage`
+        #[structopt(parse(from_os_str))]
+        data: PathBuf,
+        /// print an lcov tracefile instead; without a source map, its lines are those of `disasm`
+        #[structopt(long)]
+        lcov: bool,
+    },
+    /// Run a program, then report statistics about its execution.
+    ///
+    /// This includes every address which was both executed and written.
+    Stats {
+        #[structopt(flatten)]
+        program: ProgramArgs,
+        #[structopt(flatten)]
+        exec: ExecArgs,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+struct ProgramArgs {
+    /// file containing comma-separated programs, one per line
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+
+    /// which program in the file to use
+    #[structopt(long, default_value = "0")]
+    index: usize,
+
+    /// source map written by `asm` or `link`, to describe addresses by their source
+    #[structopt(long, parse(from_os_str))]
+    source_map: Option<PathBuf>,
+}
+
+impl ProgramArgs {
+    fn load(&self) -> Result<Memory> {
+        Memory::from_path(&self.path)?
+            .into_iter()
+            .nth(self.index)
+            .ok_or_else(|| eyre!("{} has no program {}", self.path.display(), self.index))
+    }
+
+    fn load_source_map(&self) -> Result<Option<SourceMap>> {
+        self.source_map
+            .as_ref()
+            .map(|path| Ok(std::fs::read_to_string(path)?.parse()?))
+            .transpose()
+    }
+
+    /// A computer for the program, which describes faults by the source map if there is one.
+    fn computer(&self) -> Result<Computer> {
+        let mut computer = Computer::new(self.load()?);
+        if let Some(map) = self.load_source_map()? {
+            computer.set_source_map(map);
+        }
+        Ok(computer)
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct ExecArgs {
+    /// comma-separated inputs
+    #[structopt(long, require_delimiter = true, allow_hyphen_values = true)]
+    input: Vec<Word>,
+
+    /// stop after executing this many instructions
+    #[structopt(long)]
+    fuel: Option<usize>,
+
+    /// stop after running for this many seconds; time spent waiting for stdin is included,
+    /// but is not cut short
+    #[structopt(long)]
+    timeout: Option<f64>,
+}
+
+/// How a run ended.
+#[derive(Debug)]
+enum Outcome {
+    Halted,
+    Fault(Error),
+    InputExhausted,
+    FuelExhausted,
+    TimedOut,
+}
+
+impl Outcome {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Halted => 0,
+            Outcome::Fault(_) => 3,
+            Outcome::InputExhausted => 4,
+            Outcome::FuelExhausted => 5,
+            Outcome::TimedOut => 6,
+        }
+    }
+}
+
+/// What happened during a single instruction.
+///
+/// `step` counts the instructions which ran before this one.
+#[derive(Debug, serde::Serialize)]
+struct Step {
+    step: usize,
+    ip: usize,
+    instruction: Option<String>,
+    relative_base: Word,
+    input: Option<Word>,
+    output: Option<Word>,
+    /// where the instruction came from, if the program has a source map
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// why the instruction faulted, if it did; it then had no effect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fault: Option<String>,
+}
+
+/// Run `computer` until it stops.
+///
+/// `refill` is called when the computer wants input and none is queued; it returns `false`
+/// if no more input is available. `on_step` is called after every instruction, with any
+/// outputs that instruction produced, and for an instruction which faults.
+///
+/// Only instructions which run count towards `--fuel`: not waiting for input, nor a fault.
+fn execute(
+    computer: &mut Computer,
+    exec: &ExecArgs,
+    mut refill: impl FnMut(&mut VecDeque<Word>) -> Result<bool>,
+    mut on_step: impl FnMut(&Computer, &Step) -> Result<()>,
+) -> Result<Outcome> {
+    let mut input = VecDeque::from(exec.input.clone());
+    let mut output = Vec::new();
+    let deadline = exec
+        .timeout
+        .map(|secs| Instant::now() + Duration::from_secs_f64(secs));
+
+    let mut step = 0;
+    loop {
+        if exec.fuel.is_some_and(|fuel| step >= fuel) {
+            return Ok(Outcome::FuelExhausted);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(Outcome::TimedOut);
+        }
+
+        let ip = computer.instruction_pointer();
+        let instruction = decode(computer.memory(), ip).map(|decoded| decoded.to_string());
+        let next_input = input.front().copied();
+        let input_len = input.len();
+
+        let result = computer.step_io(&mut input, &mut output);
+        if matches!(result, Err(Error::AwaitingInput)) {
+            if refill(&mut input)? {
+                continue;
+            }
+            return Ok(Outcome::InputExhausted);
+        }
+        let fault = result
+            .as_ref()
+            .err()
+            .filter(|err| !matches!(err, Error::Halt(_)));
+
+        let record = Step {
+            step,
+            ip,
+            instruction,
+            relative_base: computer.relative_base(),
+            input: next_input.filter(|_| input.len() < input_len),
+            output: output.pop(),
+            source: computer.source_map().and_then(|map| map.annotation(ip)),
+            fault: fault.map(|err| err.to_string()),
+        };
+        on_step(computer, &record)?;
+
+        match result {
+            Ok(()) => step += 1,
+            Err(Error::Halt(_)) => return Ok(Outcome::Halted),
+            Err(err) => return Ok(Outcome::Fault(err)),
+        }
+    }
+}
+
+/// Read a line of stdin as input, either as comma-separated words or as ASCII text.
+fn read_stdin(input: &mut VecDeque<Word>, ascii: bool) -> Result<bool> {
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(false);
+    }
+    let line = line.trim_end_matches(['\r', '\n']);
+    if ascii {
+        if let Some(ch) = line.chars().find(|ch| !ch.is_ascii()) {
+            return Err(Error::NonAscii(ch).into());
+        }
+        input.extend(line.bytes().map(Word::from));
+        input.push_back(b'\n'.into());
+    } else {
+        for token in line.split(',').filter(|token| !token.trim().is_empty()) {
+            input.push_back(token.trim().parse()?);
+        }
+    }
+    Ok(true)
+}
+
+fn run(
+    mut computer: Computer,
+    exec: &ExecArgs,
+    ascii: bool,
+    trace: Option<PathBuf>,
+    coverage: Option<PathBuf>,
+) -> Result<Outcome> {
+    if coverage.is_some() {
+        computer.record_coverage();
+    }
+    let mut trace = trace
+        .map(|path| File::create(path).map(BufWriter::new))
+        .transpose()?;
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+
+    let outcome = execute(
+        &mut computer,
+        exec,
+        |input| {
+            // stdout is reentrant, so this does not contend with the lock held for output
+            std::io::stdout().flush()?;
+            read_stdin(input, ascii)
+        },
+        |_, step| {
+            if let Some(trace) = trace.as_mut() {
+                serde_json::to_writer(&mut *trace, step)?;
+                writeln!(trace)?;
+            }
+            match step.output {
+                Some(word) if ascii && (0..128).contains(&word) => {
+                    write!(stdout, "{}", char::from(word as u8))?
+                }
+                Some(word) if ascii => writeln!(stdout, "answer: {word}")?,
+                Some(word) => writeln!(stdout, "{word}")?,
+                None => {}
+            }
+            Ok(())
+        },
+    )?;
+
+    stdout.flush()?;
+    if let Some(mut trace) = trace {
+        trace.flush()?;
+    }
+    if let (Some(path), Some(recorded)) = (coverage, computer.coverage()) {
+        let mut total = match std::fs::read_to_string(&path) {
+            Ok(text) => text.parse()?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Coverage::default(),
+            Err(err) => return Err(err.into()),
+        };
+        total.merge(recorded);
+        std::fs::write(path, total.to_string())?;
+    }
+    Ok(outcome)
+}
+
+fn coverage(program: &ProgramArgs, data: &Path, lcov: bool) -> Result<()> {
+    let memory = program.load()?;
+    let coverage: Coverage = std::fs::read_to_string(data)?.parse()?;
+    if lcov {
+        let listing = format!("{}.disasm", program.path.display());
+        let map = program.load_source_map()?;
+        print!("{}", coverage.lcov(&memory, map.as_ref(), &listing));
+    } else {
+        for line in coverage.listing(&memory) {
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+fn disasm(program: Memory, source_map: Option<SourceMap>) {
+    match source_map {
+        Some(map) => {
+            for line in annotated_listing(&program, &map) {
+                println!("{line}");
+            }
+        }
+        None => {
+            for line in disassemble(&program) {
+                println!("{line}");
+            }
+        }
+    }
+}
+
+/// The name of the module in the file at `path`.
+fn module_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn print_program(program: &Memory) {
+    let words = program
+        .iter()
+        .map(|word| word.to_string())
+        .collect::<Vec<_>>();
+    println!("{}", words.join(","));
+}
+
+fn asm(path: &Path, object: bool, source_map: Option<PathBuf>) -> Result<()> {
+    let source = std::fs::read_to_string(path)?;
+    if object {
+        print!("{}", assemble_object(&module_name(path), &source)?);
+        return Ok(());
+    }
+    let (program, map) = assemble_with_source_map(&path.display().to_string(), &source)?;
+    if let Some(map_path) = source_map {
+        std::fs::write(map_path, map.to_string())?;
+    }
+    print_program(&program);
+    Ok(())
+}
+
+fn link(modules: &[PathBuf], map: Option<PathBuf>, source_map: Option<PathBuf>) -> Result<()> {
+    let objects = modules
+        .iter()
+        .map(|path| {
+            let text = std::fs::read_to_string(path)?;
+            if path.extension().is_some_and(|ext| ext == "o") {
+                Ok(text.parse()?)
+            } else {
+                Ok(assemble_object(&module_name(path), &text)?)
+            }
+        })
+        .collect::<Result<Vec<Object>>>()?;
+    let linked = intcode::link::link(&objects)?;
+    if let Some(path) = map {
+        std::fs::write(path, linked.map.to_string())?;
+    }
+    if let Some(path) = source_map {
+        std::fs::write(path, linked.source_map.to_string())?;
+    }
+    print_program(&linked.program);
+    Ok(())
+}
+
+fn stats(mut computer: Computer, exec: &ExecArgs) -> Result<Outcome> {
+    let size = computer.memory().len();
+    computer.track_self_modification();
+    let mut opcodes = BTreeMap::<Opcode, usize>::new();
+    let mut peak_memory = size;
+    let mut steps = 0;
+    let mut inputs = 0;
+    let mut outputs = 0;
+
+    let outcome = execute(
+        &mut computer,
+        exec,
+        |_| Ok(false),
+        |computer, step| {
+            if step.fault.is_some() {
+                return Ok(());
+            }
+            steps += 1;
+            inputs += usize::from(step.input.is_some());
+            outputs += usize::from(step.output.is_some());
+            peak_memory = peak_memory.max(computer.memory().len());
+            if let Some(decoded) = decode(computer.memory(), step.ip) {
+                *opcodes.entry(decoded.opcode).or_default() += 1;
+            }
+            Ok(())
+        },
+    )?;
+
+    println!("outcome:      {outcome:?}");
+    println!("program size: {size}");
+    println!("peak memory:  {peak_memory}");
+    println!("steps:        {steps}");
+    println!("inputs read:  {inputs}");
+    println!("outputs:      {outputs}");
+    for (opcode, count) in opcodes {
+        println!("  {:<5} {count}", opcode.mnemonic());
+    }
+    let modified = computer.self_modification();
+    println!("self-modified: {}", modified.len());
+    for addr in modified {
+        match computer.source_map() {
+            Some(map) => println!("  {}", map.describe(addr)),
+            None => println!("  {addr}"),
+        }
+    }
+    Ok(outcome)
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let outcome = match Command::from_args() {
+        Command::Run {
+            program,
+            exec,
+            ascii,
+            trace,
+            coverage,
+        } => run(program.computer()?, &exec, ascii, trace, coverage)?,
+        Command::Disasm { program } => {
+            disasm(program.load()?, program.load_source_map()?);
+            Outcome::Halted
+        }
+        Command::Asm {
+            path,
+            object,
+            source_map,
+        } => {
+            asm(&path, object, source_map)?;
+            Outcome::Halted
+        }
+        Command::Link {
+            modules,
+            map,
+            source_map,
+        } => {
+            link(&modules, map, source_map)?;
+            Outcome::Halted
+        }
+        Command::Decompile { program } => {
+            print!("{}", decompile(&program.load()?));
+            Outcome::Halted
+        }
+        Command::Coverage {
+            program,
+            data,
+            lcov,
+        } => {
+            coverage(&program, &data, lcov)?;
+            Outcome::Halted
+        }
+        Command::Stats { program, exec } => stats(program.computer()?, &exec)?,
+    };
+
+    match &outcome {
+        Outcome::Halted => {}
+        Outcome::Fault(err) => eprintln!("fault: {err}"),
+        Outcome::InputExhausted => eprintln!("program wants input, but none is left"),
+        Outcome::FuelExhausted => eprintln!("out of fuel"),
+        Outcome::TimedOut => eprintln!("out of time"),
+    }
+    std::process::exit(outcome.exit_code())
+}
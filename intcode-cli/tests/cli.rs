@@ -0,0 +1,199 @@
+//! Tests of the `intcode` binary: its exit codes, and the formats of what it writes.
+//!
+//! Each test writes its program to a file of its own under the target directory, and runs
+//! the binary with nothing on stdin.
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+/// Write `program` to a file named for `name`, returning its path.
+fn program_file(name: &str, program: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.txt"));
+    fs::write(&path, program).unwrap();
+    path
+}
+
+/// Run the binary with `args`, where `{}` stands for the program file.
+fn intcode(name: &str, program: &str, args: &[&str]) -> Output {
+    intcode_with_stdin(name, program, args, "")
+}
+
+/// Run the binary with `args`, as [`intcode`] does, writing `stdin` to its standard input.
+fn intcode_with_stdin(name: &str, program: &str, args: &[&str], stdin: &str) -> Output {
+    let path = program_file(name, program);
+    let args = args.iter().map(|arg| {
+        if *arg == "{}" {
+            path.as_os_str()
+        } else {
+            arg.as_ref()
+        }
+    });
+    let mut child = Command::new(env!("CARGO_BIN_EXE_intcode"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+/// Double the input, output it, and halt.
+const DOUBLE: &str = "3,9,1002,9,2,9,4,9,99,0";
+
+/// Jump to itself forever.
+const SPIN: &str = "1105,1,0";
+
+#[test]
+fn halt_exits_0() {
+    let output = intcode("halt", DOUBLE, &["run", "{}", "--input", "21"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "42\n");
+}
+
+#[test]
+fn fault_exits_3() {
+    let output = intcode("fault", "98", &["run", "{}"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(
+        stderr(&output).starts_with("fault: "),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn input_exhausted_exits_4() {
+    let output = intcode("input_exhausted", DOUBLE, &["run", "{}"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn fuel_exhausted_exits_5() {
+    let output = intcode("fuel_exhausted", SPIN, &["run", "{}", "--fuel", "100"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(stderr(&output), "out of fuel\n");
+}
+
+#[test]
+fn reading_stdin_uses_no_fuel() {
+    let echo = "3,0,4,0,99";
+    let trace = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("stdin_trace.jsonl");
+    let args = [
+        "run",
+        "{}",
+        "--fuel",
+        "3",
+        "--trace",
+        trace.to_str().unwrap(),
+    ];
+    let output = intcode_with_stdin("stdin_fuel", echo, &args, "5\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "5\n");
+
+    let trace = fs::read_to_string(trace).unwrap();
+    let steps = trace
+        .lines()
+        .map(|line| line.split(',').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(steps, [r#"{"step":0"#, r#"{"step":1"#, r#"{"step":2"#]);
+}
+
+#[test]
+fn faults_are_traced() {
+    let trace = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fault_trace.jsonl");
+    let output = intcode(
+        "fault_trace",
+        "1101,1,1,5,98",
+        &["run", "{}", "--trace", trace.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(3));
+
+    let trace = fs::read_to_string(trace).unwrap();
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            r#"{"step":0,"ip":0,"instruction":"add #1, #1, [5]","relative_base":0,"input":null,"output":null}"#,
+            r#"{"step":1,"ip":4,"instruction":null,"relative_base":0,"input":null,"output":null,"fault":"unknown opcode: `98`"}"#,
+        ]
+    );
+}
+
+#[test]
+fn timeout_exits_6() {
+    let output = intcode("timeout", SPIN, &["run", "{}", "--timeout", "0.1"]);
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(stderr(&output), "out of time\n");
+}
+
+#[test]
+fn trace_records_each_instruction() {
+    let trace = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("trace.jsonl");
+    let output = intcode(
+        "trace",
+        DOUBLE,
+        &[
+            "run",
+            "{}",
+            "--input",
+            "21",
+            "--trace",
+            trace.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+
+    let trace = fs::read_to_string(trace).unwrap();
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            r#"{"step":0,"ip":0,"instruction":"in [9]","relative_base":0,"input":21,"output":null}"#,
+            r#"{"step":1,"ip":2,"instruction":"mul [9], #2, [9]","relative_base":0,"input":null,"output":null}"#,
+            r#"{"step":2,"ip":6,"instruction":"out [9]","relative_base":0,"input":null,"output":42}"#,
+            r#"{"step":3,"ip":8,"instruction":"halt","relative_base":0,"input":null,"output":null}"#,
+        ]
+    );
+}
+
+#[test]
+fn stats_format() {
+    let output = intcode("stats", DOUBLE, &["stats", "{}", "--input", "21"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "\
+outcome:      Halted
+program size: 10
+peak memory:  10
+steps:        4
+inputs read:  1
+outputs:      1
+  mul   1
+  in    1
+  out   1
+  halt  1
+self-modified: 0
+"
+    );
+}
@@ -0,0 +1,505 @@
+use std::{collections::HashMap, fmt, fmt::Write as _};
+
+use intcode::Word;
+
+use crate::{
+    parser::{BinaryOp, Expr, Function, Program, Stmt, UnaryOp},
+    Error, Result,
+};
+
+/// The cell through which every function returns its result.
+const RETURN_VALUE: &str = ".return";
+
+/// The first frame, `main`'s, starts just past the end of the program.
+const STACK: &str = ".stack";
+
+const BUILTINS: &[&str] = &["input", "output"];
+
+// User names never contain `.`, so these prefixes keep them apart from each other
+// and from the compiler's own labels.
+
+fn function_label(name: &str) -> String {
+    format!("fn.{name}")
+}
+
+fn global_label(name: &str) -> String {
+    format!("global.{name}")
+}
+
+/// Where a value lives, as an assembly operand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Immediate(Word),
+    /// `[rb+slot]` in the current frame.
+    Slot(usize),
+    Global(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Immediate(value) => write!(f, "#{value}"),
+            Value::Slot(slot) => write!(f, "[rb+{slot}]"),
+            Value::Global(name) => write!(f, "[{}]", global_label(name)),
+        }
+    }
+}
+
+fn semantic(line: usize, message: String) -> Error {
+    Error::Semantic { line, message }
+}
+
+fn apply_unary(op: UnaryOp, value: Word) -> Word {
+    match op {
+        UnaryOp::Negate => value.wrapping_neg(),
+        UnaryOp::Not => Word::from(value == 0),
+    }
+}
+
+fn apply_binary(op: BinaryOp, a: Word, b: Word) -> Word {
+    match op {
+        BinaryOp::Or => Word::from(a != 0 || b != 0),
+        BinaryOp::And => Word::from(a != 0 && b != 0),
+        BinaryOp::Eq => Word::from(a == b),
+        BinaryOp::Ne => Word::from(a != b),
+        BinaryOp::Lt => Word::from(a < b),
+        BinaryOp::Le => Word::from(a <= b),
+        BinaryOp::Gt => Word::from(a > b),
+        BinaryOp::Ge => Word::from(a >= b),
+        BinaryOp::Add => a.wrapping_add(b),
+        BinaryOp::Sub => a.wrapping_sub(b),
+        BinaryOp::Mul => a.wrapping_mul(b),
+    }
+}
+
+/// The value of `expr`, if it can be computed at compile time.
+fn constant(expr: &Expr) -> Option<Word> {
+    match expr {
+        Expr::Number(value) => Some(*value),
+        Expr::Unary(op, operand) => Some(apply_unary(*op, constant(operand)?)),
+        Expr::Binary(op, lhs, rhs) => Some(apply_binary(*op, constant(lhs)?, constant(rhs)?)),
+        Expr::Var { .. } | Expr::Call { .. } => None,
+    }
+}
+
+/// Whether evaluating `expr` might call a function, which might assign to a global.
+fn has_call(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) | Expr::Var { .. } => false,
+        Expr::Call { .. } => true,
+        Expr::Unary(_, operand) => has_call(operand),
+        Expr::Binary(_, lhs, rhs) => has_call(lhs) || has_call(rhs),
+    }
+}
+
+/// Assembly output, with one instruction or label per line.
+#[derive(Default)]
+struct Assembly {
+    text: String,
+}
+
+impl Assembly {
+    fn emit(&mut self, instruction: fmt::Arguments<'_>) {
+        writeln!(self.text, "        {instruction}").unwrap();
+    }
+
+    fn label(&mut self, label: &str) {
+        writeln!(self.text, "{label}:").unwrap();
+    }
+
+    fn line(&mut self, line: fmt::Arguments<'_>) {
+        writeln!(self.text, "{line}").unwrap();
+    }
+}
+
+/// Generates the code for one function.
+struct FunctionGen<'a> {
+    name: &'a str,
+    globals: &'a HashMap<&'a str, Word>,
+    functions: &'a HashMap<&'a str, usize>,
+    asm: &'a mut Assembly,
+    /// Local variables in each enclosing block, innermost last.
+    scopes: Vec<Vec<(&'a str, usize)>>,
+    /// The lowest slot not holding a parameter, local, or live temporary.
+    next_slot: usize,
+    /// The number of slots the frame needs: the most ever live at once.
+    frame_size: usize,
+    next_label: usize,
+}
+
+impl<'a> FunctionGen<'a> {
+    /// The assembler constant holding this function's frame size.
+    fn frame(&self) -> String {
+        format!("{}.frame", function_label(self.name))
+    }
+
+    fn new_label(&mut self) -> String {
+        self.next_label += 1;
+        format!("{}.{}", function_label(self.name), self.next_label)
+    }
+
+    fn alloc(&mut self) -> usize {
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        self.frame_size = self.frame_size.max(self.next_slot);
+        slot
+    }
+
+    fn lookup(&self, name: &str) -> Option<Value> {
+        self.scopes
+            .iter()
+            .rev()
+            .flatten()
+            .find(|(local, _)| *local == name)
+            .map(|(_, slot)| Value::Slot(*slot))
+            .or_else(|| {
+                self.globals
+                    .contains_key(name)
+                    .then(|| Value::Global(name.to_owned()))
+            })
+    }
+
+    fn copy(&mut self, from: &Value, to: &Value) {
+        if from != to {
+            self.asm.emit(format_args!("add {from}, #0, {to}"));
+        }
+    }
+
+    /// A value which will still hold its contents after `later` has been evaluated.
+    ///
+    /// Only globals need care: a function called while evaluating `later` might assign to them.
+    fn stable(&mut self, value: Value, later: &[Expr]) -> Value {
+        if matches!(value, Value::Global(_)) && later.iter().any(has_call) {
+            let temp = Value::Slot(self.alloc());
+            self.copy(&value, &temp);
+            temp
+        } else {
+            value
+        }
+    }
+
+    fn function(&mut self, function: &'a Function) -> Result<()> {
+        self.asm.line(format_args!(
+            "\n; fn {}({})",
+            function.name,
+            function.params.join(", ")
+        ));
+        self.asm.label(&function_label(self.name));
+        self.scopes.push(Vec::new());
+        for param in &function.params {
+            if self.scopes[0].iter().any(|(name, _)| name == param) {
+                return Err(semantic(
+                    function.line,
+                    format!("parameter `{param}` is declared more than once"),
+                ));
+            }
+            let slot = self.alloc();
+            self.scopes[0].push((param, slot));
+        }
+        self.block(&function.body)?;
+        self.ret(&Value::Immediate(0));
+        self.asm
+            .line(format_args!("{} = {}", self.frame(), self.frame_size));
+        Ok(())
+    }
+
+    fn block(&mut self, stmts: &'a [Stmt]) -> Result<()> {
+        let mark = self.next_slot;
+        self.scopes.push(Vec::new());
+        for stmt in stmts {
+            self.stmt(stmt)?;
+        }
+        self.scopes.pop();
+        self.next_slot = mark;
+        Ok(())
+    }
+
+    fn ret(&mut self, value: &Value) {
+        self.asm
+            .emit(format_args!("add {value}, #0, [{RETURN_VALUE}]"));
+        self.asm.emit(format_args!("jf #0, [rb]"));
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) -> Result<()> {
+        // temporaries live only until the end of their statement
+        let mark = self.next_slot;
+        match stmt {
+            Stmt::Let { name, value } => {
+                let value = self.expr(value)?;
+                self.next_slot = mark;
+                let slot = self.alloc();
+                self.copy(&value, &Value::Slot(slot));
+                self.scopes.last_mut().unwrap().push((name, slot));
+                return Ok(());
+            }
+            Stmt::Assign { name, value, line } => {
+                let target = self
+                    .lookup(name)
+                    .ok_or_else(|| semantic(*line, format!("undefined variable `{name}`")))?;
+                let value = self.expr(value)?;
+                self.copy(&value, &target);
+            }
+            Stmt::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.expr(condition)?;
+                self.next_slot = mark;
+                let else_label = self.new_label();
+                self.asm.emit(format_args!("jf {condition}, #{else_label}"));
+                self.block(then)?;
+                if otherwise.is_empty() {
+                    self.asm.label(&else_label);
+                } else {
+                    let end_label = self.new_label();
+                    self.asm.emit(format_args!("jt #1, #{end_label}"));
+                    self.asm.label(&else_label);
+                    self.block(otherwise)?;
+                    self.asm.label(&end_label);
+                }
+            }
+            Stmt::While { condition, body } => {
+                let top_label = self.new_label();
+                let end_label = self.new_label();
+                self.asm.label(&top_label);
+                let condition = self.expr(condition)?;
+                self.next_slot = mark;
+                self.asm.emit(format_args!("jf {condition}, #{end_label}"));
+                self.block(body)?;
+                self.asm.emit(format_args!("jt #1, #{top_label}"));
+                self.asm.label(&end_label);
+            }
+            Stmt::Return { value } => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => Value::Immediate(0),
+                };
+                self.ret(&value);
+            }
+            Stmt::Expr(expr) => {
+                self.expr(expr)?;
+            }
+        }
+        self.next_slot = mark;
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<Value> {
+        if let Some(value) = constant(expr) {
+            return Ok(Value::Immediate(value));
+        }
+        match expr {
+            Expr::Number(value) => Ok(Value::Immediate(*value)),
+            Expr::Var { name, line } => self
+                .lookup(name)
+                .ok_or_else(|| semantic(*line, format!("undefined variable `{name}`"))),
+            Expr::Call { name, args, line } => self.call(name, args, *line),
+            Expr::Unary(op, operand) => {
+                let operand = self.expr(operand)?;
+                let result = Value::Slot(self.alloc());
+                match op {
+                    UnaryOp::Negate => self.asm.emit(format_args!("mul {operand}, #-1, {result}")),
+                    UnaryOp::Not => self.asm.emit(format_args!("eq {operand}, #0, {result}")),
+                }
+                Ok(result)
+            }
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), lhs, rhs) => {
+                // the result is `lhs` as a boolean, unless that was not enough to decide it
+                let result = Value::Slot(self.alloc());
+                let end_label = self.new_label();
+                let lhs = self.expr(lhs)?;
+                if *op == BinaryOp::And {
+                    self.copy(&Value::Immediate(0), &result);
+                    self.asm.emit(format_args!("jf {lhs}, #{end_label}"));
+                } else {
+                    self.copy(&Value::Immediate(1), &result);
+                    self.asm.emit(format_args!("jt {lhs}, #{end_label}"));
+                }
+                let rhs = self.expr(rhs)?;
+                self.asm.emit(format_args!("eq {rhs}, #0, {result}"));
+                self.asm.emit(format_args!("eq {result}, #0, {result}"));
+                self.asm.label(&end_label);
+                Ok(result)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let lhs = self.stable(lhs, std::slice::from_ref(rhs));
+                let rhs = self.expr(rhs)?;
+                let result = Value::Slot(self.alloc());
+                match op {
+                    BinaryOp::Add => self.asm.emit(format_args!("add {lhs}, {rhs}, {result}")),
+                    BinaryOp::Mul => self.asm.emit(format_args!("mul {lhs}, {rhs}, {result}")),
+                    BinaryOp::Sub => match rhs {
+                        Value::Immediate(rhs) => {
+                            let negated = rhs.wrapping_neg();
+                            self.asm
+                                .emit(format_args!("add {lhs}, #{negated}, {result}"));
+                        }
+                        rhs => {
+                            self.asm.emit(format_args!("mul {rhs}, #-1, {result}"));
+                            self.asm.emit(format_args!("add {lhs}, {result}, {result}"));
+                        }
+                    },
+                    BinaryOp::Eq => self.asm.emit(format_args!("eq {lhs}, {rhs}, {result}")),
+                    BinaryOp::Lt => self.asm.emit(format_args!("lt {lhs}, {rhs}, {result}")),
+                    BinaryOp::Gt => self.asm.emit(format_args!("lt {rhs}, {lhs}, {result}")),
+                    // the rest are the negations of the above
+                    BinaryOp::Ne => self.asm.emit(format_args!("eq {lhs}, {rhs}, {result}")),
+                    BinaryOp::Le => self.asm.emit(format_args!("lt {rhs}, {lhs}, {result}")),
+                    BinaryOp::Ge => self.asm.emit(format_args!("lt {lhs}, {rhs}, {result}")),
+                    BinaryOp::And | BinaryOp::Or => unreachable!("handled above"),
+                }
+                if matches!(op, BinaryOp::Ne | BinaryOp::Le | BinaryOp::Ge) {
+                    self.asm.emit(format_args!("eq {result}, #0, {result}"));
+                }
+                Ok(result)
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], line: usize) -> Result<Value> {
+        let arity = match name {
+            "input" => 0,
+            "output" => 1,
+            _ => *self
+                .functions
+                .get(name)
+                .ok_or_else(|| semantic(line, format!("undefined function `{name}`")))?,
+        };
+        if args.len() != arity {
+            return Err(semantic(
+                line,
+                format!(
+                    "`{name}` takes {arity} arguments, but {} were given",
+                    args.len()
+                ),
+            ));
+        }
+
+        match name {
+            "input" => {
+                let result = Value::Slot(self.alloc());
+                self.asm.emit(format_args!("in {result}"));
+                return Ok(result);
+            }
+            "output" => {
+                let value = self.expr(&args[0])?;
+                self.asm.emit(format_args!("out {value}"));
+                return Ok(Value::Immediate(0));
+            }
+            _ => {}
+        }
+
+        // arguments are all evaluated before any is stored, because evaluating one
+        // might involve a call which would overwrite the callee's frame
+        let mut values = Vec::with_capacity(args.len());
+        for (idx, arg) in args.iter().enumerate() {
+            let value = self.expr(arg)?;
+            values.push(self.stable(value, &args[idx + 1..]));
+        }
+
+        let frame = self.frame();
+        let return_label = self.new_label();
+        self.asm
+            .emit(format_args!("add #{return_label}, #0, [rb+{frame}]"));
+        for (idx, value) in values.iter().enumerate() {
+            self.asm
+                .emit(format_args!("add {value}, #0, [rb+{frame}+{}]", idx + 1));
+        }
+        self.asm.emit(format_args!("arb #{frame}"));
+        self.asm
+            .emit(format_args!("jt #1, #{}", function_label(name)));
+        self.asm.label(&return_label);
+        self.asm.emit(format_args!("arb #-{frame}"));
+        let result = Value::Slot(self.alloc());
+        self.asm
+            .emit(format_args!("add [{RETURN_VALUE}], #0, {result}"));
+        Ok(result)
+    }
+}
+
+/// Generate assembly for a parsed program.
+pub(crate) fn generate(program: &Program) -> Result<String> {
+    let mut globals = HashMap::new();
+    for global in &program.globals {
+        let value = constant(&global.value).ok_or_else(|| {
+            semantic(
+                global.line,
+                format!(
+                    "global `{}` must be initialized with a constant",
+                    global.name
+                ),
+            )
+        })?;
+        if globals.insert(global.name.as_str(), value).is_some() {
+            return Err(semantic(
+                global.line,
+                format!("global `{}` is declared more than once", global.name),
+            ));
+        }
+    }
+
+    let mut functions = HashMap::new();
+    for function in &program.functions {
+        if BUILTINS.contains(&function.name.as_str()) {
+            return Err(semantic(
+                function.line,
+                format!("`{}` is a builtin, and cannot be redefined", function.name),
+            ));
+        }
+        if functions
+            .insert(function.name.as_str(), function.params.len())
+            .is_some()
+        {
+            return Err(semantic(
+                function.line,
+                format!("function `{}` is defined more than once", function.name),
+            ));
+        }
+    }
+    match functions.get("main") {
+        Some(0) => {}
+        Some(_) => {
+            let line = program
+                .functions
+                .iter()
+                .find(|function| function.name == "main")
+                .map_or(1, |function| function.line);
+            return Err(semantic(line, "`main` must not take parameters".into()));
+        }
+        None => return Err(semantic(1, "no `main` function".into())),
+    }
+
+    let mut asm = Assembly::default();
+    asm.emit(format_args!("arb #{STACK}"));
+    asm.emit(format_args!("add #.exit, #0, [rb]"));
+    asm.emit(format_args!("jt #1, #{}", function_label("main")));
+    asm.label(".exit");
+    asm.emit(format_args!("halt"));
+
+    for function in &program.functions {
+        FunctionGen {
+            name: &function.name,
+            globals: &globals,
+            functions: &functions,
+            asm: &mut asm,
+            scopes: Vec::new(),
+            // slot 0 holds the return address
+            next_slot: 1,
+            frame_size: 1,
+            next_label: 0,
+        }
+        .function(function)?;
+    }
+
+    asm.line(format_args!(""));
+    for global in &program.globals {
+        asm.label(&global_label(&global.name));
+        asm.emit(format_args!("data {}", globals[global.name.as_str()]));
+    }
+    asm.label(RETURN_VALUE);
+    asm.emit(format_args!("data 0"));
+    asm.label(STACK);
+    Ok(asm.text)
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+
+use intcode::Word;
+
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    Ident(String),
+    Number(Word),
+    Fn,
+    Let,
+    If,
+    Else,
+    While,
+    Return,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Semicolon,
+    Assign,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Plus,
+    Minus,
+    Star,
+    Not,
+    And,
+    Or,
+    Eof,
+}
+
+impl Token {
+    fn keyword(ident: &str) -> Option<Self> {
+        Some(match ident {
+            "fn" => Token::Fn,
+            "let" => Token::Let,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "while" => Token::While,
+            "return" => Token::Return,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Token::Ident(name) => return write!(f, "`{name}`"),
+            Token::Number(value) => return write!(f, "`{value}`"),
+            Token::Eof => return write!(f, "end of input"),
+            Token::Fn => "fn",
+            Token::Let => "let",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::Return => "return",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::Comma => ",",
+            Token::Semicolon => ";",
+            Token::Assign => "=",
+            Token::Eq => "==",
+            Token::Ne => "!=",
+            Token::Lt => "<",
+            Token::Le => "<=",
+            Token::Gt => ">",
+            Token::Ge => ">=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Not => "!",
+            Token::And => "&&",
+            Token::Or => "||",
+        };
+        write!(f, "`{text}`")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Spanned {
+    pub token: Token,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Split `source` into tokens, ending with [`Token::Eof`].
+pub(crate) fn tokenize(source: &str) -> Result<Vec<Spanned>> {
+    let mut tokens = Vec::new();
+    for (idx, line) in source.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.split("//").next().unwrap_or_default();
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, ch)) = chars.next() {
+            let column = start + 1;
+            let mut next_is =
+                |expected: char| chars.next_if(|&(_, next)| next == expected).is_some();
+            let token = match ch {
+                _ if ch.is_whitespace() => continue,
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '{' => Token::LBrace,
+                '}' => Token::RBrace,
+                ',' => Token::Comma,
+                ';' => Token::Semicolon,
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '*' => Token::Star,
+                '=' if next_is('=') => Token::Eq,
+                '=' => Token::Assign,
+                '!' if next_is('=') => Token::Ne,
+                '!' => Token::Not,
+                '<' if next_is('=') => Token::Le,
+                '<' => Token::Lt,
+                '>' if next_is('=') => Token::Ge,
+                '>' => Token::Gt,
+                '&' if next_is('&') => Token::And,
+                '|' if next_is('|') => Token::Or,
+                _ if ch.is_ascii_alphanumeric() || ch == '_' => {
+                    let mut end = start + ch.len_utf8();
+                    while let Some((idx, next)) =
+                        chars.next_if(|&(_, next)| next.is_ascii_alphanumeric() || next == '_')
+                    {
+                        end = idx + next.len_utf8();
+                    }
+                    let word = &line[start..end];
+                    if ch.is_ascii_digit() {
+                        Token::Number(word.parse().map_err(|_| Error::Syntax {
+                            line: line_no,
+                            column,
+                            message: format!("invalid number `{word}`"),
+                        })?)
+                    } else {
+                        Token::keyword(word).unwrap_or_else(|| Token::Ident(word.to_owned()))
+                    }
+                }
+                _ => {
+                    return Err(Error::Syntax {
+                        line: line_no,
+                        column,
+                        message: format!("unexpected character `{ch}`"),
+                    })
+                }
+            };
+            tokens.push(Spanned {
+                token,
+                line: line_no,
+                column,
+            });
+        }
+    }
+    tokens.push(Spanned {
+        token: Token::Eof,
+        line: source.lines().count().max(1),
+        column: source.lines().last().map_or(0, str::len) + 1,
+    });
+    Ok(tokens)
+}
@@ -0,0 +1,71 @@
+//! A small structured language which compiles to Intcode.
+//!
+//! ```text
+//! // globals are initialized with constants
+//! let calls = 0;
+//!
+//! fn factorial(n) {
+//!     calls = calls + 1;
+//!     if n <= 1 {
+//!         return 1;
+//!     }
+//!     return n * factorial(n - 1);
+//! }
+//!
+//! // execution begins at `main`, and the program halts when it returns
+//! fn main() {
+//!     let n = input();
+//!     while n > 0 {
+//!         output(factorial(n));
+//!         n = n - 1;
+//!     }
+//!     output(calls);
+//! }
+//! ```
+//!
+//! Every value is a [`Word`][intcode::Word]. There are no division, arrays, or pointers,
+//! because Intcode has no instructions which would make them cheap.
+//!
+//! - statements: `let name = expr;`, `name = expr;`, `if cond { ... } else { ... }`,
+//!   `while cond { ... }`, `return expr;`, and `expr;`
+//! - operators, loosest first: `||`, `&&`, comparisons, `+ -`, `*`, and unary `- !`;
+//!   comparisons and logical operators produce 0 or 1, and `&&` and `||` short-circuit
+//! - builtins: `input()` reads a word, and `output(expr)` writes one
+//! - a function without a `return` returns 0
+//!
+//! Functions keep their frames on a stack addressed by the relative base: on entry, `[rb]`
+//! holds the return address, followed by the arguments, then the locals and temporaries.
+
+mod codegen;
+mod lexer;
+mod parser;
+
+use intcode::Memory;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("syntax error at {line}:{column}: {message}")]
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("error at line {line}: {message}")]
+    Semantic { line: usize, message: String },
+    #[error("generated assembly was rejected")]
+    Assemble(#[from] intcode::Error),
+}
+
+/// Compile `source` to Intcode assembly, in the syntax accepted by [`intcode::assemble`].
+pub fn compile_to_assembly(source: &str) -> Result<String> {
+    let program = parser::parse(source)?;
+    codegen::generate(&program)
+}
+
+/// Compile `source` to a program runnable on [`intcode::Computer`].
+pub fn compile(source: &str) -> Result<Memory> {
+    let assembly = compile_to_assembly(source)?;
+    Ok(intcode::assemble(&assembly)?)
+}
@@ -0,0 +1,365 @@
+use intcode::Word;
+
+use crate::{
+    lexer::{tokenize, Spanned, Token},
+    Error, Result,
+};
+
+#[derive(Debug)]
+pub(crate) struct Program {
+    pub globals: Vec<Global>,
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Global {
+    pub name: String,
+    pub value: Expr,
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub(crate) struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Stmt>,
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub(crate) enum Stmt {
+    Let {
+        name: String,
+        value: Expr,
+    },
+    Assign {
+        name: String,
+        value: Expr,
+        line: usize,
+    },
+    If {
+        condition: Expr,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+    While {
+        condition: Expr,
+        body: Vec<Stmt>,
+    },
+    Return {
+        value: Option<Expr>,
+    },
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+}
+
+impl BinaryOp {
+    /// Binding power of this operator; higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => 3,
+            BinaryOp::Add | BinaryOp::Sub => 4,
+            BinaryOp::Mul => 5,
+        }
+    }
+
+    fn from_token(token: &Token) -> Option<Self> {
+        Some(match token {
+            Token::Or => BinaryOp::Or,
+            Token::And => BinaryOp::And,
+            Token::Eq => BinaryOp::Eq,
+            Token::Ne => BinaryOp::Ne,
+            Token::Lt => BinaryOp::Lt,
+            Token::Le => BinaryOp::Le,
+            Token::Gt => BinaryOp::Gt,
+            Token::Ge => BinaryOp::Ge,
+            Token::Plus => BinaryOp::Add,
+            Token::Minus => BinaryOp::Sub,
+            Token::Star => BinaryOp::Mul,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Expr {
+    Number(Word),
+    Var {
+        name: String,
+        line: usize,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+        line: usize,
+    },
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Spanned {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) -> &Spanned {
+        let spanned = &self.tokens[self.pos];
+        if spanned.token != Token::Eof {
+            self.pos += 1;
+        }
+        spanned
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let matches = self.peek().token == *token;
+        if matches {
+            self.advance();
+        }
+        matches
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T> {
+        let Spanned {
+            token,
+            line,
+            column,
+        } = self.peek();
+        Err(Error::Syntax {
+            line: *line,
+            column: *column,
+            message: format!("expected {expected}, found {token}"),
+        })
+    }
+
+    fn expect(&mut self, token: Token, expected: &str) -> Result<()> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            self.error(expected)
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match &self.peek().token {
+            Token::Ident(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => self.error("a name"),
+        }
+    }
+
+    fn program(&mut self) -> Result<Program> {
+        let mut program = Program {
+            globals: Vec::new(),
+            functions: Vec::new(),
+        };
+        loop {
+            let line = self.peek().line;
+            match self.peek().token {
+                Token::Eof => return Ok(program),
+                Token::Let => {
+                    self.advance();
+                    let name = self.ident()?;
+                    self.expect(Token::Assign, "`=`")?;
+                    let value = self.expr()?;
+                    self.expect(Token::Semicolon, "`;`")?;
+                    program.globals.push(Global { name, value, line });
+                }
+                Token::Fn => {
+                    self.advance();
+                    let name = self.ident()?;
+                    self.expect(Token::LParen, "`(`")?;
+                    let mut params = Vec::new();
+                    if !self.eat(&Token::RParen) {
+                        loop {
+                            params.push(self.ident()?);
+                            if self.eat(&Token::RParen) {
+                                break;
+                            }
+                            self.expect(Token::Comma, "`,` or `)`")?;
+                        }
+                    }
+                    let body = self.block()?;
+                    program.functions.push(Function {
+                        name,
+                        params,
+                        body,
+                        line,
+                    });
+                }
+                _ => return self.error("`fn` or `let`"),
+            }
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>> {
+        self.expect(Token::LBrace, "`{`")?;
+        let mut stmts = Vec::new();
+        while !self.eat(&Token::RBrace) {
+            stmts.push(self.stmt()?);
+        }
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt> {
+        let line = self.peek().line;
+        let stmt = match &self.peek().token {
+            Token::Let => {
+                self.advance();
+                let name = self.ident()?;
+                self.expect(Token::Assign, "`=`")?;
+                let value = self.expr()?;
+                Stmt::Let { name, value }
+            }
+            Token::If => return self.if_stmt(),
+            Token::While => {
+                self.advance();
+                let condition = self.expr()?;
+                let body = self.block()?;
+                return Ok(Stmt::While { condition, body });
+            }
+            Token::Return => {
+                self.advance();
+                let value = if self.peek().token == Token::Semicolon {
+                    None
+                } else {
+                    Some(self.expr()?)
+                };
+                Stmt::Return { value }
+            }
+            Token::Ident(name)
+                if self.tokens.get(self.pos + 1).map(|next| &next.token)
+                    == Some(&Token::Assign) =>
+            {
+                let name = name.clone();
+                self.pos += 2;
+                let value = self.expr()?;
+                Stmt::Assign { name, value, line }
+            }
+            _ => Stmt::Expr(self.expr()?),
+        };
+        self.expect(Token::Semicolon, "`;`")?;
+        Ok(stmt)
+    }
+
+    fn if_stmt(&mut self) -> Result<Stmt> {
+        self.expect(Token::If, "`if`")?;
+        let condition = self.expr()?;
+        let then = self.block()?;
+        let otherwise = if !self.eat(&Token::Else) {
+            Vec::new()
+        } else if self.peek().token == Token::If {
+            vec![self.if_stmt()?]
+        } else {
+            self.block()?
+        };
+        Ok(Stmt::If {
+            condition,
+            then,
+            otherwise,
+        })
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        self.binary(0)
+    }
+
+    /// Parse a chain of binary operators which bind tighter than `min_precedence`.
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some(op) =
+            BinaryOp::from_token(&self.peek().token).filter(|op| op.precedence() > min_precedence)
+        {
+            self.advance();
+            let rhs = self.binary(op.precedence())?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let op = match self.peek().token {
+            Token::Minus => UnaryOp::Negate,
+            Token::Not => UnaryOp::Not,
+            _ => return self.primary(),
+        };
+        self.advance();
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let line = self.peek().line;
+        match self.peek().token.clone() {
+            Token::Number(value) => {
+                self.advance();
+                Ok(Expr::Number(value))
+            }
+            Token::LParen => {
+                self.advance();
+                let expr = self.expr()?;
+                self.expect(Token::RParen, "`)`")?;
+                Ok(expr)
+            }
+            Token::Ident(name) => {
+                self.advance();
+                if !self.eat(&Token::LParen) {
+                    return Ok(Expr::Var { name, line });
+                }
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        self.expect(Token::Comma, "`,` or `)`")?;
+                    }
+                }
+                Ok(Expr::Call { name, args, line })
+            }
+            _ => self.error("an expression"),
+        }
+    }
+}
+
+pub(crate) fn parse(source: &str) -> Result<Program> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+    };
+    parser.program()
+}
@@ -0,0 +1,62 @@
+//! End-to-end suite: compile each program, then run it on the interpreter.
+//!
+//! Each case in `tests/programs` is a group of files sharing a name:
+//!
+//! - `NAME.ic`: the program; required
+//! - `NAME.input`: comma-separated inputs; none if absent
+//! - `NAME.output`: the expected comma-separated outputs
+//! - `NAME.error`: the expected compile error; if present, the program must fail to compile
+//!
+//! Every case needs either an `.output` or an `.error`.
+
+use std::{fs, path::Path};
+
+use intcode_test_support::{case_file, output_of, read_words, run_suite};
+
+/// Run the case at `base` (the path without an extension), describing each way in which it failed.
+fn run_case(base: &Path) -> Vec<String> {
+    let source = fs::read_to_string(base.with_extension("ic")).unwrap();
+    let expected_error =
+        case_file(base, "error").map(|path| fs::read_to_string(path).unwrap().trim().to_owned());
+    let program = match (intcode_lang::compile(&source), expected_error) {
+        (Ok(program), None) => program,
+        (Ok(_), Some(expected)) => {
+            return vec![format!("expected error {expected:?}, but compiled")]
+        }
+        (Err(err), None) => return vec![format!("failed to compile: {err}")],
+        (Err(err), Some(expected)) if err.to_string() != expected => {
+            return vec![format!(
+                "expected error {expected:?}, but got {:?}",
+                err.to_string()
+            )]
+        }
+        (Err(_), Some(_)) => return Vec::new(),
+    };
+
+    let Some(expected_output) = case_file(base, "output").map(|path| read_words(&path)) else {
+        return vec!["missing .output file".into()];
+    };
+    let input = case_file(base, "input")
+        .map(|path| read_words(&path))
+        .unwrap_or_default();
+
+    match output_of(program, &input) {
+        Ok(output) if output != expected_output => vec![format!(
+            "expected output {expected_output:?}, but got {output:?}"
+        )],
+        Ok(_) => Vec::new(),
+        Err(failure) => vec![failure],
+    }
+}
+
+#[test]
+fn end_to_end() {
+    run_suite(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("programs"),
+        &["ic", "input", "output", "error"],
+        "ic",
+        run_case,
+    );
+}
@@ -0,0 +1,51 @@
+//! Every program in `tests/programs` which compiles must produce the same output once
+//! optimized, and must not grow.
+
+use std::{fs, path::Path};
+
+use intcode::optimize::optimize;
+use intcode_test_support::{case_file, output_of, read_words, run_suite};
+
+/// Optimize the case at `base` (the path without an extension), describing each way in which
+/// the optimized program differs.
+fn run_case(base: &Path) -> Vec<String> {
+    if case_file(base, "error").is_some() {
+        return Vec::new();
+    }
+    let source = fs::read_to_string(base.with_extension("ic")).unwrap();
+    let Ok(program) = intcode_lang::compile(&source) else {
+        // reported by the end-to-end suite
+        return Vec::new();
+    };
+    let input = case_file(base, "input")
+        .map(|path| read_words(&path))
+        .unwrap_or_default();
+
+    let mut failures = Vec::new();
+    let optimized = optimize(&program).program;
+    if optimized.len() > program.len() {
+        failures.push("optimization made the program longer".into());
+    }
+    match (output_of(program, &input), output_of(optimized, &input)) {
+        (Ok(output), Ok(optimized_output)) if optimized_output != output => failures.push(format!(
+            "optimized program output {optimized_output:?} instead of {output:?}"
+        )),
+        (Ok(_), Ok(_)) => {}
+        (Ok(_), Err(failure)) => failures.push(format!("optimized program {failure}")),
+        // reported by the end-to-end suite
+        (Err(_), _) => {}
+    }
+    failures
+}
+
+#[test]
+fn optimized_programs_behave_the_same() {
+    run_suite(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("programs"),
+        &["ic", "input", "output", "error"],
+        "ic",
+        run_case,
+    );
+}
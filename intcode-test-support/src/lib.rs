@@ -0,0 +1,132 @@
+//! Helpers shared by the data-driven test suites of the other crates; used only as a
+//! dev-dependency.
+//!
+//! A suite is a directory of cases. Each case is a group of files sharing a name, whose
+//! extensions say what each file holds.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use intcode::{Computer, Error, Memory, Word};
+
+/// Any program still running after this many instructions has failed.
+pub const STEP_LIMIT: usize = 1_000_000;
+
+/// Read a file of comma-separated words.
+///
+/// Panics, naming the file, if it cannot be read or parsed.
+pub fn read_words(path: &Path) -> Vec<Word> {
+    let text = fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    let memory: Memory = text
+        .parse()
+        .unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    memory.into_inner()
+}
+
+/// The file with extension `ext` of the case at `base`, if the case has one.
+pub fn case_file(base: &Path, ext: &str) -> Option<PathBuf> {
+    Some(base.with_extension(ext)).filter(|path| path.is_file())
+}
+
+/// How a [`run`] ended.
+#[derive(Debug)]
+pub enum Outcome {
+    Halted,
+    Fault(Error),
+    StepLimit,
+}
+
+impl Outcome {
+    /// `Halt`, `StepLimit`, or the name of the fault's [`Error`] variant, as written in
+    /// expectation files.
+    pub fn name(&self) -> String {
+        match self {
+            Outcome::Halted => "Halt".into(),
+            Outcome::Fault(err) => format!("{err:?}")
+                .chars()
+                .take_while(char::is_ascii_alphanumeric)
+                .collect(),
+            Outcome::StepLimit => "StepLimit".into(),
+        }
+    }
+}
+
+/// Step `computer` with in-memory queues until it halts, faults, or has executed `limit`
+/// instructions.
+pub fn run(
+    computer: &mut Computer,
+    input: &mut VecDeque<Word>,
+    output: &mut Vec<Word>,
+    limit: usize,
+) -> Outcome {
+    for _ in 0..limit {
+        match computer.step_io(input, output) {
+            Ok(()) => {}
+            Err(Error::Halt(_)) => return Outcome::Halted,
+            Err(err) => return Outcome::Fault(err),
+        }
+    }
+    Outcome::StepLimit
+}
+
+/// Run `program` on `input` until it halts, returning its output, or describing why it did
+/// not halt within [`STEP_LIMIT`] instructions.
+pub fn output_of(program: Memory, input: &[Word]) -> Result<Vec<Word>, String> {
+    let mut input = input.iter().copied().collect::<VecDeque<_>>();
+    let mut output = Vec::new();
+    let mut computer = Computer::<0>::new(program);
+    match run(&mut computer, &mut input, &mut output, STEP_LIMIT) {
+        Outcome::Halted => Ok(output),
+        Outcome::Fault(err) => Err(format!("faulted: {err}")),
+        Outcome::StepLimit => Err(format!("did not halt within {STEP_LIMIT} steps")),
+    }
+}
+
+/// Run every case in `dir`, then panic if any failed.
+///
+/// Each file's extension must be one of `extensions`, and every case must have a file with
+/// the `required` extension. `run_case` is given the path of each case without an extension,
+/// and returns a description of each way in which it failed.
+pub fn run_suite(
+    dir: &Path,
+    extensions: &[&str],
+    required: &str,
+    run_case: impl Fn(&Path) -> Vec<String>,
+) {
+    let mut cases = BTreeMap::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        assert!(
+            extensions.contains(&ext),
+            "{}: unknown test file type",
+            path.display()
+        );
+        let has_required = cases.entry(path.with_extension("")).or_insert(false);
+        *has_required |= ext == required;
+    }
+    assert!(!cases.is_empty(), "no cases found in {}", dir.display());
+
+    let mut failed = 0;
+    for (base, has_required) in &cases {
+        let name = base.file_name().unwrap().to_string_lossy();
+        if !has_required {
+            eprintln!("{name}: missing .{required} file");
+            failed += 1;
+            continue;
+        }
+        let failures = run_case(base);
+        for failure in &failures {
+            eprintln!("{name}: {failure}");
+        }
+        failed += usize::from(!failures.is_empty());
+    }
+
+    assert_eq!(failed, 0, "{failed} of {} cases failed", cases.len());
+}
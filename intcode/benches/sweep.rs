@@ -0,0 +1,91 @@
+//! Compares running every variant of a day02-style program one at a time against [`sweep`],
+//! and finding its noun and verb one variant at a time against [`sweep_find`].
+//!
+//! Each variant runs in a fresh [`Computer`] in the sequential versions, so the difference
+//! on a single core is the per-variant overhead which the sweeps avoid; on more cores, it
+//! also shows the speedup from running variants in parallel.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use intcode::{assemble, sweep, sweep_find, Computer, Memory, Word};
+
+/// Leaves `100 * noun + verb` in `mem[0]`, after a loop which runs `noun` times.
+const PROGRAM: &str = "
+        add #0, #0, [dummy]     ; words 1 and 2 are the noun and verb
+        mul [1], #100, [acc]
+        add [acc], [2], [acc]
+        add [1], #0, [count]
+loop:   jf [count], #done
+        add [count], #-1, [count]
+        mul [acc], #1, [acc]
+        jt #1, #loop
+done:   add [acc], #0, [0]
+        halt
+dummy:  data 0
+acc:    data 0
+count:  data 0
+";
+
+const WANT: Word = 100 * 76 + 21;
+
+fn variants() -> Vec<[(usize, Word); 2]> {
+    (0..100)
+        .flat_map(|noun| (0..100).map(move |verb| [(1, noun), (2, verb)]))
+        .collect()
+}
+
+/// What `program` leaves in `mem[0]` with `variant` applied, run in a computer of its own.
+fn run_alone(program: &Memory, variant: &[(usize, Word)]) -> Word {
+    let mut computer = Computer::<0>::new(program.clone());
+    for &(addr, value) in variant {
+        computer.patch(addr, value).unwrap();
+    }
+    computer.run().unwrap();
+    computer.peek(0).unwrap()
+}
+
+fn bench(c: &mut Criterion) {
+    let program = assemble(PROGRAM).unwrap();
+    let variants = variants();
+    let expect = Some(7621);
+
+    let mut group = c.benchmark_group("noun_verb");
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            let values = variants
+                .iter()
+                .map(|variant| run_alone(&program, variant))
+                .collect::<Vec<_>>();
+            assert_eq!(values.iter().position(|value| *value == WANT), expect);
+        })
+    });
+    group.bench_function("sweep", |b| {
+        b.iter(|| {
+            let values = sweep(&program, &variants, |computer| computer.peek(0));
+            let found = values
+                .iter()
+                .position(|value| matches!(value, Ok(Ok(WANT))));
+            assert_eq!(found, expect);
+        })
+    });
+    group.bench_function("sequential_find", |b| {
+        b.iter(|| {
+            let found = variants
+                .iter()
+                .position(|variant| run_alone(&program, variant) == WANT);
+            assert_eq!(found, expect);
+        })
+    });
+    group.bench_function("sweep_find", |b| {
+        b.iter(|| {
+            let found = sweep_find(&program, &variants, |computer| {
+                (computer.peek(0).ok()? == WANT).then_some(())
+            });
+            assert_eq!(found.map(|(idx, ())| idx), expect);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
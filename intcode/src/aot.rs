@@ -0,0 +1,568 @@
+//! Ahead-of-time translation of Intcode programs into Rust source.
+//!
+//! [`translate`] finds the code in a program by following its control flow from address 0,
+//! and emits a Rust module whose `run` function executes each straight-line block of that
+//! code directly, dispatching between blocks with a `match` on the instruction pointer.
+//!
+//! Anything the translation could not see is interpreted instead: addresses which were not
+//! found to be code, jumps to computed addresses which are not the start of a block, and
+//! blocks whose code has been overwritten since translation. Self-modification is tracked
+//! word by word.
+//!
+//! Compiled code does not record coverage, check execute protection, track
+//! self-modification, or describe faults by a source map. A computer with any of those, or
+//! with devices attached, is interpreted from start to finish instead. Either way, a compiled
+//! program behaves exactly like [`Computer::step_io`].
+//!
+//! The output is meant to be written to `OUT_DIR` by a build script, then included:
+//!
+//! ```rust,ignore
+//! // build.rs
+//! let program: Memory = std::fs::read_to_string("program.txt")?.parse()?;
+//! std::fs::write(out_dir.join("program.rs"), intcode::aot::translate(&program))?;
+//!
+//! // lib.rs
+//! #[allow(clippy::all)]
+//! mod program {
+//!     include!(concat!(env!("OUT_DIR"), "/program.rs"));
+//! }
+//!
+//! program::COMPILED.run(&mut computer, &mut input, &mut output)?;
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Write as _,
+};
+
+use crate::{
+    disasm::{decode, Decoded, Operand},
+    error::{Error, Result},
+    mem_idx::MemIdx as _,
+    memory::Memory,
+    opcode::Opcode,
+    parameter_mode::ParameterMode,
+    Computer, Word,
+};
+
+/// A program translated by [`translate`].
+#[derive(Clone, Copy)]
+pub struct Compiled {
+    /// The program as it was when translated.
+    pub program: &'static [Word],
+    /// The translated `run` function.
+    pub entry: fn(&mut Computer, &mut VecDeque<Word>, &mut Vec<Word>) -> Result<()>,
+}
+
+impl Compiled {
+    /// Whether this was translated from `program`.
+    pub fn is_for(&self, program: &Memory) -> bool {
+        program.len() == self.program.len() && program.iter().eq(self.program.iter().copied())
+    }
+
+    /// Run `computer` until it halts, using in-memory queues for I/O.
+    ///
+    /// This behaves exactly as stepping `computer` with [`Computer::step_io`] would. If the
+    /// program wants input and `input` is empty, this returns [`Error::AwaitingInput`],
+    /// and may be called again once more input is available.
+    ///
+    /// If `computer` has a source map, devices, memory protection, self-modification
+    /// tracking, or coverage, it is stepped by the interpreter instead.
+    pub fn run(
+        &self,
+        computer: &mut Computer,
+        input: &mut VecDeque<Word>,
+        output: &mut Vec<Word>,
+    ) -> Result<()> {
+        if !computer.is_instrumented() {
+            return (self.entry)(computer, input, output);
+        }
+        loop {
+            match computer.step_io(input, output) {
+                Ok(()) => {}
+                Err(Error::Halt(_)) => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// The state of a compiled program as it runs; used only by translated code.
+pub struct Context<'a, const CHANNEL_BUFFER: usize = 0> {
+    computer: &'a mut Computer<CHANNEL_BUFFER>,
+    input: &'a mut VecDeque<Word>,
+    output: &'a mut Vec<Word>,
+    program: &'static [Word],
+    /// Whether each address of `program` is part of a compiled block.
+    code: Vec<bool>,
+    /// Whether each address of `program` is compiled code which no longer holds its original value.
+    dirty: Vec<bool>,
+    any_dirty: bool,
+}
+
+impl<'a, const CHANNEL_BUFFER: usize> Context<'a, CHANNEL_BUFFER> {
+    /// `blocks` are the `start..end` address ranges of each compiled block of `program`.
+    pub fn new(
+        computer: &'a mut Computer<CHANNEL_BUFFER>,
+        input: &'a mut VecDeque<Word>,
+        output: &'a mut Vec<Word>,
+        program: &'static [Word],
+        blocks: &[(usize, usize)],
+    ) -> Self {
+        let mut code = vec![false; program.len()];
+        for &(start, end) in blocks {
+            code[start..end].fill(true);
+        }
+        let mut context = Self {
+            computer,
+            input,
+            output,
+            program,
+            code,
+            dirty: vec![false; program.len()],
+            any_dirty: false,
+        };
+        // the computer may have been patched or run since it was loaded
+        for addr in 0..program.len() {
+            context.touch(addr as Word);
+        }
+        context
+    }
+
+    #[inline]
+    pub fn ip(&self) -> usize {
+        self.computer.instruction_pointer
+    }
+
+    /// Set the instruction pointer.
+    #[inline]
+    pub fn at(&mut self, addr: usize) {
+        self.computer.instruction_pointer = addr;
+    }
+
+    /// Whether the code in `start..end` is unchanged since translation.
+    #[inline]
+    pub fn is_clean(&self, start: usize, end: usize) -> bool {
+        !self.any_dirty || !self.dirty[start..end].contains(&true)
+    }
+
+    #[inline]
+    pub fn read(&mut self, addr: Word) -> Result<Word> {
+        self.computer.operand(addr)
+    }
+
+    #[inline]
+    pub fn read_relative(&mut self, offset: Word) -> Result<Word> {
+        self.read(offset.wrapping_add(self.computer.relative_base))
+    }
+
+    /// Write `value` to `addr`, returning whether this changed any compiled code.
+    #[inline]
+    pub fn write(&mut self, addr: Word, value: Word) -> Result<bool> {
+        *self.computer.operand_mut(addr)? = value;
+        self.computer.devices.flush();
+        Ok(self.touch(addr))
+    }
+
+    /// Write `value` relative to the relative base, returning whether this changed any compiled code.
+    #[inline]
+    pub fn write_relative(&mut self, offset: Word, value: Word) -> Result<bool> {
+        self.write(offset.wrapping_add(self.computer.relative_base), value)
+    }
+
+    /// Store the next input at `addr`, returning as [`write`](Self::write) does.
+    ///
+    /// The input is only consumed once it has been stored.
+    #[inline]
+    pub fn input(&mut self, addr: Word) -> Result<bool> {
+        let value = *self.input.front().ok_or(Error::AwaitingInput)?;
+        let changed = self.write(addr, value)?;
+        self.input.pop_front();
+        Ok(changed)
+    }
+
+    /// Store the next input relative to the relative base, returning as [`write`](Self::write) does.
+    #[inline]
+    pub fn input_relative(&mut self, offset: Word) -> Result<bool> {
+        self.input(offset.wrapping_add(self.computer.relative_base))
+    }
+
+    #[inline]
+    pub fn output(&mut self, value: Word) {
+        self.output.push(value);
+    }
+
+    #[inline]
+    pub fn adjust_relative_base(&mut self, by: Word) {
+        self.computer.relative_base = self.computer.relative_base.wrapping_add(by);
+    }
+
+    /// Jump to a computed address.
+    #[inline]
+    pub fn jump(&mut self, target: Word) -> Result<()> {
+        self.computer.instruction_pointer =
+            target.try_into().map_err(|_| Error::IndexFailed(target))?;
+        Ok(())
+    }
+
+    /// Interpret the instruction at the instruction pointer, returning whether it was `Halt`.
+    pub fn interpret(&mut self) -> Result<bool> {
+        let target = decode(&self.computer.memory, self.ip())
+            .filter(|decoded| decoded.opcode.writes())
+            .and_then(|decoded| decoded.operands.last().copied());
+        match self.computer.step_io(self.input, self.output) {
+            Ok(()) => {}
+            Err(Error::Halt(_)) => return Ok(true),
+            Err(err) => return Err(err),
+        }
+        match target {
+            Some(Operand {
+                mode: ParameterMode::Position,
+                value,
+            }) => {
+                self.touch(value);
+            }
+            Some(Operand {
+                mode: ParameterMode::Relative,
+                value,
+            }) => {
+                self.touch(value.wrapping_add(self.computer.relative_base));
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Note a write to `addr`, returning whether it changed compiled code.
+    fn touch(&mut self, addr: Word) -> bool {
+        let Some(addr) = usize::try_from(addr)
+            .ok()
+            .filter(|&addr| self.code.get(addr) == Some(&true))
+        else {
+            return false;
+        };
+        if self.computer.memory.ix(addr).ok() == Some(self.program[addr]) {
+            return false;
+        }
+        self.dirty[addr] = true;
+        self.any_dirty = true;
+        true
+    }
+}
+
+/// Whether `decoded` can be compiled: instructions which always fault are left to the interpreter.
+fn compilable(decoded: &Decoded) -> bool {
+    !(decoded.opcode.writes()
+        && decoded.operands.last().map(|operand| operand.mode) == Some(ParameterMode::Immediate))
+}
+
+/// If `decoded` copies an immediate value, return that value.
+///
+/// Intcode compilers call functions by storing the return address like this,
+/// so these values are likely to be code addresses.
+fn copied_immediate(decoded: &Decoded) -> Option<Word> {
+    let identity = match decoded.opcode {
+        Opcode::Add => 0,
+        Opcode::Multiply => 1,
+        _ => return None,
+    };
+    match decoded.operands.as_slice() {
+        [a, b, _]
+            if a.mode == ParameterMode::Immediate
+                && b.mode == ParameterMode::Immediate
+                && b.value == identity =>
+        {
+            Some(a.value)
+        }
+        [a, b, _]
+            if a.mode == ParameterMode::Immediate
+                && b.mode == ParameterMode::Immediate
+                && a.value == identity =>
+        {
+            Some(b.value)
+        }
+        _ => None,
+    }
+}
+
+/// The code of a program, divided into straight-line blocks.
+struct Analysis {
+    instructions: BTreeMap<usize, Decoded>,
+    /// Addresses at which a block must start.
+    leaders: BTreeSet<usize>,
+}
+
+impl Analysis {
+    fn new(program: &Memory) -> Self {
+        let mut instructions = BTreeMap::new();
+        let mut leaders = BTreeSet::from([0]);
+        let mut pending = vec![0];
+
+        while let Some(addr) = pending.pop() {
+            if instructions.contains_key(&addr) {
+                continue;
+            }
+            let Some(decoded) = decode(program, addr).filter(compilable) else {
+                continue;
+            };
+
+            let mut successors = Vec::new();
+            if decoded.opcode != Opcode::Halt {
+                successors.push(decoded.next_addr());
+            }
+            if decoded.opcode.jumps() {
+                if let [_, target] = decoded.operands.as_slice() {
+                    if target.mode == ParameterMode::Immediate {
+                        successors.extend(usize::try_from(target.value).ok());
+                    }
+                }
+                // both ways out of a conditional jump start a new block
+                leaders.extend(successors.iter().copied());
+            }
+            if let Some(pointer) = copied_immediate(&decoded)
+                .and_then(|value| usize::try_from(value).ok())
+                .filter(|&pointer| pointer < program.len())
+            {
+                leaders.insert(pointer);
+                successors.push(pointer);
+            }
+
+            pending.extend(successors.into_iter().filter(|&addr| addr < program.len()));
+            instructions.insert(addr, decoded);
+        }
+
+        Self {
+            instructions,
+            leaders,
+        }
+    }
+
+    /// Each block, as the instructions it contains.
+    fn blocks(&self) -> Vec<Vec<&Decoded>> {
+        let mut blocks = Vec::new();
+        for &leader in &self.leaders {
+            let mut block = Vec::new();
+            let mut addr = leader;
+            while let Some(decoded) = self.instructions.get(&addr) {
+                block.push(decoded);
+                addr = decoded.next_addr();
+                if decoded.opcode.jumps()
+                    || decoded.opcode == Opcode::Halt
+                    || self.leaders.contains(&addr)
+                {
+                    break;
+                }
+            }
+            if !block.is_empty() {
+                blocks.push(block);
+            }
+        }
+        blocks
+    }
+}
+
+/// A Rust expression which evaluates `operand`.
+fn read(operand: Operand) -> String {
+    match operand.mode {
+        ParameterMode::Position => format!("ctx.read({})?", operand.value),
+        ParameterMode::Immediate => operand.value.to_string(),
+        ParameterMode::Relative => format!("ctx.read_relative({})?", operand.value),
+    }
+}
+
+/// Emit a statement writing `value` to `operand`.
+///
+/// If the write might change compiled code, execution resumes from the dispatch loop,
+/// starting at the next instruction.
+fn write(out: &mut String, operand: Operand, value: &str, next: usize, code: &[bool]) {
+    let (method, static_addr) = match operand.mode {
+        ParameterMode::Position => ("write", usize::try_from(operand.value).ok()),
+        ParameterMode::Relative => ("write_relative", None),
+        ParameterMode::Immediate => unreachable!("immediate writes are not compiled"),
+    };
+    let call = format!("ctx.{method}({}, {value})?", operand.value);
+    store(out, &call, static_addr, next, code);
+}
+
+/// Emit a statement storing the next input to `operand`, as [`write`] does.
+fn input(out: &mut String, operand: Operand, next: usize, code: &[bool]) {
+    let (method, static_addr) = match operand.mode {
+        ParameterMode::Position => ("input", usize::try_from(operand.value).ok()),
+        ParameterMode::Relative => ("input_relative", None),
+        ParameterMode::Immediate => unreachable!("immediate writes are not compiled"),
+    };
+    let call = format!("ctx.{method}({})?", operand.value);
+    store(out, &call, static_addr, next, code);
+}
+
+/// Emit `call`, which stores to `static_addr` if that is known, and returns whether it
+/// changed compiled code.
+fn store(out: &mut String, call: &str, static_addr: Option<usize>, next: usize, code: &[bool]) {
+    match static_addr {
+        Some(addr) if code.get(addr) != Some(&true) => {
+            writeln!(out, "                {call};").unwrap();
+        }
+        _ => {
+            writeln!(out, "                if {call} {{").unwrap();
+            writeln!(out, "                    ctx.at({next});").unwrap();
+            writeln!(out, "                    continue;").unwrap();
+            writeln!(out, "                }}").unwrap();
+        }
+    }
+}
+
+/// Emit the statements which execute `decoded`.
+fn instruction(out: &mut String, decoded: &Decoded, code: &[bool]) {
+    let next = decoded.next_addr();
+    let ops = &decoded.operands;
+    writeln!(out, "                // {}: {decoded}", decoded.addr).unwrap();
+    writeln!(out, "                ctx.at({});", decoded.addr).unwrap();
+    match decoded.opcode {
+        Opcode::Add | Opcode::Multiply => {
+            let method = if decoded.opcode == Opcode::Add {
+                "wrapping_add"
+            } else {
+                "wrapping_mul"
+            };
+            let value = format!(
+                "::intcode::Word::{method}({}, {})",
+                read(ops[0]),
+                read(ops[1])
+            );
+            writeln!(out, "                let value = {value};").unwrap();
+            write(out, ops[2], "value", next, code);
+        }
+        Opcode::LessThan | Opcode::Equals => {
+            let op = if decoded.opcode == Opcode::LessThan {
+                "<"
+            } else {
+                "=="
+            };
+            writeln!(out, "                let a = {};", read(ops[0])).unwrap();
+            writeln!(out, "                let b = {};", read(ops[1])).unwrap();
+            writeln!(
+                out,
+                "                let value = ::intcode::Word::from(a {op} b);"
+            )
+            .unwrap();
+            write(out, ops[2], "value", next, code);
+        }
+        Opcode::Input => {
+            input(out, ops[0], next, code);
+        }
+        Opcode::Output => {
+            writeln!(out, "                let value = {};", read(ops[0])).unwrap();
+            writeln!(out, "                ctx.output(value);").unwrap();
+        }
+        Opcode::RelativeBaseOffset => {
+            writeln!(out, "                let value = {};", read(ops[0])).unwrap();
+            writeln!(out, "                ctx.adjust_relative_base(value);").unwrap();
+        }
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+            let test = if decoded.opcode == Opcode::JumpIfTrue {
+                "!="
+            } else {
+                "=="
+            };
+            writeln!(out, "                let test = {};", read(ops[0])).unwrap();
+            writeln!(out, "                let target = {};", read(ops[1])).unwrap();
+            writeln!(out, "                if test {test} 0 {{").unwrap();
+            writeln!(out, "                    ctx.jump(target)?;").unwrap();
+            writeln!(out, "                }} else {{").unwrap();
+            writeln!(out, "                    ctx.at({next});").unwrap();
+            writeln!(out, "                }}").unwrap();
+        }
+        Opcode::Halt => {
+            writeln!(out, "                return Ok(());").unwrap();
+        }
+    }
+}
+
+/// Translate `program` into the source of a Rust module.
+///
+/// The module contains `PROGRAM`, the words of `program`; `run`, which executes a computer
+/// as [`Compiled::run`] describes; and `COMPILED`, a [`Compiled`] referring to both.
+pub fn translate(program: &Memory) -> String {
+    let analysis = Analysis::new(program);
+    let blocks = analysis.blocks();
+
+    let mut code = vec![false; program.len()];
+    for block in &blocks {
+        let (start, end) = (block[0].addr, block[block.len() - 1].next_addr());
+        code[start..end].fill(true);
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by `intcode::aot::translate`; do not edit."
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "pub const PROGRAM: &[::intcode::Word] = &[").unwrap();
+    for chunk in program.iter().collect::<Vec<_>>().chunks(16) {
+        let words = chunk.iter().map(Word::to_string).collect::<Vec<_>>();
+        writeln!(out, "    {},", words.join(", ")).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "const BLOCKS: &[(usize, usize)] = &[").unwrap();
+    for block in &blocks {
+        let (start, end) = (block[0].addr, block[block.len() - 1].next_addr());
+        writeln!(out, "    ({start}, {end}),").unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(
+        out,
+        "pub const COMPILED: ::intcode::aot::Compiled = ::intcode::aot::Compiled {{
+    program: PROGRAM,
+    entry: run,
+}};
+
+pub fn run(
+    computer: &mut ::intcode::Computer,
+    input: &mut ::std::collections::VecDeque<::intcode::Word>,
+    output: &mut ::std::vec::Vec<::intcode::Word>,
+) -> ::std::result::Result<(), ::intcode::Error> {{
+    let mut ctx = ::intcode::aot::Context::new(computer, input, output, PROGRAM, BLOCKS);
+    loop {{
+        match ctx.ip() {{"
+    )
+    .unwrap();
+
+    for block in &blocks {
+        let (start, end) = (block[0].addr, block[block.len() - 1].next_addr());
+        writeln!(
+            out,
+            "            {start} if ctx.is_clean({start}, {end}) => {{"
+        )
+        .unwrap();
+        for decoded in block {
+            instruction(&mut out, decoded, &code);
+        }
+        let last = block[block.len() - 1];
+        if !last.opcode.jumps() && last.opcode != Opcode::Halt {
+            writeln!(out, "                ctx.at({end});").unwrap();
+        }
+        writeln!(out, "            }}").unwrap();
+    }
+
+    writeln!(
+        out,
+        "            _ => {{
+                if ctx.interpret()? {{
+                    return Ok(());
+                }}
+            }}
+        }}
+    }}
+}}"
+    )
+    .unwrap();
+
+    out
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    error::{Error, Result},
+    io::{Queues, Status},
+    Computer, Word,
+};
+
+/// Drive a computer whose program communicates in ASCII text.
+///
+/// Inputs are sent a line at a time. Outputs in the ASCII range are assembled into lines;
+/// any other output word is an answer, and is collected separately.
+///
+/// The computer runs on the current thread, using in-memory queues instead of its channels.
+pub struct Ascii<const CHANNEL_BUFFER: usize = 0> {
+    computer: Computer<CHANNEL_BUFFER>,
+    inbox: VecDeque<Word>,
+    outbox: Vec<Word>,
+    /// Text output since the last newline.
+    partial: String,
+    lines: Vec<String>,
+    answers: Vec<Word>,
+}
+
+impl<const CHANNEL_BUFFER: usize> Ascii<CHANNEL_BUFFER> {
+    pub fn new(computer: Computer<CHANNEL_BUFFER>) -> Self {
+        Self {
+            computer,
+            inbox: VecDeque::new(),
+            outbox: Vec::new(),
+            partial: String::new(),
+            lines: Vec::new(),
+            answers: Vec::new(),
+        }
+    }
+
+    pub fn into_computer(self) -> Computer<CHANNEL_BUFFER> {
+        self.computer
+    }
+
+    /// Queue `line` as input, followed by a newline.
+    pub fn send_line(&mut self, line: &str) -> Result<()> {
+        if let Some(ch) = line.chars().find(|ch| !ch.is_ascii()) {
+            return Err(Error::NonAscii(ch));
+        }
+        self.inbox.extend(line.bytes().map(Word::from));
+        self.inbox.push_back(b'\n'.into());
+        Ok(())
+    }
+
+    /// Run until the program halts or wants more input than has been sent.
+    pub fn run(&mut self) -> Result<Status> {
+        let (status, _) = self.computer.run_queued(
+            &mut Queues {
+                input: &mut self.inbox,
+                output: &mut self.outbox,
+            },
+            usize::MAX,
+        )?;
+
+        for word in self.outbox.drain(..) {
+            match u8::try_from(word) {
+                Ok(b'\n') => self.lines.push(std::mem::take(&mut self.partial)),
+                Ok(byte) if byte.is_ascii() => self.partial.push(byte.into()),
+                _ => self.answers.push(word),
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Take all complete lines of output produced since the last time they were taken.
+    pub fn take_lines(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.lines)
+    }
+
+    /// Text output since the last newline; typically a prompt.
+    pub fn partial_line(&self) -> &str {
+        &self.partial
+    }
+
+    /// Take all non-ASCII outputs produced since the last time they were taken.
+    pub fn take_answers(&mut self) -> Vec<Word> {
+        std::mem::take(&mut self.answers)
+    }
+
+    /// Run an interactive session: program output is written to `output`,
+    /// and each line read from `input` is sent to the program.
+    ///
+    /// Returns when the program halts, or when `input` is exhausted while the program
+    /// is awaiting input.
+    pub fn interact(&mut self, mut input: impl BufRead, mut output: impl Write) -> Result<Status> {
+        let mut line = String::new();
+        loop {
+            let status = self.run()?;
+
+            for text in self.take_lines() {
+                writeln!(output, "{text}")?;
+            }
+            for answer in self.take_answers() {
+                writeln!(output, "answer: {answer}")?;
+            }
+            write!(output, "{}", self.partial)?;
+            output.flush()?;
+            self.partial.clear();
+
+            if status == Status::Halted {
+                return Ok(status);
+            }
+
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(status);
+            }
+            self.send_line(line.trim_end_matches(['\r', '\n']))?;
+        }
+    }
+}
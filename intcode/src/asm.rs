@@ -0,0 +1,553 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    error::{Error, Result},
+    link::{Object, Relocation},
+    memory::Memory,
+    opcode::Opcode,
+    parameter_mode::ParameterMode,
+    source_map::{Location, SourceMap},
+    Word,
+};
+
+/// A value in assembly source: a sum of numbers and labels, such as `loop`, `-3`, or `table+2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expr {
+    terms: Vec<(Word, Term)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Number(Word),
+    Label(String),
+}
+
+impl Expr {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut terms = Vec::new();
+        let mut rest = text.trim();
+        if rest.is_empty() {
+            return Err("expected a value".into());
+        }
+        while !rest.is_empty() {
+            // the operator before each term is folded into its sign
+            let mut sign = 1;
+            loop {
+                rest = rest.trim_start();
+                if let Some(after) = rest.strip_prefix('-') {
+                    sign = -sign;
+                    rest = after;
+                } else if let Some(after) = rest.strip_prefix('+') {
+                    rest = after;
+                } else {
+                    break;
+                }
+            }
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            let token = rest[..end].trim();
+            let term = if token.starts_with(|ch: char| ch.is_ascii_digit()) {
+                Term::Number(
+                    token
+                        .parse()
+                        .map_err(|_| format!("invalid number {token:?}"))?,
+                )
+            } else if is_identifier(token) {
+                Term::Label(token.to_owned())
+            } else {
+                return Err(format!("invalid value {:?}", text.trim()));
+            };
+            terms.push((sign, term));
+            rest = &rest[end..];
+        }
+        Ok(Self { terms })
+    }
+
+    fn evaluate(&self, symbols: &Symbols) -> Result<Value, String> {
+        self.terms
+            .iter()
+            .try_fold(Value::default(), |sum, (sign, term)| {
+                let value = match term {
+                    Term::Number(value) => Value::absolute(*value),
+                    Term::Label(label) if symbols.externs.contains(label) => Value {
+                        externs: vec![(1, label.clone())],
+                        ..Value::default()
+                    },
+                    Term::Label(label) => symbols
+                        .values
+                        .get(label)
+                        .cloned()
+                        .ok_or_else(|| format!("undefined label `{label}`"))?,
+                };
+                Ok(sum.plus(*sign, value))
+            })
+    }
+}
+
+/// The value of an expression, as it depends on where its module is placed.
+///
+/// The final value is `offset`, plus `labels` times the address of the module, plus the
+/// address of each extern with its sign.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Value {
+    offset: Word,
+    labels: Word,
+    externs: Vec<(Word, String)>,
+}
+
+impl Value {
+    fn absolute(offset: Word) -> Self {
+        Self {
+            offset,
+            ..Self::default()
+        }
+    }
+
+    fn plus(mut self, sign: Word, other: Value) -> Self {
+        self.offset = self.offset.wrapping_add(sign.wrapping_mul(other.offset));
+        self.labels = self.labels.wrapping_add(sign.wrapping_mul(other.labels));
+        self.externs.extend(
+            other
+                .externs
+                .into_iter()
+                .map(|(other_sign, name)| (sign * other_sign, name)),
+        );
+        self
+    }
+
+    /// The value if the module is placed at address 0, and is not linked to any other.
+    fn resolve(self) -> Result<Word, String> {
+        match self.externs.first() {
+            Some((_, name)) => Err(format!("`{name}` is extern; link the program instead")),
+            None => Ok(self.offset),
+        }
+    }
+
+    /// The word to store, and how the linker must relocate it.
+    fn relocate(self, offset: usize) -> Result<(Word, Option<Relocation>), String> {
+        let relocation = match (self.labels, self.externs.as_slice()) {
+            (0, []) => None,
+            (1, []) => Some(Relocation {
+                offset,
+                symbol: None,
+            }),
+            (0, [(1, name)]) => Some(Relocation {
+                offset,
+                symbol: Some(name.clone()),
+            }),
+            _ => return Err("value must be a number, or a number plus one label or extern".into()),
+        };
+        Ok((self.offset, relocation))
+    }
+}
+
+/// Everything a value in the source may refer to.
+#[derive(Debug, Default)]
+struct Symbols {
+    values: HashMap<String, Value>,
+    externs: HashSet<String>,
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_' || ch == '.')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '.')
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AsmOperand {
+    mode: ParameterMode,
+    value: Expr,
+}
+
+impl AsmOperand {
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if let Some(value) = text.strip_prefix('#') {
+            return Ok(Self {
+                mode: ParameterMode::Immediate,
+                value: Expr::parse(value)?,
+            });
+        }
+        let Some(inner) = text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        else {
+            return Err(format!(
+                "invalid operand {text:?}; expected `#value`, `[address]`, or `[rb+offset]`"
+            ));
+        };
+        let inner = inner.trim();
+        match inner.strip_prefix("rb").map(str::trim_start) {
+            Some("") => Ok(Self {
+                mode: ParameterMode::Relative,
+                value: Expr::parse("0")?,
+            }),
+            Some(offset) if offset.starts_with(['+', '-']) => Ok(Self {
+                mode: ParameterMode::Relative,
+                value: Expr::parse(offset)?,
+            }),
+            _ => Ok(Self {
+                mode: ParameterMode::Position,
+                value: Expr::parse(inner)?,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Statement {
+    Instruction {
+        opcode: Opcode,
+        operands: Vec<AsmOperand>,
+    },
+    Data(Vec<Expr>),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction { operands, .. } => 1 + operands.len(),
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+fn opcode_for(mnemonic: &str) -> Option<Opcode> {
+    [
+        Opcode::Add,
+        Opcode::Multiply,
+        Opcode::Input,
+        Opcode::Output,
+        Opcode::JumpIfTrue,
+        Opcode::JumpIfFalse,
+        Opcode::LessThan,
+        Opcode::Equals,
+        Opcode::RelativeBaseOffset,
+        Opcode::Halt,
+    ]
+    .into_iter()
+    .find(|opcode| opcode.mnemonic() == mnemonic)
+}
+
+/// Split `text` on commas which are not inside brackets.
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, ch) in text.char_indices() {
+        match ch {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn parse_statement(text: &str) -> Result<Statement, String> {
+    let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    if mnemonic == "data" {
+        let values = split_operands(rest)
+            .into_iter()
+            .map(Expr::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.is_empty() {
+            return Err("`data` needs at least one value".into());
+        }
+        return Ok(Statement::Data(values));
+    }
+
+    let opcode = opcode_for(mnemonic).ok_or_else(|| format!("unknown mnemonic `{mnemonic}`"))?;
+    let operands = split_operands(rest)
+        .into_iter()
+        .map(AsmOperand::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    if operands.len() != opcode.parameter_count() {
+        return Err(format!(
+            "`{mnemonic}` takes {} operands, but {} were given",
+            opcode.parameter_count(),
+            operands.len()
+        ));
+    }
+    if opcode.writes()
+        && operands.last().map(|operand| operand.mode) == Some(ParameterMode::Immediate)
+    {
+        return Err(format!("`{mnemonic}` cannot write to an immediate operand"));
+    }
+    Ok(Statement::Instruction { opcode, operands })
+}
+
+fn assemble_error(line: usize, message: String) -> Error {
+    Error::Assemble { line, message }
+}
+
+/// A source file, once every line has been parsed and every label placed.
+struct Module {
+    statements: Vec<(usize, Statement)>,
+    symbols: Symbols,
+    /// Each label, with its address.
+    labels: Vec<(String, usize)>,
+    exports: Vec<(usize, String)>,
+    size: usize,
+}
+
+impl Module {
+    fn parse(source: &str) -> Result<Self> {
+        let mut symbols = Symbols::default();
+        let mut constants = Vec::new();
+        let mut statements = Vec::new();
+        let mut exports = Vec::new();
+        let mut labels = Vec::new();
+        let mut addr = 0;
+
+        for (idx, line) in source.lines().enumerate() {
+            let line_no = idx + 1;
+            let mut text = line.split(';').next().unwrap_or_default().trim();
+            let defined = |symbols: &Symbols, constants: &[(_, String, _)], name: &str| {
+                if symbols.values.contains_key(name)
+                    || symbols.externs.contains(name)
+                    || constants.iter().any(|(_, constant, _)| constant == name)
+                {
+                    Err(assemble_error(
+                        line_no,
+                        format!("label `{name}` is defined more than once"),
+                    ))
+                } else {
+                    Ok(())
+                }
+            };
+
+            if let Some((name, value)) = text
+                .split_once('=')
+                .filter(|(name, _)| is_identifier(name.trim()))
+            {
+                // constants are evaluated once every label is known
+                let name = name.trim();
+                defined(&symbols, &constants, name)?;
+                let value =
+                    Expr::parse(value).map_err(|message| assemble_error(line_no, message))?;
+                constants.push((line_no, name.to_owned(), value));
+                continue;
+            }
+
+            while let Some((label, rest)) = text
+                .split_once(':')
+                .filter(|(label, _)| is_identifier(label.trim()))
+            {
+                let label = label.trim();
+                defined(&symbols, &constants, label)?;
+                let value = Value {
+                    offset: addr as Word,
+                    labels: 1,
+                    externs: Vec::new(),
+                };
+                symbols.values.insert(label.to_owned(), value);
+                labels.push((label.to_owned(), addr));
+                text = rest.trim();
+            }
+
+            if text.is_empty() {
+                continue;
+            }
+            let (directive, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            let names = || {
+                split_operands(rest)
+                    .into_iter()
+                    .map(str::trim)
+                    .map(|name| {
+                        if is_identifier(name) {
+                            Ok(name.to_owned())
+                        } else {
+                            Err(assemble_error(line_no, format!("invalid name {name:?}")))
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()
+            };
+            match directive {
+                "export" => exports.extend(names()?.into_iter().map(|name| (line_no, name))),
+                "extern" => {
+                    for name in names()? {
+                        defined(&symbols, &constants, &name)?;
+                        symbols.externs.insert(name);
+                    }
+                }
+                _ => {
+                    let statement = parse_statement(text)
+                        .map_err(|message| assemble_error(line_no, message))?;
+                    addr += statement.size();
+                    statements.push((line_no, statement));
+                }
+            }
+        }
+
+        // a constant may refer to any label, and to any constant defined above it
+        for (line_no, name, value) in constants {
+            let value = value
+                .evaluate(&symbols)
+                .map_err(|message| assemble_error(line_no, message))?;
+            symbols.values.insert(name, value);
+        }
+
+        Ok(Self {
+            statements,
+            symbols,
+            labels,
+            exports,
+            size: addr,
+        })
+    }
+
+    /// Where each word and label came from, attributing every line to `file`.
+    fn source_map(&self, file: &str) -> SourceMap {
+        let mut map = SourceMap::default();
+        let mut addr = 0;
+        for (line, statement) in &self.statements {
+            let location = Location {
+                file: file.to_owned(),
+                line: *line,
+            };
+            map.add_span(addr, statement.size(), location);
+            addr += statement.size();
+        }
+        for (name, addr) in &self.labels {
+            map.add_label(name, *addr);
+        }
+        map
+    }
+
+    /// The words of the module, placed at address 0 and not linked to any other.
+    fn words(&self) -> Result<Vec<Word>> {
+        let mut words = Vec::with_capacity(self.size);
+        for (line_no, value) in self.values() {
+            let word = value?
+                .resolve()
+                .map_err(|message| assemble_error(line_no, message))?;
+            words.push(word);
+        }
+        Ok(words)
+    }
+
+    /// Every value the module's words hold, with the line which defines each.
+    fn values(&self) -> impl Iterator<Item = (usize, Result<Value>)> + '_ {
+        self.statements
+            .iter()
+            .flat_map(move |(line_no, statement)| {
+                let line_no = *line_no;
+                let evaluate = move |expr: &Expr| {
+                    expr.evaluate(&self.symbols)
+                        .map_err(|message| assemble_error(line_no, message))
+                };
+                let values: Vec<Result<Value>> = match statement {
+                    Statement::Instruction { opcode, operands } => {
+                        let modes = operands
+                            .iter()
+                            .rev()
+                            .fold(0, |modes, operand| modes * 10 + operand.mode as Word);
+                        std::iter::once(Ok(Value::absolute(*opcode as Word + 100 * modes)))
+                            .chain(operands.iter().map(|operand| evaluate(&operand.value)))
+                            .collect()
+                    }
+                    Statement::Data(values) => values.iter().map(evaluate).collect(),
+                };
+                values.into_iter().map(move |value| (line_no, value))
+            })
+    }
+}
+
+/// Assemble a program written in the syntax of [`disassemble`][crate::disassemble].
+///
+/// Each line holds an optional label definition (`name:`), then an optional instruction
+/// or `data` directive, then an optional comment starting with `;`:
+///
+/// ```text
+/// start:  in [count]
+/// loop:   out [count]
+///         add [count], #-1, [count]
+///         jt [count], #loop
+///         halt
+/// count:  data 0
+/// ```
+///
+/// Wherever a number may appear, so may a label, or a sum such as `table+2`.
+///
+/// A line of the form `name = value` defines a constant, which may be used like a label.
+///
+/// `export` and `extern` lines only matter to [`assemble_object`]; a program which uses an
+/// extern must be linked.
+pub fn assemble(source: &str) -> Result<Memory> {
+    Ok(Module::parse(source)?.words()?.into())
+}
+
+/// Assemble a program as [`assemble`] does, also mapping each address back to its line
+/// in `source`, which is called `file`.
+pub fn assemble_with_source_map(file: &str, source: &str) -> Result<(Memory, SourceMap)> {
+    let module = Module::parse(source)?;
+    Ok((module.words()?.into(), module.source_map(file)))
+}
+
+/// Assemble a module of a program, to be combined with others by [`link`][crate::link::link].
+///
+/// The syntax is that of [`assemble`], plus two directives, each taking a comma-separated
+/// list of names:
+///
+/// - `export name` makes the label `name` visible to other modules
+/// - `extern name` declares that `name` is a label which another module exports
+///
+/// The object's source map attributes each line to a file called `name`.
+///
+/// Every word which depends on where the module is placed, or on an extern, gets a
+/// relocation. Such a word may hold a number plus at most one label or one extern.
+pub fn assemble_object(name: &str, source: &str) -> Result<Object> {
+    let module = Module::parse(source)?;
+    let mut words = Vec::with_capacity(module.size);
+    let mut relocations = Vec::new();
+    for (line_no, value) in module.values() {
+        let (word, relocation) = value?
+            .relocate(words.len())
+            .map_err(|message| assemble_error(line_no, message))?;
+        words.push(word);
+        relocations.extend(relocation);
+    }
+
+    let mut exports = BTreeMap::new();
+    for (line_no, export) in &module.exports {
+        let offset = match module.symbols.values.get(export) {
+            Some(Value {
+                offset,
+                labels: 1,
+                externs,
+            }) if externs.is_empty() => *offset,
+            Some(_) => {
+                return Err(assemble_error(
+                    *line_no,
+                    format!("`{export}` is not a label, so it cannot be exported"),
+                ))
+            }
+            None => {
+                return Err(assemble_error(
+                    *line_no,
+                    format!("undefined label `{export}`"),
+                ))
+            }
+        };
+        exports.insert(export.clone(), offset as usize);
+    }
+
+    Ok(Object {
+        name: name.to_owned(),
+        words,
+        exports,
+        relocations,
+        source_map: module.source_map(name),
+    })
+}
@@ -0,0 +1,103 @@
+use crate::{error::Result, scheduler::Scheduler, Computer, Word};
+
+/// Identifies a node within a [`Circuit`].
+///
+/// This is also the index of the node's outputs in the result of [`Circuit::run`].
+pub type NodeId = usize;
+
+/// A network of computers, where each computer's outputs are wired to other computers' inputs.
+///
+/// Wiring is arbitrary: lines, rings, fan-out (each output is copied to every target),
+/// and fan-in (outputs from several sources are interleaved in arrival order) are all permitted.
+///
+/// ```rust,ignore
+/// let mut circuit = Circuit::new();
+/// let a = circuit.add_node(Computer::new(program));
+/// let b = circuit.add_node(Computer::new(program));
+/// circuit.ring(&[a, b]);
+/// circuit.seed(a, [0]);
+/// let outputs = circuit.run()?;
+/// ```
+///
+/// Nodes run on a [`Scheduler`] rather than talking over channels, so their computers'
+/// channel buffer size is irrelevant and always left at the default.
+pub struct Circuit {
+    scheduler: Scheduler,
+    targets: Vec<Vec<NodeId>>,
+}
+
+impl Default for Circuit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Circuit {
+    pub fn new() -> Self {
+        Self {
+            scheduler: Scheduler::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Add a computer to this circuit.
+    pub fn add_node(&mut self, computer: Computer) -> NodeId {
+        self.targets.push(Vec::new());
+        self.scheduler.spawn(computer)
+    }
+
+    /// Queue inputs for `node`.
+    ///
+    /// Seed inputs are always delivered before any input from a connected node.
+    pub fn seed(&mut self, node: NodeId, inputs: impl IntoIterator<Item = Word>) {
+        self.scheduler.send(node, inputs);
+    }
+
+    /// Wire the outputs of `from` into the inputs of `to`.
+    pub fn connect(&mut self, from: NodeId, to: NodeId) {
+        self.targets[from].push(to);
+    }
+
+    /// Wire each node's outputs into the inputs of the next node in `nodes`.
+    pub fn chain(&mut self, nodes: &[NodeId]) {
+        for window in nodes.windows(2) {
+            self.connect(window[0], window[1]);
+        }
+    }
+
+    /// Wire `nodes` into a chain, and then wire the last node back into the first.
+    pub fn ring(&mut self, nodes: &[NodeId]) {
+        self.chain(nodes);
+        if let (Some(&first), Some(&last)) = (nodes.first(), nodes.last()) {
+            self.connect(last, first);
+        }
+    }
+
+    /// Run every computer in the circuit until all of them have halted.
+    ///
+    /// Computers are time-sliced on the current thread by a [`Scheduler`]; outputs are
+    /// delivered to their targets after each round. Inputs sent to a node's own input channel
+    /// are not read.
+    ///
+    /// If the circuit can make no further progress before every computer has halted,
+    /// this returns [`Error::Deadlock`][crate::Error::Deadlock].
+    ///
+    /// Returns every output produced by each node, indexed by [`NodeId`].
+    pub fn run(&mut self) -> Result<Vec<Vec<Word>>> {
+        let mut outputs = vec![Vec::new(); self.targets.len()];
+
+        while !self.scheduler.is_halted() {
+            self.scheduler.round()?;
+
+            for (id, targets) in self.targets.iter().enumerate() {
+                let new_outputs = self.scheduler.take_outputs(id);
+                for &target in targets {
+                    self.scheduler.send(target, new_outputs.iter().copied());
+                }
+                outputs[id].extend(new_outputs);
+            }
+        }
+
+        Ok(outputs)
+    }
+}
@@ -1,18 +1,32 @@
-use std::{fmt, time::Duration};
+use std::{collections::VecDeque, fmt, sync::Arc, time::Duration};
 
 use crossbeam_channel::{Receiver, Sender};
 
 use crate::{
+    coverage::Coverage,
     error::Error,
     error::Result,
     instruction::{Instruction, ParameterModes},
-    mem_idx::MemIdx as _,
+    io::{Channels, Io, Queues, Status},
+    mem_idx::MemIdx,
     memory::Memory,
+    mmio::Devices,
     opcode::Opcode,
     parameters::Parameters,
+    protect::Guard,
+    source_map::SourceMap,
     Word,
 };
 
+/// An Intcode computer.
+///
+/// Where the puzzles leave behavior unspecified:
+///
+/// - arithmetic, including relative addressing, wraps on overflow
+/// - an instruction whose parameters extend past the end of memory faults
+/// - the target of a jump is only checked if the jump is taken
+/// - `Input` from a channel consumes its input before resolving where to store it, but
+///   `Input` from a queue leaves its input queued if storing it faults
 pub struct Computer<const CHANNEL_BUFFER: usize = 0> {
     pub(crate) memory: Memory,
     pub(crate) instruction_pointer: usize,
@@ -21,6 +35,10 @@ pub struct Computer<const CHANNEL_BUFFER: usize = 0> {
     input_rx: Receiver<Word>,
     output_tx: Sender<Word>,
     output_rx: Receiver<Word>,
+    source_map: Option<Arc<SourceMap>>,
+    pub(crate) devices: Devices,
+    pub(crate) guard: Guard,
+    pub(crate) coverage: Option<Coverage>,
 }
 
 impl<const CHANNEL_BUFFER: usize> fmt::Debug for Computer<CHANNEL_BUFFER> {
@@ -46,9 +64,93 @@ impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
             input_rx,
             output_tx,
             output_rx,
+            source_map: None,
+            devices: Devices::default(),
+            guard: Guard::default(),
+            coverage: None,
         }
     }
 
+    /// Fork this computer, producing an independent computer in the same execution state.
+    ///
+    /// Memory is copy-on-write: the fork shares pages with `self` until either of them
+    /// writes to a page, so forking is cheap even when done many times at branch points.
+    ///
+    /// The fork has fresh input and output channels. Inputs which were sent to `self`
+    /// but not yet consumed are not duplicated. Attached [devices](crate::mmio) are shared,
+    /// and if `self` is recording coverage, the fork records its own from scratch.
+    pub fn fork(&self) -> Self {
+        Self {
+            instruction_pointer: self.instruction_pointer,
+            relative_base: self.relative_base,
+            source_map: self.source_map.clone(),
+            devices: self.devices.clone(),
+            guard: self.guard.clone(),
+            coverage: self.coverage.as_ref().map(|_| Coverage::default()),
+            ..Self::new(self.memory.clone())
+        }
+    }
+
+    /// Reset this computer to the initial state of `program`, reusing existing allocations.
+    ///
+    /// Any inputs and outputs still pending in this computer's channels are discarded, as
+    /// is the record of which addresses were executed and written, for
+    /// [`self_modification`](Self::self_modification). What is configured for the computer
+    /// persists: its source map, attached devices, and protected ranges; and so does any
+    /// coverage it is recording, so that coverage accumulates over every run.
+    pub fn reset_from(&mut self, program: &Memory) {
+        self.memory.copy_from(program);
+        self.instruction_pointer = 0;
+        self.relative_base = 0;
+        self.devices.reset();
+        self.guard.reset();
+        for _ in self.input_rx.try_iter() {}
+        for _ in self.output_rx.try_iter() {}
+    }
+
+    /// Overwrite the word at `addr`, growing memory if required.
+    pub fn patch(&mut self, addr: usize, value: Word) -> Result<()> {
+        *self.memory.ix_mut(addr)? = value;
+        Ok(())
+    }
+
+    /// Read the word at `addr`.
+    pub fn peek(&self, addr: usize) -> Result<Word> {
+        self.memory.ix(addr)
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    pub fn relative_base(&self) -> Word {
+        self.relative_base
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Describe faults by where they happened in the source of the program, per `map`.
+    ///
+    /// Every fault is then wrapped in [`Error::Located`].
+    pub fn set_source_map(&mut self, map: SourceMap) {
+        self.source_map = Some(Arc::new(map));
+    }
+
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.source_map.as_deref()
+    }
+
+    /// Whether anything is attached which must see every instruction as it is interpreted:
+    /// a source map, devices, memory protection or self-modification tracking, or coverage.
+    pub(crate) fn is_instrumented(&self) -> bool {
+        self.source_map.is_some()
+            || !self.devices.is_empty()
+            || self.guard.is_active()
+            || self.coverage.is_some()
+    }
+
     /// Get `N` raw parameters for the current instruction.
     ///
     /// This means that we have not yet applied the parameter modes to the parameters.
@@ -57,13 +159,17 @@ impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
     pub(crate) fn raw_parameters<const N: usize>(&self) -> Result<[Word; N]> {
         let low = self.instruction_pointer + 1;
         let high = low + N;
-        let slice = self.memory.get(low..high).ok_or(Error::MemoryExhausted {
-            idx: high,
-            len: self.memory.len(),
-        })?;
-        Ok(slice
-            .try_into()
-            .expect("`fn raw_parameters()` produces an appropriately sized slice"))
+        if high > self.memory.len() {
+            return Err(Error::MemoryExhausted {
+                idx: high,
+                len: self.memory.len(),
+            });
+        }
+        let mut raw = [0; N];
+        for (offset, param) in raw.iter_mut().enumerate() {
+            *param = self.memory.ix(low + offset)?;
+        }
+        Ok(raw)
     }
 
     pub(crate) fn parameters<'a, P>(&'a mut self, modes: ParameterModes) -> Result<P>
@@ -73,47 +179,87 @@ impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
         P::apply(self, modes)
     }
 
-    /// Execute the opcode at the current instruction pointer.
+    pub(crate) fn recv_input(&self) -> Result<Word> {
+        self.input_rx
+            .recv_timeout(Self::CHANNEL_TIMEOUT)
+            .map_err(|_| Error::InputTimeout)
+    }
+
+    pub(crate) fn send_output(&self, value: Word) -> Result<()> {
+        self.output_tx
+            .send_timeout(value, Self::CHANNEL_TIMEOUT)
+            .map_err(|_| Error::OutputTimeout)
+    }
+
+    /// Execute the opcode at the current instruction pointer, performing I/O on this computer's channels.
+    pub(crate) fn step(&mut self) -> Result<()> {
+        self.step_with(&mut Channels)
+    }
+
+    /// Execute the opcode at the current instruction pointer, performing I/O via `io`.
     ///
     /// If the instruction succeeded, increment the instruction pointer appropriately.
     /// Otherwise, leave it, for debugging purposes.
-    pub(crate) fn step(&mut self) -> Result<()> {
+    pub(crate) fn step_with(&mut self, io: &mut impl Io) -> Result<()> {
+        let result = self.execute(io);
+        if result.is_err() {
+            self.devices.reset();
+        }
+        match (result, &self.source_map) {
+            (Err(err), Some(map)) if err.is_fault() => Err(Error::Located {
+                location: map.describe(self.instruction_pointer),
+                source: Box::new(err),
+            }),
+            (result, _) => result,
+        }
+    }
+
+    fn execute(&mut self, io: &mut impl Io) -> Result<()> {
         let instruction: Instruction = self.memory.ix(self.instruction_pointer)?.try_into()?;
+        self.guard.execute(
+            self.instruction_pointer,
+            1 + instruction.opcode.parameter_count(),
+        )?;
         let next_ip = match instruction.opcode {
             Opcode::Add => {
                 let (a, b, out): (_, _, &mut _) = self.parameters(instruction.modes)?;
-                *out = a + b;
+                *out = a.wrapping_add(b);
                 None
             }
             Opcode::Multiply => {
                 let (a, b, out): (_, _, &mut _) = self.parameters(instruction.modes)?;
-                *out = a * b;
+                *out = a.wrapping_mul(b);
                 None
             }
             Opcode::Input => {
-                let value = self
-                    .input_rx
-                    .recv_timeout(Self::CHANNEL_TIMEOUT)
-                    .map_err(|_| Error::InputTimeout)?;
+                let value = io.input(self)?;
                 let store: &mut _ = self.parameters(instruction.modes)?;
                 *store = value;
+                io.consume();
                 None
             }
             Opcode::Output => {
                 let value = self.parameters(instruction.modes)?;
-                self.output_tx
-                    .send_timeout(value, Self::CHANNEL_TIMEOUT)
-                    .map_err(|_| Error::OutputTimeout)?;
+                io.output(self, value)?;
                 None
             }
-            Opcode::Halt => return Err(Error::Halt(self.instruction_pointer)),
+            Opcode::Halt => {
+                if let Some(coverage) = &mut self.coverage {
+                    coverage.record(self.instruction_pointer, None);
+                }
+                return Err(Error::Halt(self.instruction_pointer));
+            }
             Opcode::JumpIfTrue => {
                 let (test, target): (_, Word) = self.parameters(instruction.modes)?;
-                (test != 0).then_some(target.try_into().map_err(|_| Error::IndexFailed(target))?)
+                (test != 0)
+                    .then(|| target.try_into().map_err(|_| Error::IndexFailed(target)))
+                    .transpose()?
             }
             Opcode::JumpIfFalse => {
                 let (test, target): (_, Word) = self.parameters(instruction.modes)?;
-                (test == 0).then_some(target.try_into().map_err(|_| Error::IndexFailed(target))?)
+                (test == 0)
+                    .then(|| target.try_into().map_err(|_| Error::IndexFailed(target)))
+                    .transpose()?
             }
             Opcode::LessThan => {
                 let (a, b, out): (_, _, &mut _) = self.parameters(instruction.modes)?;
@@ -127,10 +273,15 @@ impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
             }
             Opcode::RelativeBaseOffset => {
                 let adjust: Word = self.parameters(instruction.modes)?;
-                self.relative_base += adjust;
+                self.relative_base = self.relative_base.wrapping_add(adjust);
                 None
             }
         };
+        self.devices.flush();
+        if let Some(coverage) = &mut self.coverage {
+            let taken = instruction.opcode.jumps().then_some(next_ip.is_some());
+            coverage.record(self.instruction_pointer, taken);
+        }
 
         match next_ip {
             Some(explicit) => self.instruction_pointer = explicit,
@@ -143,20 +294,53 @@ impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
         Ok(())
     }
 
+    /// Step until the program halts, without touching the output channel afterwards.
+    pub(crate) fn run_to_halt(&mut self) -> Result<()> {
+        loop {
+            match self.step() {
+                Ok(()) => {}
+                Err(Error::Halt(_)) => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Execute the opcode at the current instruction pointer, using in-memory queues for I/O.
+    ///
+    /// `Input` consumes the front of `input`, and `Output` appends to `output`.
+    ///
+    /// Executing `Halt` produces [`Error::Halt`], and executing `Input` while `input` is empty
+    /// produces [`Error::AwaitingInput`]. As with any other error, the instruction pointer
+    /// is left in place.
+    pub fn step_io(&mut self, input: &mut VecDeque<Word>, output: &mut Vec<Word>) -> Result<()> {
+        self.step_with(&mut Queues { input, output })
+    }
+
+    /// Step using in-memory queues for I/O until the program halts, awaits input,
+    /// or has executed `limit` instructions.
+    ///
+    /// Returns the status and the number of instructions executed, including any `Halt`.
+    pub(crate) fn run_queued(
+        &mut self,
+        queues: &mut Queues<'_>,
+        limit: usize,
+    ) -> Result<(Status, usize)> {
+        for steps in 0..limit {
+            match self.step_with(queues) {
+                Ok(()) => {}
+                Err(Error::Halt(_)) => return Ok((Status::Halted, steps + 1)),
+                Err(Error::AwaitingInput) => return Ok((Status::AwaitingInput, steps)),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok((Status::Preempted, limit))
+    }
+
     /// Execute the contained program until completion.
     ///
     /// This drops the output sender on completion, for synchronization.
     pub fn run(&mut self) -> Result<()> {
-        fn inner<const CB: usize>(computer: &mut Computer<CB>) -> Result<()> {
-            loop {
-                match computer.step() {
-                    Ok(()) => {}
-                    Err(Error::Halt(_)) => return Ok(()),
-                    Err(err) => return Err(err),
-                }
-            }
-        }
-        let output = inner(self);
+        let output = self.run_to_halt();
 
         // for synchronization purposes, we have to replace the output channel now.
         let (tx, rx) = crossbeam_channel::bounded(CHANNEL_BUFFER);
@@ -237,3 +421,30 @@ impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
         })
     }
 }
+
+impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
+    /// Read the operand at `addr`, from the device mapped there if there is one.
+    #[inline]
+    pub(crate) fn operand(&mut self, addr: Word) -> Result<Word> {
+        let addr: usize = addr.try_into().map_err(|_| Error::IndexFailed(addr))?;
+        match self.devices.read(addr) {
+            Some(value) => Ok(value),
+            None => self.memory.ix(addr),
+        }
+    }
+
+    /// Where to store the operand at `addr`: the device mapped there if there is one.
+    #[inline]
+    pub(crate) fn operand_mut(&mut self, addr: Word) -> Result<&mut Word> {
+        let addr: usize = addr.try_into().map_err(|_| Error::IndexFailed(addr))?;
+        self.guard.write(self.instruction_pointer, addr)?;
+        match self.devices.latch(addr) {
+            Some(latch) => Ok(latch),
+            None => {
+                let word = self.memory.ix_mut(addr)?;
+                self.guard.written(addr);
+                Ok(word)
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Which instructions a program executed, and which way its jumps went.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Write as _},
+    str::FromStr,
+};
+
+use crate::{
+    disasm::{decode, disassemble, Line},
+    error::{Error, Result},
+    flow::{taken, Code},
+    memory::Memory,
+    source_map::SourceMap,
+    Computer,
+};
+
+/// Execution counts over a program's memory.
+///
+/// Counts are kept for each address at which an instruction was executed, and for each
+/// `JumpIfTrue` or `JumpIfFalse`, of how often it was taken and not taken. Coverage from
+/// several runs of the same program can be [merged](Self::merge).
+///
+/// Coverage can be stored as text, one entry per line: `hits ADDR COUNT` for each executed
+/// instruction, and `branch ADDR TAKEN NOT_TAKEN` for each executed jump.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    hits: Vec<u64>,
+    /// How often the jump at each address was taken, and not taken.
+    branches: Vec<[u64; 2]>,
+}
+
+fn count<T: Default + Clone>(counts: &mut Vec<T>, addr: usize) -> &mut T {
+    if counts.len() <= addr {
+        counts.resize(addr + 1, T::default());
+    }
+    &mut counts[addr]
+}
+
+impl Coverage {
+    /// Count an execution of the instruction at `addr`; for a jump, with whether it was taken.
+    pub(crate) fn record(&mut self, addr: usize, taken: Option<bool>) {
+        *count(&mut self.hits, addr) += 1;
+        if let Some(taken) = taken {
+            count(&mut self.branches, addr)[usize::from(!taken)] += 1;
+        }
+    }
+
+    /// How many times the instruction at `addr` was executed.
+    pub fn hits(&self, addr: usize) -> u64 {
+        self.hits.get(addr).copied().unwrap_or_default()
+    }
+
+    /// How many times the jump at `addr` was taken, and not taken.
+    pub fn branch(&self, addr: usize) -> [u64; 2] {
+        self.branches.get(addr).copied().unwrap_or_default()
+    }
+
+    /// The addresses of executed instructions, in order.
+    pub fn executed(&self) -> impl '_ + Iterator<Item = usize> {
+        self.hits
+            .iter()
+            .enumerate()
+            .filter(|(_, hits)| **hits > 0)
+            .map(|(addr, _)| addr)
+    }
+
+    /// Add the counts from `other`, which should be coverage of the same program.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (addr, hits) in other.hits.iter().enumerate() {
+            *count(&mut self.hits, addr) += hits;
+        }
+        for (addr, [taken, not_taken]) in other.branches.iter().enumerate() {
+            let branch = count(&mut self.branches, addr);
+            branch[0] += taken;
+            branch[1] += not_taken;
+        }
+    }
+
+    /// The addresses which hold code: every instruction found by following control flow
+    /// from address 0, and every instruction which was executed.
+    fn code(&self, program: &Memory) -> BTreeSet<usize> {
+        let mut code = Code::new(program)
+            .instructions
+            .into_keys()
+            .collect::<BTreeSet<_>>();
+        code.extend(self.executed());
+        code
+    }
+
+    /// For the conditional jump at `addr`, the times it was taken and not taken.
+    fn conditional(&self, program: &Memory, addr: usize) -> Option<[u64; 2]> {
+        decode(program, addr)
+            .filter(|decoded| decoded.opcode.jumps() && taken(decoded).is_none())
+            .map(|_| self.branch(addr))
+    }
+
+    /// A [disassembly](crate::disassemble) of `program`, with each line prefixed by how many
+    /// times it was executed, and each conditional jump followed by how often it went each way.
+    ///
+    /// As with `gcov`, code which never ran is marked `#####`, and data `-`.
+    pub fn listing(&self, program: &Memory) -> Vec<String> {
+        let code = self.code(program);
+        disassemble(program)
+            .into_iter()
+            .map(|line| {
+                let addr = line.addr();
+                let hits = match self.hits(addr) {
+                    0 if code.contains(&addr) => "#####".to_owned(),
+                    0 => "-".to_owned(),
+                    hits => hits.to_string(),
+                };
+                let mut text = format!("{hits:>8} | {line}");
+                if let Some([taken, not_taken]) = self.conditional(program, addr) {
+                    write!(text, "  ; taken {taken}, not taken {not_taken}").unwrap();
+                }
+                text
+            })
+            .collect()
+    }
+
+    /// Render this coverage of `program` in the `lcov` tracefile format.
+    ///
+    /// With a source map, lines are those of the program's source files. Otherwise they are
+    /// those of its [disassembly](crate::disassemble), as printed by `intcode disasm`, which
+    /// the tracefile calls `listing`.
+    pub fn lcov(&self, program: &Memory, map: Option<&SourceMap>, listing: &str) -> String {
+        let listing_lines = disassemble(program)
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| matches!(line, Line::Instruction(_)))
+            .map(|(idx, line)| (line.addr(), idx + 1))
+            .collect::<BTreeMap<_, _>>();
+
+        #[derive(Default)]
+        struct File {
+            lines: BTreeMap<usize, u64>,
+            branches: Vec<(usize, usize, u64, [u64; 2])>,
+        }
+        let mut files = BTreeMap::<String, File>::new();
+        for addr in self.code(program) {
+            let (name, line) = match map {
+                Some(map) => match map.location(addr) {
+                    Some(location) => (location.file.clone(), location.line),
+                    None => continue,
+                },
+                None => match listing_lines.get(&addr) {
+                    Some(line) => (listing.to_owned(), *line),
+                    None => continue,
+                },
+            };
+            let file = files.entry(name).or_default();
+            let hits = file.lines.entry(line).or_default();
+            *hits = (*hits).max(self.hits(addr));
+            if let Some(branch) = self.conditional(program, addr) {
+                file.branches.push((line, addr, self.hits(addr), branch));
+            }
+        }
+
+        let mut lcov = String::new();
+        for (name, file) in files {
+            writeln!(lcov, "TN:\nSF:{name}").unwrap();
+            let mut branches_hit = 0;
+            for (line, addr, hits, counts) in &file.branches {
+                for (idx, count) in counts.iter().enumerate() {
+                    branches_hit += usize::from(*count > 0);
+                    match hits {
+                        0 => writeln!(lcov, "BRDA:{line},{addr},{idx},-"),
+                        _ => writeln!(lcov, "BRDA:{line},{addr},{idx},{count}"),
+                    }
+                    .unwrap();
+                }
+            }
+            writeln!(lcov, "BRF:{}", 2 * file.branches.len()).unwrap();
+            writeln!(lcov, "BRH:{branches_hit}").unwrap();
+            for (line, hits) in &file.lines {
+                writeln!(lcov, "DA:{line},{hits}").unwrap();
+            }
+            let lines_hit = file.lines.values().filter(|hits| **hits > 0).count();
+            writeln!(lcov, "LF:{}", file.lines.len()).unwrap();
+            writeln!(lcov, "LH:{lines_hit}").unwrap();
+            writeln!(lcov, "end_of_record").unwrap();
+        }
+        lcov
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for addr in self.executed() {
+            writeln!(f, "hits {addr} {}", self.hits[addr])?;
+        }
+        for (addr, [taken, not_taken]) in self.branches.iter().enumerate() {
+            if taken + not_taken > 0 {
+                writeln!(f, "branch {addr} {taken} {not_taken}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Coverage {
+    type Err = Error;
+
+    /// Parse coverage in the form which it displays as; blank lines are ignored.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut coverage = Self::default();
+        for (idx, line) in s.lines().enumerate() {
+            let error = |message: String| Error::Coverage {
+                line: idx + 1,
+                message,
+            };
+            let numbers = line
+                .split_whitespace()
+                .skip(1)
+                .map(|field| {
+                    field
+                        .parse::<u64>()
+                        .map_err(|_| error(format!("invalid number {field:?}")))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let addr = |addr: u64| {
+                usize::try_from(addr).map_err(|_| error(format!("invalid address {addr}")))
+            };
+            match (line.split_whitespace().next(), numbers.as_slice()) {
+                (None, _) => {}
+                (Some("hits"), [at, hits]) => *count(&mut coverage.hits, addr(*at)?) += hits,
+                (Some("branch"), [at, taken, not_taken]) => {
+                    let branch = count(&mut coverage.branches, addr(*at)?);
+                    branch[0] += taken;
+                    branch[1] += not_taken;
+                }
+                _ => return Err(error(format!("unexpected {line:?}"))),
+            }
+        }
+        Ok(coverage)
+    }
+}
+
+impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
+    /// Start counting which instructions are executed, and which way each jump goes.
+    ///
+    /// Counts accumulate until [`take_coverage`](Self::take_coverage), including across
+    /// [`reset_from`](Self::reset_from), so several runs of one program are merged.
+    pub fn record_coverage(&mut self) {
+        self.coverage.get_or_insert_with(Coverage::default);
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Stop recording coverage, returning what was recorded.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+}
@@ -0,0 +1,1089 @@
+//! Decompilation of Intcode programs into structured, C-like pseudocode.
+//!
+//! [`decompile`] finds the code in a program by following its control flow, divides it into
+//! functions and basic blocks, and recovers `while` loops and `if`/`else` from the shape of
+//! the jumps between blocks. Whatever does not fit that shape is left as a `goto`.
+//!
+//! Functions are recognized by the calling convention which Intcode compilers use, with the
+//! relative base as a stack pointer: the caller stores the return address and the arguments
+//! in consecutive stack slots, then jumps to the function, which returns by jumping to the
+//! address stored in its first slot. A call appears as `fn_ADDR(args)`, and stack slots as
+//! `argN` and `localN`, numbered from the slot holding the return address.
+//!
+//! Memory cells which the code names often are given variable names; other cells are shown
+//! as `mem[ADDR]`.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Write as _,
+};
+
+use crate::{
+    disasm::{Decoded, Operand},
+    flow::{immediate_target, move_source, reads, taken, write, Code},
+    mem_idx::MemIdx as _,
+    memory::Memory,
+    opcode::Opcode,
+    parameter_mode::ParameterMode,
+    Word,
+};
+
+/// A memory cell named by at least this many operands gets a variable name.
+const FREQUENT: usize = 3;
+
+/// A place an operand refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Cell {
+    Mem(usize),
+    /// A slot in the current function's frame, counted from the relative base on entry.
+    Slot(Word),
+}
+
+/// How control leaves a basic block.
+#[derive(Debug, Clone)]
+enum Exit {
+    Fall(usize),
+    Jump(usize),
+    /// A conditional jump to `target`, which otherwise falls through to `next`.
+    Branch {
+        jump: Decoded,
+        target: usize,
+        next: usize,
+    },
+    Call {
+        callee: usize,
+        ret: usize,
+    },
+    /// A jump to an address read from memory.
+    Computed(Decoded),
+    Halt,
+    /// Execution runs into something which is not an instruction.
+    End(usize),
+}
+
+impl Exit {
+    fn successors(&self) -> Vec<usize> {
+        match self {
+            Exit::Fall(next) | Exit::Jump(next) | Exit::Call { ret: next, .. } => vec![*next],
+            Exit::Branch { target, next, .. } => vec![*target, *next],
+            Exit::Computed(_) | Exit::Halt | Exit::End(_) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Block {
+    instructions: Vec<Decoded>,
+    exit: Exit,
+}
+
+/// The condition under which a branch is taken.
+#[derive(Debug, Clone)]
+enum Cond {
+    NonZero(String),
+    Zero(String),
+    Compare(String, &'static str, String),
+}
+
+impl Cond {
+    fn negate(self) -> Self {
+        match self {
+            Cond::NonZero(value) => Cond::Zero(value),
+            Cond::Zero(value) => Cond::NonZero(value),
+            Cond::Compare(lhs, op, rhs) => {
+                let op = match op {
+                    "<" => ">=",
+                    ">=" => "<",
+                    "==" => "!=",
+                    _ => "==",
+                };
+                Cond::Compare(lhs, op, rhs)
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            Cond::NonZero(value) => value.clone(),
+            Cond::Zero(value) => format!("!{value}"),
+            Cond::Compare(lhs, op, rhs) => format!("{lhs} {op} {rhs}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Stmt {
+    Label(usize),
+    Line(String),
+    If {
+        cond: Cond,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+    Loop(Vec<Stmt>),
+    While {
+        cond: Cond,
+        body: Vec<Stmt>,
+    },
+    DoWhile {
+        body: Vec<Stmt>,
+        cond: Cond,
+    },
+    /// Leave the innermost loop, if the condition holds.
+    Break(Option<Cond>),
+    /// Go back to the start of the innermost loop, if the condition holds.
+    Continue(Option<Cond>),
+    Goto(usize),
+}
+
+impl Stmt {
+    /// Whether control never continues to the statement after this one.
+    fn diverges(&self) -> bool {
+        match self {
+            Stmt::Break(None) | Stmt::Continue(None) | Stmt::Goto(_) => true,
+            Stmt::Line(line) => line == "return;" || line == "halt();" || line.starts_with("goto "),
+            _ => false,
+        }
+    }
+}
+
+/// Immediate dominators of the graph with nodes `order`, in reverse postorder from the root,
+/// and predecessors `preds`; from "A Simple, Fast Dominance Algorithm" by Cooper et al.
+fn dominators(order: &[usize], preds: &HashMap<usize, Vec<usize>>) -> HashMap<usize, usize> {
+    let index = order
+        .iter()
+        .enumerate()
+        .map(|(idx, &node)| (node, idx))
+        .collect::<HashMap<_, _>>();
+    let mut idom = vec![None; order.len()];
+    idom[0] = Some(0);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (idx, node) in order.iter().enumerate().skip(1) {
+            let mut new = None;
+            for pred in preds.get(node).into_iter().flatten() {
+                let Some(&pred) = index.get(pred).filter(|&&pred| idom[pred].is_some()) else {
+                    continue;
+                };
+                new = Some(match new {
+                    None => pred,
+                    Some(mut other) => {
+                        let mut pred = pred;
+                        while pred != other {
+                            while pred > other {
+                                pred = idom[pred].unwrap();
+                            }
+                            while other > pred {
+                                other = idom[other].unwrap();
+                            }
+                        }
+                        pred
+                    }
+                });
+            }
+            if new.is_some() && idom[idx] != new {
+                idom[idx] = new;
+                changed = true;
+            }
+        }
+    }
+    idom.into_iter()
+        .enumerate()
+        .filter_map(|(idx, dom)| Some((order[idx], order[dom?])))
+        .collect()
+}
+
+/// Nodes reachable from `root` through `succs`, in reverse postorder.
+fn reverse_postorder(root: usize, succs: impl Fn(usize) -> Vec<usize>) -> Vec<usize> {
+    let mut order = Vec::new();
+    let mut seen = HashSet::from([root]);
+    let mut stack = vec![(root, succs(root).into_iter())];
+    while let Some((node, children)) = stack.last_mut() {
+        match children.next() {
+            Some(child) => {
+                if seen.insert(child) {
+                    let grandchildren = succs(child).into_iter();
+                    stack.push((child, grandchildren));
+                }
+            }
+            None => {
+                order.push(*node);
+                stack.pop();
+            }
+        }
+    }
+    order.reverse();
+    order
+}
+
+/// Whether `node` is dominated by `by`, according to `idom`.
+fn dominated(idom: &HashMap<usize, usize>, mut node: usize, by: usize) -> bool {
+    loop {
+        if node == by {
+            return true;
+        }
+        match idom.get(&node) {
+            Some(&dom) if dom != node => node = dom,
+            _ => return false,
+        }
+    }
+}
+
+struct Loop {
+    body: HashSet<usize>,
+    exit: Option<usize>,
+}
+
+/// A function: the blocks reachable from its entry without following calls.
+struct Function<'a> {
+    entry: usize,
+    blocks: &'a BTreeMap<usize, Block>,
+    /// The relative base on arrival at each instruction, relative to its value on entry.
+    deltas: HashMap<usize, Option<Word>>,
+    loops: HashMap<usize, Loop>,
+    /// Immediate post-dominators; absent for blocks from which the function never returns.
+    ipdom: HashMap<usize, usize>,
+}
+
+/// Stand-in for the single exit of a function, when computing post-dominators.
+const EXIT: usize = usize::MAX;
+
+impl<'a> Function<'a> {
+    fn new(entry: usize, blocks: &'a BTreeMap<usize, Block>) -> Self {
+        let succs = |node: usize| {
+            blocks
+                .get(&node)
+                .map(|block| {
+                    block
+                        .exit
+                        .successors()
+                        .into_iter()
+                        .filter(|next| blocks.contains_key(next))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let order = reverse_postorder(entry, succs);
+
+        let mut preds = HashMap::<_, Vec<_>>::new();
+        let mut post_preds = HashMap::<_, Vec<_>>::new();
+        for &node in &order {
+            let next = succs(node);
+            if next.is_empty() {
+                post_preds.entry(node).or_default().push(EXIT);
+            }
+            for succ in next {
+                preds.entry(succ).or_default().push(node);
+                post_preds.entry(node).or_default().push(succ);
+            }
+        }
+        let idom = dominators(&order, &preds);
+
+        let mut loops = HashMap::<usize, Loop>::new();
+        for &node in &order {
+            for header in succs(node) {
+                if !dominated(&idom, node, header) {
+                    continue;
+                }
+                let lp = loops.entry(header).or_insert_with(|| Loop {
+                    body: HashSet::from([header]),
+                    exit: None,
+                });
+                let mut pending = vec![node];
+                while let Some(member) = pending.pop() {
+                    if lp.body.insert(member) {
+                        pending.extend(preds.get(&member).into_iter().flatten().copied());
+                    }
+                }
+            }
+        }
+        for (&header, lp) in &mut loops {
+            let exits = |node: usize| succs(node).into_iter().filter(|n| !lp.body.contains(n));
+            lp.exit = exits(header)
+                .next()
+                .or_else(|| lp.body.iter().flat_map(|&node| exits(node)).min());
+        }
+
+        // post-dominators are dominators of the reversed graph, rooted at the exit
+        let mut post_succs = HashMap::<_, Vec<_>>::new();
+        for (&node, nodes) in &post_preds {
+            for &succ in nodes {
+                post_succs.entry(succ).or_default().push(node);
+            }
+        }
+        let post_order = reverse_postorder(EXIT, |node| {
+            post_succs.get(&node).cloned().unwrap_or_default()
+        });
+        let ipdom = dominators(&post_order, &post_preds)
+            .into_iter()
+            .filter(|&(node, dom)| node != EXIT && dom != EXIT)
+            .collect();
+
+        let mut function = Self {
+            entry,
+            blocks,
+            deltas: HashMap::new(),
+            loops,
+            ipdom,
+        };
+        function.deltas = function.relative_deltas(&order);
+        function
+    }
+
+    /// Track the relative base through the function, assuming calls preserve it.
+    fn relative_deltas(&self, order: &[usize]) -> HashMap<usize, Option<Word>> {
+        let mut at_block = HashMap::from([(self.entry, Some(0))]);
+        let mut deltas = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &start in order {
+                let Some(&delta) = at_block.get(&start) else {
+                    continue;
+                };
+                let block = &self.blocks[&start];
+                let mut delta: Option<Word> = delta;
+                for decoded in &block.instructions {
+                    deltas.insert(decoded.addr, delta);
+                    if decoded.opcode == Opcode::RelativeBaseOffset {
+                        let adjust = decoded.operands[0];
+                        delta = delta
+                            .filter(|_| adjust.mode == ParameterMode::Immediate)
+                            .map(|delta| delta.wrapping_add(adjust.value));
+                    }
+                }
+                for succ in block.exit.successors() {
+                    let merged = match at_block.get(&succ) {
+                        None => delta,
+                        Some(&known) if known == delta => continue,
+                        Some(_) => None,
+                    };
+                    if at_block.insert(succ, merged) != Some(merged) {
+                        changed = true;
+                    }
+                }
+            }
+        }
+        deltas
+    }
+
+    /// Whether `block` ends by returning from the function.
+    fn returns(&self, block: &Block) -> bool {
+        match &block.exit {
+            Exit::Computed(jump) => {
+                self.entry != 0 && self.cell(jump.addr, &jump.operands[1]) == Some(Cell::Slot(0))
+            }
+            _ => false,
+        }
+    }
+
+    /// The cell `operand` refers to in the instruction at `addr`, if it refers to one.
+    fn cell(&self, addr: usize, operand: &Operand) -> Option<Cell> {
+        match operand.mode {
+            ParameterMode::Position => usize::try_from(operand.value).ok().map(Cell::Mem),
+            ParameterMode::Immediate => None,
+            ParameterMode::Relative => {
+                let delta = (*self.deltas.get(&addr)?)?;
+                let slot = delta.wrapping_add(operand.value);
+                // before any call, the relative base is zero
+                if self.entry == 0 {
+                    usize::try_from(slot).ok().map(Cell::Mem)
+                } else {
+                    Some(Cell::Slot(slot))
+                }
+            }
+        }
+    }
+}
+
+/// Everything needed to name things throughout the program.
+struct Decompiler<'a> {
+    program: &'a Memory,
+    blocks: BTreeMap<usize, Block>,
+    globals: BTreeMap<usize, String>,
+    /// The number of arguments each function is called with.
+    arity: BTreeMap<usize, usize>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(program: &'a Memory) -> Self {
+        let code = Code::new(program);
+
+        let mut leaders = code.leaders.clone();
+        let mut entries = BTreeSet::from([0]);
+        for decoded in code.instructions.values() {
+            if taken(decoded) == Some(true) && code.return_points.contains(&decoded.next_addr()) {
+                entries.extend(immediate_target(decoded));
+            }
+        }
+        leaders.extend(entries.iter().copied());
+
+        let mut blocks = BTreeMap::new();
+        for &leader in &leaders {
+            let mut instructions = Vec::new();
+            let mut addr = leader;
+            let exit = loop {
+                let Some(decoded) = code.instructions.get(&addr) else {
+                    break Exit::End(addr);
+                };
+                instructions.push(decoded.clone());
+                addr = decoded.next_addr();
+                if decoded.opcode == Opcode::Halt {
+                    break Exit::Halt;
+                }
+                if decoded.opcode.jumps() {
+                    break match (taken(decoded), immediate_target(decoded)) {
+                        (Some(false), _) => Exit::Fall(addr),
+                        (Some(true), Some(target)) if code.return_points.contains(&addr) => {
+                            Exit::Call {
+                                callee: target,
+                                ret: addr,
+                            }
+                        }
+                        (Some(true), Some(target)) => Exit::Jump(target),
+                        (None, Some(target)) => Exit::Branch {
+                            jump: decoded.clone(),
+                            target,
+                            next: addr,
+                        },
+                        (_, None) => Exit::Computed(decoded.clone()),
+                    };
+                }
+                if leaders.contains(&addr) {
+                    break Exit::Fall(addr);
+                }
+            };
+            if !instructions.is_empty() || leader == 0 {
+                blocks.insert(leader, Block { instructions, exit });
+            }
+        }
+
+        let code_words = code
+            .instructions
+            .values()
+            .flat_map(|decoded| decoded.addr..decoded.next_addr())
+            .collect::<HashSet<_>>();
+        let mut references = BTreeMap::<usize, usize>::new();
+        for decoded in code.instructions.values() {
+            for operand in &decoded.operands {
+                if let Some(addr) = (operand.mode == ParameterMode::Position)
+                    .then(|| usize::try_from(operand.value).ok())
+                    .flatten()
+                    .filter(|addr| !code_words.contains(addr))
+                {
+                    *references.entry(addr).or_default() += 1;
+                }
+            }
+        }
+        let globals = references
+            .into_iter()
+            .filter(|&(_, count)| count >= FREQUENT)
+            .map(|(addr, _)| (addr, format!("var_{addr}")))
+            .collect();
+
+        let mut decompiler = Self {
+            program,
+            blocks,
+            globals,
+            arity: entries.iter().map(|&entry| (entry, 0)).collect(),
+        };
+        for &entry in &entries {
+            let function = Function::new(entry, &decompiler.blocks);
+            for start in function.deltas.keys() {
+                let Some(block) = decompiler.blocks.get(start) else {
+                    continue;
+                };
+                if let Exit::Call { callee, .. } = block.exit {
+                    let args = decompiler.call_arguments(&function, block).len();
+                    let arity = decompiler.arity.entry(callee).or_default();
+                    *arity = (*arity).max(args);
+                }
+            }
+        }
+        decompiler
+    }
+
+    /// The callee's frame slot which `operand` of the instruction at `addr` writes, for a call
+    /// made by `block`.
+    fn callee_slot(&self, function: &Function, block: &Block, decoded: &Decoded) -> Option<Word> {
+        let jump = block.instructions.last()?;
+        let at_call = (*function.deltas.get(&jump.addr)?)?;
+        let operand = write(decoded)?;
+        if operand.mode != ParameterMode::Relative {
+            return None;
+        }
+        let delta = (*function.deltas.get(&decoded.addr)?)?;
+        Some(delta.wrapping_add(operand.value).wrapping_sub(at_call))
+    }
+
+    /// The instructions in `block` which store the arguments of the call it ends with, in order.
+    fn call_arguments<'b>(&self, function: &Function, block: &'b Block) -> Vec<&'b Decoded> {
+        let mut args = BTreeMap::new();
+        for decoded in &block.instructions {
+            match self.callee_slot(function, block, decoded) {
+                Some(slot) if slot > 0 => {
+                    args.insert(slot, decoded);
+                }
+                _ => {}
+            }
+        }
+        // only consecutive slots from the first count
+        args.into_iter()
+            .enumerate()
+            .take_while(|(idx, (slot, _))| *slot == *idx as Word + 1)
+            .map(|(_, (_, decoded))| decoded)
+            .collect()
+    }
+
+    fn function_name(&self, entry: usize) -> String {
+        if entry == 0 {
+            "start".into()
+        } else {
+            format!("fn_{entry}")
+        }
+    }
+
+    fn cell_name(&self, function: &Function, cell: Cell) -> String {
+        match cell {
+            Cell::Mem(addr) => self
+                .globals
+                .get(&addr)
+                .cloned()
+                .unwrap_or_else(|| format!("mem[{addr}]")),
+            Cell::Slot(0) => "ret".into(),
+            Cell::Slot(slot) if slot < 0 => format!("caller[{slot}]"),
+            Cell::Slot(slot) if slot as usize <= self.arity[&function.entry] => {
+                format!("arg{slot}")
+            }
+            Cell::Slot(slot) => format!("local{slot}"),
+        }
+    }
+
+    fn operand(&self, function: &Function, addr: usize, operand: &Operand) -> String {
+        match function.cell(addr, operand) {
+            Some(cell) => self.cell_name(function, cell),
+            None if operand.mode == ParameterMode::Immediate => operand.value.to_string(),
+            None if operand.value < 0 => format!("rb[{}]", operand.value),
+            None => format!("rb[+{}]", operand.value),
+        }
+    }
+
+    /// The value `decoded` computes, as an expression.
+    fn expression(&self, function: &Function, decoded: &Decoded) -> String {
+        let operand = |idx: usize| self.operand(function, decoded.addr, &decoded.operands[idx]);
+        if let Some(source) = move_source(decoded) {
+            return operand(source);
+        }
+        let [_, b, _] = decoded.operands.as_slice() else {
+            unreachable!("only called for instructions with three operands")
+        };
+        match decoded.opcode {
+            Opcode::Add if b.mode == ParameterMode::Immediate && b.value < 0 => {
+                format!("{} - {}", operand(0), b.value.unsigned_abs())
+            }
+            Opcode::Add => format!("{} + {}", operand(0), operand(1)),
+            Opcode::Multiply if b.mode == ParameterMode::Immediate && b.value == -1 => {
+                format!("-{}", operand(0))
+            }
+            Opcode::Multiply => format!("{} * {}", operand(0), operand(1)),
+            Opcode::LessThan => format!("{} < {}", operand(0), operand(1)),
+            Opcode::Equals => format!("{} == {}", operand(0), operand(1)),
+            _ => unreachable!("only arithmetic and comparisons have three operands"),
+        }
+    }
+
+    fn statement(&self, function: &Function, decoded: &Decoded) -> Option<String> {
+        let operand = |idx: usize| self.operand(function, decoded.addr, &decoded.operands[idx]);
+        Some(match decoded.opcode {
+            _ if write(decoded).is_some_and(|op| op.mode == ParameterMode::Immediate) => {
+                format!("fault(); // {decoded} writes to an immediate")
+            }
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => {
+                format!("{} = {};", operand(2), self.expression(function, decoded))
+            }
+            Opcode::Input => format!("{} = input();", operand(0)),
+            Opcode::Output => format!("output({});", operand(0)),
+            Opcode::RelativeBaseOffset
+                if function
+                    .deltas
+                    .get(&decoded.addr)
+                    .copied()
+                    .flatten()
+                    .is_some() =>
+            {
+                // the relative base is tracked, so its adjustments show in the slot names
+                return None;
+            }
+            Opcode::RelativeBaseOffset => format!("rb += {};", operand(0)),
+            Opcode::Halt => "halt();".into(),
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => unreachable!("jumps end blocks"),
+        })
+    }
+
+    /// The condition under which `jump` is taken, and the addresses of the instructions
+    /// which computed it, if nothing else needs their results.
+    ///
+    /// Comparisons are followed back through any number of `x == 0` which negate them.
+    fn condition(&self, function: &Function, block: &Block, jump: &Decoded) -> (Cond, Vec<usize>) {
+        let mut cell = function.cell(jump.addr, &jump.operands[0]);
+        let mut negated = jump.opcode == Opcode::JumpIfFalse;
+        let mut computed = Vec::new();
+        let mut compare = None;
+        for previous in block.instructions.iter().rev().skip(1) {
+            let is_comparison = matches!(previous.opcode, Opcode::LessThan | Opcode::Equals)
+                && move_source(previous).is_none();
+            if cell.is_none()
+                || !is_comparison
+                || function.cell(previous.addr, &previous.operands[2]) != cell
+            {
+                break;
+            }
+            computed.push(previous.addr);
+            let [a, b, _] = previous.operands.as_slice() else {
+                unreachable!("comparisons have three operands")
+            };
+            let operand = |op: &Operand| self.operand(function, previous.addr, op);
+            if previous.opcode == Opcode::Equals
+                && b.mode == ParameterMode::Immediate
+                && b.value == 0
+            {
+                negated = !negated;
+                cell = function.cell(previous.addr, a);
+                continue;
+            }
+            let op = if previous.opcode == Opcode::LessThan {
+                "<"
+            } else {
+                "=="
+            };
+            compare = Some(Cond::Compare(operand(a), op, operand(b)));
+            break;
+        }
+
+        let cond = match compare {
+            Some(compare) => compare,
+            None => {
+                // only the negations were found, so test the value they negate
+                let tested = match computed.last() {
+                    Some(&addr) => {
+                        let previous = block.instructions.iter().find(|d| d.addr == addr).unwrap();
+                        self.operand(function, addr, &previous.operands[0])
+                    }
+                    None => self.operand(function, jump.addr, &jump.operands[0]),
+                };
+                Cond::NonZero(tested)
+            }
+        };
+        let cond = if negated { cond.negate() } else { cond };
+
+        // the intermediate results may be left out only if they are dead temporaries
+        let dead = computed.iter().all(|&addr| {
+            let decoded = block.instructions.iter().find(|d| d.addr == addr).unwrap();
+            match function.cell(addr, &decoded.operands[2]) {
+                Some(cell @ Cell::Slot(slot)) if slot as usize > self.arity[&function.entry] => {
+                    self.dead_after(function, block, cell)
+                }
+                _ => false,
+            }
+        });
+        if !dead {
+            computed.clear();
+        }
+        (cond, computed)
+    }
+
+    /// Whether `cell` is certainly overwritten or discarded before it is next read, once
+    /// control leaves `block`.
+    fn dead_after(&self, function: &Function, block: &Block, cell: Cell) -> bool {
+        block.exit.successors().into_iter().all(|next| {
+            // follow the path on while it does not branch
+            let mut seen = HashSet::new();
+            let mut next = Some(next);
+            while let Some(successor) = next.filter(|&next| seen.insert(next)) {
+                let Some(successor) = function.blocks.get(&successor) else {
+                    return false;
+                };
+                for decoded in &successor.instructions {
+                    let refers = |op: &Operand| function.cell(decoded.addr, op) == Some(cell);
+                    if reads(decoded).iter().any(refers) {
+                        return false;
+                    }
+                    if write(decoded).is_some_and(refers) {
+                        return true;
+                    }
+                }
+                // the temporaries of a function do not outlive it
+                if matches!(successor.exit, Exit::Halt) || function.returns(successor) {
+                    return true;
+                }
+                next = match successor.exit {
+                    Exit::Fall(next) | Exit::Jump(next) | Exit::Call { ret: next, .. } => {
+                        Some(next)
+                    }
+                    _ => None,
+                };
+            }
+            false
+        })
+    }
+
+    /// Emit the statements of `block`, except any which `skip` says to leave out.
+    fn block_statements(
+        &self,
+        function: &Function,
+        block: &Block,
+        skip: &[usize],
+        out: &mut Vec<Stmt>,
+    ) {
+        for decoded in &block.instructions {
+            if decoded.opcode.jumps() || skip.contains(&decoded.addr) {
+                continue;
+            }
+            if let Some(line) = self.statement(function, decoded) {
+                out.push(Stmt::Line(line));
+            }
+        }
+    }
+}
+
+/// The structuring of one function into statements.
+struct Structurer<'a, 'b> {
+    decompiler: &'a Decompiler<'b>,
+    function: &'a Function<'a>,
+    emitted: HashSet<usize>,
+    /// Headers of the loops being emitted, innermost last.
+    loops: Vec<usize>,
+}
+
+impl Structurer<'_, '_> {
+    fn region(&mut self, mut cur: Option<usize>, stop: Option<usize>, out: &mut Vec<Stmt>) {
+        while let Some(start) = cur {
+            if Some(start) == stop {
+                return;
+            }
+            if let Some(&header) = self.loops.last() {
+                if start == header && self.emitted.contains(&start) {
+                    out.push(Stmt::Continue(None));
+                    return;
+                }
+                if Some(start) == self.function.loops[&header].exit {
+                    out.push(Stmt::Break(None));
+                    return;
+                }
+            }
+            if self.emitted.contains(&start) {
+                out.push(Stmt::Goto(start));
+                return;
+            }
+            let Some(block) = self.function.blocks.get(&start) else {
+                out.push(Stmt::Line(format!("goto {start}; // not code")));
+                return;
+            };
+            if self.function.loops.contains_key(&start) && !self.loops.contains(&start) {
+                self.loops.push(start);
+                let mut body = Vec::new();
+                self.region(Some(start), None, &mut body);
+                self.loops.pop();
+                out.push(Stmt::Loop(body));
+                cur = self.function.loops[&start].exit;
+                continue;
+            }
+
+            self.emitted.insert(start);
+            out.push(Stmt::Label(start));
+            cur = self.exit(block, stop, out);
+        }
+    }
+
+    /// Emit `block` and the way it exits, returning the block which follows, if any.
+    fn exit(&mut self, block: &Block, stop: Option<usize>, out: &mut Vec<Stmt>) -> Option<usize> {
+        let decompiler = self.decompiler;
+        let function = self.function;
+        match &block.exit {
+            Exit::Fall(next) | Exit::Jump(next) => {
+                decompiler.block_statements(function, block, &[], out);
+                Some(*next)
+            }
+            Exit::Call { callee, ret } => {
+                let args = decompiler.call_arguments(function, block);
+                let skip = block
+                    .instructions
+                    .iter()
+                    .filter(|decoded| {
+                        decompiler.callee_slot(function, block, decoded) == Some(0)
+                            || args.iter().any(|arg| arg.addr == decoded.addr)
+                    })
+                    .map(|decoded| decoded.addr)
+                    .collect::<Vec<_>>();
+                decompiler.block_statements(function, block, &skip, out);
+                let args = args
+                    .iter()
+                    .map(|arg| match arg.opcode {
+                        Opcode::Input => "input()".into(),
+                        _ => decompiler.expression(function, arg),
+                    })
+                    .collect::<Vec<_>>();
+                out.push(Stmt::Line(format!(
+                    "{}({});",
+                    decompiler.function_name(*callee),
+                    args.join(", ")
+                )));
+                Some(*ret)
+            }
+            Exit::Branch { jump, target, next } => {
+                let (cond, computed) = decompiler.condition(function, block, jump);
+                decompiler.block_statements(function, block, &computed, out);
+
+                // a branch straight out of, or back to the start of, the innermost loop
+                if let Some(&header) = self.loops.last() {
+                    let exit = function.loops[&header].exit;
+                    for (to, other, cond) in [
+                        (*target, *next, &cond),
+                        (*next, *target, &cond.clone().negate()),
+                    ] {
+                        if to == header {
+                            out.push(Stmt::Continue(Some(cond.clone())));
+                            return Some(other);
+                        }
+                        if Some(to) == exit {
+                            out.push(Stmt::Break(Some(cond.clone())));
+                            return Some(other);
+                        }
+                    }
+                }
+
+                let join = function.ipdom.get(&block.instructions[0].addr).copied();
+                let join = join.filter(|&join| Some(join) != stop || stop.is_some());
+                let mut then = Vec::new();
+                let mut otherwise = Vec::new();
+                self.region(Some(*next), join, &mut then);
+                self.region(Some(*target), join, &mut otherwise);
+                out.push(Stmt::If {
+                    cond: cond.negate(),
+                    then,
+                    otherwise,
+                });
+                join
+            }
+            Exit::Computed(jump) => {
+                decompiler.block_statements(function, block, &[], out);
+                let target = &jump.operands[1];
+                let line = if function.returns(block) {
+                    "return;".into()
+                } else {
+                    format!("goto *{};", decompiler.operand(function, jump.addr, target))
+                };
+                match taken(jump) {
+                    Some(true) => out.push(Stmt::Line(line)),
+                    _ => {
+                        let (cond, _) = decompiler.condition(function, block, jump);
+                        out.push(Stmt::Line(format!("if ({}) {line}", cond.render())));
+                        return Some(jump.next_addr());
+                    }
+                }
+                None
+            }
+            Exit::Halt => {
+                decompiler.block_statements(function, block, &[], out);
+                None
+            }
+            Exit::End(addr) => {
+                decompiler.block_statements(function, block, &[], out);
+                out.push(Stmt::Line(format!("// runs into data at {addr}")));
+                None
+            }
+        }
+    }
+}
+
+/// Whether `stmts` does nothing, apart from holding labels.
+fn is_empty(stmts: &[Stmt]) -> bool {
+    stmts.iter().all(|stmt| matches!(stmt, Stmt::Label(_)))
+}
+
+/// Tidy up the statements recovered from the jump structure.
+fn simplify(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut out = Vec::new();
+    for stmt in stmts {
+        match stmt {
+            Stmt::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                let (cond, then, otherwise) = match (simplify(then), simplify(otherwise)) {
+                    (then, otherwise) if is_empty(&then) && !is_empty(&otherwise) => {
+                        out.extend(then);
+                        (cond.negate(), otherwise, Vec::new())
+                    }
+                    (then, otherwise) => (cond, then, otherwise),
+                };
+                // `if (cond) { ...; return; } else { ... }` needs no `else`
+                let flatten = then.iter().rev().find(|s| !matches!(s, Stmt::Label(_)));
+                if flatten.is_some_and(Stmt::diverges) {
+                    out.push(Stmt::If {
+                        cond,
+                        then,
+                        otherwise: Vec::new(),
+                    });
+                    out.extend(otherwise);
+                } else {
+                    out.push(Stmt::If {
+                        cond,
+                        then,
+                        otherwise,
+                    });
+                }
+            }
+            Stmt::Loop(body) => {
+                let mut body = simplify(body);
+                if matches!(body.last(), Some(Stmt::Continue(None))) {
+                    body.pop();
+                }
+                let first = body.iter().position(|s| !matches!(s, Stmt::Label(_)));
+                let len = body.len();
+                match (first.map(|idx| &body[idx]), &body[len.saturating_sub(2)..]) {
+                    // `while (1) { if (cond) break; ... }` is `while (!cond) { ... }`
+                    (Some(Stmt::Break(Some(_))), _) => {
+                        let idx = first.unwrap();
+                        out.extend(body.drain(..idx));
+                        let Stmt::Break(Some(cond)) = body.remove(0) else {
+                            unreachable!()
+                        };
+                        out.push(Stmt::While {
+                            cond: cond.negate(),
+                            body,
+                        });
+                    }
+                    // `while (1) { ...; if (cond) continue; break; }` is `do { ... } while (cond)`
+                    (_, [Stmt::Continue(Some(_)), Stmt::Break(None)]) => {
+                        body.pop();
+                        let Some(Stmt::Continue(Some(cond))) = body.pop() else {
+                            unreachable!()
+                        };
+                        out.push(Stmt::DoWhile { body, cond });
+                    }
+                    _ => out.push(Stmt::Loop(body)),
+                }
+            }
+            stmt => out.push(stmt),
+        }
+    }
+    out
+}
+
+/// Render `stmts`, printing only the labels which some `goto` refers to.
+fn render(stmts: &[Stmt], targets: &HashSet<usize>, depth: usize, out: &mut String) {
+    let indent = "    ".repeat(depth);
+    let conditional = |cond: &Option<Cond>, what: &str| match cond {
+        Some(cond) => format!("{indent}if ({}) {what};", cond.render()),
+        None => format!("{indent}{what};"),
+    };
+    for stmt in stmts {
+        match stmt {
+            Stmt::Label(addr) if targets.contains(addr) => {
+                let _ = writeln!(out, "{}L{addr}:", "    ".repeat(depth.saturating_sub(1)));
+            }
+            Stmt::Label(_) => {}
+            Stmt::Line(line) => {
+                let _ = writeln!(out, "{indent}{line}");
+            }
+            Stmt::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                let _ = writeln!(out, "{indent}if ({}) {{", cond.render());
+                render(then, targets, depth + 1, out);
+                if !is_empty(otherwise) {
+                    let _ = writeln!(out, "{indent}}} else {{");
+                    render(otherwise, targets, depth + 1, out);
+                }
+                let _ = writeln!(out, "{indent}}}");
+            }
+            Stmt::Loop(body) => {
+                let _ = writeln!(out, "{indent}while (1) {{");
+                render(body, targets, depth + 1, out);
+                let _ = writeln!(out, "{indent}}}");
+            }
+            Stmt::While { cond, body } => {
+                let _ = writeln!(out, "{indent}while ({}) {{", cond.render());
+                render(body, targets, depth + 1, out);
+                let _ = writeln!(out, "{indent}}}");
+            }
+            Stmt::DoWhile { body, cond } => {
+                let _ = writeln!(out, "{indent}do {{");
+                render(body, targets, depth + 1, out);
+                let _ = writeln!(out, "{indent}}} while ({});", cond.render());
+            }
+            Stmt::Break(cond) => {
+                let _ = writeln!(out, "{}", conditional(cond, "break"));
+            }
+            Stmt::Continue(cond) => {
+                let _ = writeln!(out, "{}", conditional(cond, "continue"));
+            }
+            Stmt::Goto(addr) => {
+                let _ = writeln!(out, "{indent}goto L{addr};");
+            }
+        }
+    }
+}
+
+fn goto_targets(stmts: &[Stmt], targets: &mut HashSet<usize>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Goto(addr) => {
+                targets.insert(*addr);
+            }
+            Stmt::If {
+                then, otherwise, ..
+            } => {
+                goto_targets(then, targets);
+                goto_targets(otherwise, targets);
+            }
+            Stmt::Loop(body) | Stmt::While { body, .. } | Stmt::DoWhile { body, .. } => {
+                goto_targets(body, targets)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Decompile `program` into pseudocode, as described in the [module documentation](self).
+pub fn decompile(program: &Memory) -> String {
+    let decompiler = Decompiler::new(program);
+    let mut out = String::new();
+
+    for (&addr, name) in &decompiler.globals {
+        let value = decompiler.program.ix(addr).unwrap_or_default();
+        let _ = writeln!(out, "int {name} = {value};");
+    }
+
+    for (&entry, &arity) in &decompiler.arity {
+        let function = Function::new(entry, &decompiler.blocks);
+        let mut structurer = Structurer {
+            decompiler: &decompiler,
+            function: &function,
+            emitted: HashSet::new(),
+            loops: Vec::new(),
+        };
+        let mut stmts = Vec::new();
+        structurer.region(Some(entry), None, &mut stmts);
+        let stmts = simplify(stmts);
+
+        let params = (1..=arity)
+            .map(|idx| format!("int arg{idx}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "void {}({params}) {{", decompiler.function_name(entry));
+        let mut targets = HashSet::new();
+        goto_targets(&stmts, &mut targets);
+        render(&stmts, &targets, 1, &mut out);
+        out.push_str("}\n");
+    }
+    out
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+
+use crate::{
+    instruction::Instruction, mem_idx::MemIdx as _, memory::Memory, opcode::Opcode,
+    parameter_mode::ParameterMode, source_map::SourceMap, Word,
+};
+
+/// A single operand of a decoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Operand {
+    pub mode: ParameterMode,
+    pub value: Word,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            ParameterMode::Position => write!(f, "[{}]", self.value),
+            ParameterMode::Immediate => write!(f, "#{}", self.value),
+            ParameterMode::Relative if self.value < 0 => write!(f, "[rb{}]", self.value),
+            ParameterMode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+/// An instruction decoded from memory, without executing it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Decoded {
+    pub addr: usize,
+    pub opcode: Opcode,
+    pub operands: Vec<Operand>,
+}
+
+impl Decoded {
+    /// The number of words this instruction occupies.
+    pub fn size(&self) -> usize {
+        1 + self.operands.len()
+    }
+
+    /// The address of the instruction which follows this one in memory.
+    pub fn next_addr(&self) -> usize {
+        self.addr + self.size()
+    }
+
+    /// Encode this instruction as the words it occupies in memory.
+    pub fn words(&self) -> Vec<Word> {
+        let modes = self
+            .operands
+            .iter()
+            .rev()
+            .fold(0, |modes, operand| modes * 10 + operand.mode as Word);
+        std::iter::once(self.opcode as Word + 100 * modes)
+            .chain(self.operands.iter().map(|operand| operand.value))
+            .collect()
+    }
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.opcode.mnemonic())?;
+        for (idx, operand) in self.operands.iter().enumerate() {
+            let sep = if idx == 0 { " " } else { ", " };
+            write!(f, "{sep}{operand}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the instruction at `addr`, if the word there is a valid instruction
+/// and all its operands lie within memory.
+pub fn decode(memory: &Memory, addr: usize) -> Option<Decoded> {
+    let instruction = Instruction::try_from(memory.ix(addr).ok()?).ok()?;
+    let n_params = instruction.opcode.parameter_count();
+    if addr + n_params >= memory.len() {
+        return None;
+    }
+    let operands = (0..n_params)
+        .map(|idx| {
+            Some(Operand {
+                mode: instruction.modes[idx],
+                value: memory.ix(addr + 1 + idx).ok()?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(Decoded {
+        addr,
+        opcode: instruction.opcode,
+        operands,
+    })
+}
+
+/// One line of a disassembly listing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Line {
+    Instruction(Decoded),
+    Data { addr: usize, value: Word },
+}
+
+impl Line {
+    pub fn addr(&self) -> usize {
+        match self {
+            Line::Instruction(decoded) => decoded.addr,
+            Line::Data { addr, .. } => *addr,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Instruction(decoded) => write!(f, "{:>6}: {decoded}", decoded.addr),
+            Line::Data { addr, value } => write!(f, "{addr:>6}: data {value}"),
+        }
+    }
+}
+
+/// Disassemble `memory` with a linear sweep from address 0.
+///
+/// Every word which can be decoded as the start of an instruction is; anything else is data.
+/// Intcode does not distinguish code from data, so data which happens to look like an
+/// instruction will be listed as one.
+pub fn disassemble(memory: &Memory) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < memory.len() {
+        match decode(memory, addr) {
+            Some(decoded) => {
+                addr = decoded.next_addr();
+                lines.push(Line::Instruction(decoded));
+            }
+            None => {
+                let value = memory.ix(addr).unwrap_or_default();
+                lines.push(Line::Data { addr, value });
+                addr += 1;
+            }
+        }
+    }
+    lines
+}
+
+/// Disassemble `memory` as [`disassemble`] does, with each label in `map` on a line of its
+/// own, and each instruction or word followed by the source line it came from.
+pub fn annotated_listing(memory: &Memory, map: &SourceMap) -> Vec<String> {
+    let mut labels = map.labels().peekable();
+    let mut listing = Vec::new();
+    for line in disassemble(memory) {
+        let (start, end) = match &line {
+            Line::Instruction(decoded) => (decoded.addr, decoded.next_addr()),
+            Line::Data { addr, .. } => (*addr, addr + 1),
+        };
+        while let Some((addr, name)) = labels.next_if(|(addr, _)| *addr < end) {
+            // a label inside an instruction gets its exact address
+            if addr == start {
+                listing.push(format!("{name}:"));
+            } else {
+                listing.push(format!("{name}: ; at {addr}"));
+            }
+        }
+        listing.push(match map.location(start) {
+            Some(location) => format!("{:<40} ; {location}", line.to_string()),
+            None => line.to_string(),
+        });
+    }
+    listing.extend(labels.map(|(addr, name)| format!("{name}: ; at {addr}")));
+    listing
+}
@@ -8,6 +8,8 @@ pub enum Error {
     UnknownOpcode(Word),
     #[error("unknown parameter mode: `{0}`")]
     UnknownParameterMode(Word),
+    #[error("instruction `{0}` has more parameter modes than any opcode has parameters")]
+    TooManyParameterModes(Word),
     #[error("attempted to access position {idx} but max capacity is {len}")]
     MemoryExhausted { idx: usize, len: usize },
     #[error("failed to convert `Int` value ({0}) to `usize` for indexing")]
@@ -20,4 +22,62 @@ pub enum Error {
     InputTimeout,
     #[error("timeout while attempting to send output")]
     OutputTimeout,
+    #[error("no input available")]
+    AwaitingInput,
+    #[error(
+        "deadlock: every running computer is awaiting input at (node, instruction pointer) {0:?}"
+    )]
+    Deadlock(Vec<(usize, usize)>),
+    #[error("packet sent to unknown network address {0}")]
+    UnknownAddress(Word),
+    #[error("network is idle and no device responded")]
+    NetworkIdle,
+    #[error("cannot send non-ASCII character {0:?}")]
+    NonAscii(char),
+    #[error("invalid word {token:?} at line {line}, column {column}")]
+    Parse {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    #[error("assembly error at line {line}: {message}")]
+    Assemble { line: usize, message: String },
+    #[error("link error: {0}")]
+    Link(String),
+    #[error("write to read-only address {addr} at instruction pointer {ip}")]
+    WriteProtected { ip: usize, addr: usize },
+    #[error("execution of no-execute address {addr} at instruction pointer {ip}")]
+    ExecuteProtected { ip: usize, addr: usize },
+    #[error("device at {start}..{end} overlaps one which is already attached")]
+    DeviceOverlap { start: usize, end: usize },
+    #[error("source map error at line {line}: {message}")]
+    SourceMap { line: usize, message: String },
+    #[error("coverage error at line {line}: {message}")]
+    Coverage { line: usize, message: String },
+    /// A fault, with where it happened according to the computer's source map.
+    #[error("{source} at {location}")]
+    Located {
+        location: String,
+        source: Box<Error>,
+    },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    /// Whether this is a fault in the program itself, rather than a condition of its
+    /// surroundings such as halting or waiting for input.
+    pub fn is_fault(&self) -> bool {
+        matches!(
+            self,
+            Error::UnknownOpcode(_)
+                | Error::UnknownParameterMode(_)
+                | Error::TooManyParameterModes(_)
+                | Error::MemoryExhausted { .. }
+                | Error::IndexFailed(_)
+                | Error::ImmediateWrite
+                | Error::WriteProtected { .. }
+                | Error::ExecuteProtected { .. }
+        )
+    }
 }
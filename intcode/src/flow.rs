@@ -0,0 +1,202 @@
+//! Recovery of the code in a program, by following its control flow.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::{
+    disasm::{decode, Decoded, Operand},
+    mem_idx::MemIdx as _,
+    memory::Memory,
+    opcode::Opcode,
+    parameter_mode::ParameterMode,
+    Word,
+};
+
+/// The operands which `decoded` reads, as opposed to the one it writes.
+pub(crate) fn reads(decoded: &Decoded) -> &[Operand] {
+    let n_reads = decoded.operands.len() - usize::from(decoded.opcode.writes());
+    &decoded.operands[..n_reads]
+}
+
+/// The operand which `decoded` writes, if any.
+pub(crate) fn write(decoded: &Decoded) -> Option<&Operand> {
+    decoded
+        .opcode
+        .writes()
+        .then(|| decoded.operands.last())
+        .flatten()
+}
+
+/// The address of a position-mode operand, if it lies within `len`.
+pub(crate) fn position(operand: &Operand, len: usize) -> Option<usize> {
+    (operand.mode == ParameterMode::Position)
+        .then(|| usize::try_from(operand.value).ok())
+        .flatten()
+        .filter(|&addr| addr < len)
+}
+
+/// For a jump whose condition is immediate, whether it is taken.
+pub(crate) fn taken(decoded: &Decoded) -> Option<bool> {
+    let condition = decoded.operands.first()?;
+    if !decoded.opcode.jumps() || condition.mode != ParameterMode::Immediate {
+        return None;
+    }
+    Some((condition.value != 0) == (decoded.opcode == Opcode::JumpIfTrue))
+}
+
+pub(crate) fn immediate_target(decoded: &Decoded) -> Option<usize> {
+    match decoded.operands.as_slice() {
+        [_, target] if decoded.opcode.jumps() && target.mode == ParameterMode::Immediate => {
+            usize::try_from(target.value).ok()
+        }
+        _ => None,
+    }
+}
+
+/// If `decoded` copies a value unchanged, the index of the operand it copies.
+pub(crate) fn move_source(decoded: &Decoded) -> Option<usize> {
+    let identity = match decoded.opcode {
+        Opcode::Add => 0,
+        Opcode::Multiply => 1,
+        _ => return None,
+    };
+    let is_identity =
+        |operand: &Operand| operand.mode == ParameterMode::Immediate && operand.value == identity;
+    match decoded.operands.as_slice() {
+        [_, b, _] if is_identity(b) => Some(0),
+        [a, _, _] if is_identity(a) => Some(1),
+        _ => None,
+    }
+}
+
+/// If `decoded` copies a value unchanged, return the source and destination.
+pub(crate) fn as_move(decoded: &Decoded) -> Option<(&Operand, &Operand)> {
+    let source = move_source(decoded)?;
+    Some((&decoded.operands[source], &decoded.operands[2]))
+}
+
+/// The code of a program, as found by following its control flow.
+pub(crate) struct Code {
+    pub instructions: BTreeMap<usize, Decoded>,
+    /// Addresses at which control may arrive other than by falling through.
+    pub leaders: BTreeSet<usize>,
+    /// Addresses which the program stores as return addresses.
+    pub return_points: BTreeSet<usize>,
+    /// Addresses which control reaches, but which do not hold an instruction.
+    pub undecodable: BTreeSet<usize>,
+}
+
+impl Code {
+    pub fn new(program: &Memory) -> Self {
+        let mut instructions = BTreeMap::new();
+        let mut leaders = BTreeSet::from([0]);
+        let mut return_points = BTreeSet::new();
+        let mut undecodable = BTreeSet::new();
+        let mut copied = HashSet::new();
+        let mut pending = vec![0];
+
+        loop {
+            while let Some(addr) = pending.pop() {
+                if addr >= program.len() || instructions.contains_key(&addr) {
+                    continue;
+                }
+                let Some(decoded) = decode(program, addr) else {
+                    undecodable.insert(addr);
+                    continue;
+                };
+                if decoded.opcode != Opcode::Halt && taken(&decoded) != Some(true) {
+                    pending.push(decoded.next_addr());
+                }
+                if decoded.opcode.jumps() {
+                    leaders.insert(decoded.next_addr());
+                    if let Some(target) = immediate_target(&decoded) {
+                        leaders.insert(target);
+                        pending.push(target);
+                    }
+                }
+                if let Some((source, _)) = as_move(&decoded) {
+                    if source.mode == ParameterMode::Immediate {
+                        copied.extend(usize::try_from(source.value).ok());
+                    }
+                }
+                instructions.insert(addr, decoded);
+            }
+
+            // code reached only by returning from a call
+            let found = instructions
+                .values()
+                .filter(|decoded| taken(decoded) == Some(true))
+                .map(Decoded::next_addr)
+                .filter(|addr| copied.contains(addr) && !return_points.contains(addr))
+                .collect::<Vec<_>>();
+            if found.is_empty() {
+                break;
+            }
+            leaders.extend(found.iter().copied());
+            return_points.extend(found.iter().copied());
+            pending.extend(found);
+        }
+
+        Self {
+            instructions,
+            leaders,
+            return_points,
+            undecodable,
+        }
+    }
+
+    /// The relative base on arrival at each instruction, where it is the same every time.
+    ///
+    /// `written` holds the addresses which the program might write by position.
+    pub fn relative_bases(
+        &self,
+        program: &Memory,
+        written: &HashSet<usize>,
+    ) -> BTreeMap<usize, Option<Word>> {
+        let mut bases = BTreeMap::new();
+        // nothing is known about the state in which a call returns
+        let mut pending = std::iter::once((0, Some(0 as Word)))
+            .chain(self.return_points.iter().map(|&addr| (addr, None)))
+            .collect::<Vec<_>>();
+
+        while let Some((addr, base)) = pending.pop() {
+            let Some(decoded) = self.instructions.get(&addr) else {
+                continue;
+            };
+            let merged = match bases.get(&addr) {
+                None => base,
+                Some(&known) if known == base => continue,
+                Some(_) => None,
+            };
+            if bases.insert(addr, merged) == Some(None) {
+                continue;
+            }
+
+            let mut next = merged;
+            if decoded.opcode == Opcode::RelativeBaseOffset {
+                let operand = decoded.operands[0];
+                let adjust = match operand.mode {
+                    ParameterMode::Immediate => Some(operand.value),
+                    ParameterMode::Position => position(&operand, program.len())
+                        .filter(|addr| !written.contains(addr))
+                        .map(|addr| program.ix(addr).unwrap_or_default()),
+                    ParameterMode::Relative => None,
+                };
+                next = merged
+                    .zip(adjust)
+                    .map(|(base, adjust)| base.wrapping_add(adjust));
+            }
+            if decoded.opcode != Opcode::Halt && taken(decoded) != Some(true) {
+                pending.push((decoded.next_addr(), next));
+            }
+            pending.extend(immediate_target(decoded).map(|target| (target, next)));
+        }
+        bases
+    }
+
+    pub fn overlaps(&self) -> bool {
+        self.instructions
+            .values()
+            .zip(self.instructions.values().skip(1))
+            .any(|(a, b)| a.next_addr() > b.addr)
+    }
+}
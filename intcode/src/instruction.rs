@@ -15,7 +15,8 @@ pub(crate) struct Instruction {
 impl TryFrom<Word> for Instruction {
     type Error = Error;
 
-    fn try_from(mut value: Word) -> Result<Self, Self::Error> {
+    fn try_from(original: Word) -> Result<Self, Self::Error> {
+        let mut value = original;
         let opcode = (value % 100).try_into()?;
         value /= 100;
 
@@ -26,10 +27,9 @@ impl TryFrom<Word> for Instruction {
             value /= 10;
         }
 
-        debug_assert_eq!(
-            value, 0,
-            "we should not be leaving parameter modes on the table"
-        );
+        if value != 0 {
+            return Err(Error::TooManyParameterModes(original));
+        }
 
         Ok(Self { modes, opcode })
     }
@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+
+use crate::{
+    error::{Error, Result},
+    Computer, Word,
+};
+
+/// Where a computer's `Input` and `Output` instructions are directed.
+pub(crate) trait Io {
+    /// The next input, left in place if the source allows it.
+    fn input<const CB: usize>(&mut self, computer: &Computer<CB>) -> Result<Word>;
+    /// Consume the input last returned by [`input`](Self::input), once it has been stored.
+    fn consume(&mut self) {}
+    fn output<const CB: usize>(&mut self, computer: &Computer<CB>, value: Word) -> Result<()>;
+}
+
+/// Perform I/O on the computer's own channels, blocking up to the channel timeout.
+pub(crate) struct Channels;
+
+impl Io for Channels {
+    fn input<const CB: usize>(&mut self, computer: &Computer<CB>) -> Result<Word> {
+        computer.recv_input()
+    }
+
+    fn output<const CB: usize>(&mut self, computer: &Computer<CB>, value: Word) -> Result<()> {
+        computer.send_output(value)
+    }
+}
+
+/// Perform I/O on in-memory queues, without ever blocking.
+///
+/// When the input queue is empty, `Input` fails with [`Error::AwaitingInput`],
+/// leaving the computer ready to retry the instruction. An input stays at the front of the
+/// queue until it has been stored, so it is not lost if the store faults.
+pub(crate) struct Queues<'a> {
+    pub(crate) input: &'a mut VecDeque<Word>,
+    pub(crate) output: &'a mut Vec<Word>,
+}
+
+impl Io for Queues<'_> {
+    fn input<const CB: usize>(&mut self, _computer: &Computer<CB>) -> Result<Word> {
+        self.input.front().copied().ok_or(Error::AwaitingInput)
+    }
+
+    fn consume(&mut self) {
+        self.input.pop_front();
+    }
+
+    fn output<const CB: usize>(&mut self, _computer: &Computer<CB>, value: Word) -> Result<()> {
+        self.output.push(value);
+        Ok(())
+    }
+}
+
+/// Why a computer stopped executing without an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The program executed a `Halt` instruction.
+    Halted,
+    /// The program is waiting at an `Input` instruction for an input which is not yet available.
+    AwaitingInput,
+    /// The program used up its allotted number of instructions, and can continue immediately.
+    Preempted,
+}
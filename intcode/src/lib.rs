@@ -1,20 +1,53 @@
+pub mod aot;
+mod ascii;
+mod asm;
+mod circuit;
 mod computer;
+mod coverage;
+pub mod decompile;
+mod disasm;
 mod error;
+mod flow;
 mod instruction;
+mod io;
+pub mod link;
 mod mem_idx;
 mod memory;
+pub mod mmio;
+mod network;
 mod opcode;
+pub mod optimize;
 mod parameter_mode;
 mod parameters;
+mod protect;
+mod scheduler;
+mod source_map;
+mod sweep;
+pub mod symbolic;
 
+pub use ascii::Ascii;
+pub use asm::{assemble, assemble_object, assemble_with_source_map};
+pub use circuit::{Circuit, NodeId};
 pub use computer::Computer;
+pub use coverage::Coverage;
+pub use disasm::{annotated_listing, decode, disassemble, Decoded, Line, Operand};
 pub use error::Error;
+pub use io::Status;
+pub use memory::Memory;
+pub use network::{Device, Network, Packet};
+pub use opcode::Opcode;
+pub use parameter_mode::ParameterMode;
+pub use protect::Protection;
+pub use scheduler::{Scheduler, TaskId};
+pub use source_map::{Location, SourceMap};
+pub use sweep::{sweep, sweep_find};
 
 pub type Word = i64;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mem_idx::MemIdx as _;
 
     #[test]
     fn day02_example() {
@@ -74,4 +107,1021 @@ mod tests {
 
         assert_eq!(program.as_slice(), &out);
     }
+
+    #[test]
+    fn fork_is_independent() {
+        // add 1 to position 9 and output it, forever
+        let program = [1001, 9, 1, 9, 4, 9, 1105, 1, 0, 0];
+
+        let mut computer = Computer::<1>::new(program);
+        // execute the first add
+        computer.step().unwrap();
+
+        let mut fork = computer.fork();
+        fork.step().unwrap();
+        fork.step().unwrap();
+        assert_eq!(fork.output().try_recv().unwrap(), 1);
+
+        for _ in 0..3 {
+            fork.step().unwrap();
+        }
+        assert_eq!(fork.output().try_recv().unwrap(), 2);
+
+        // the original was unaffected by the fork's writes and outputs
+        assert_eq!(computer.memory.ix(9_usize).unwrap(), 1);
+        assert!(computer.output().try_recv().is_err());
+        computer.step().unwrap();
+        assert_eq!(computer.output().try_recv().unwrap(), 1);
+    }
+
+    #[test]
+    fn memory_pages_grow_on_write() {
+        let mut memory = Memory::from([1, 2, 3]);
+        let snapshot = memory.clone();
+        *memory.ix_mut(5000_usize).unwrap() = 7;
+
+        assert_eq!(memory.len(), 5001);
+        assert_eq!(memory.ix(5000_usize).unwrap(), 7);
+        assert_eq!(memory.ix(4000_usize).unwrap(), 0);
+        assert_eq!(snapshot.into_inner(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn reset_from_restores_program() {
+        let program = Memory::from([1_i64, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        let mut computer = Computer::<0>::new(program.clone());
+        computer.run().unwrap();
+        assert_eq!(computer.peek(0).unwrap(), 3500);
+
+        computer.reset_from(&program);
+        computer.patch(10, 1).unwrap();
+        computer.run().unwrap();
+        assert_eq!(computer.peek(0).unwrap(), (30 + 1) * 50);
+        assert_eq!(program.into_inner()[10], 40);
+    }
+
+    #[test]
+    fn sweep_preserves_variant_order() {
+        let program = Memory::from([1_i64, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        let variants = (0..50).map(|value| [(9, value)]).collect::<Vec<_>>();
+        let values = sweep(&program, &variants, |computer| computer.peek(0).unwrap())
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expect = (0..50).map(|value| (value + 40) * 50).collect::<Vec<_>>();
+        assert_eq!(values, expect);
+    }
+
+    #[test]
+    fn sweep_isolates_faults_and_finds_first_match() {
+        let program = Memory::from([1_i64, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        // variant 3 replaces the first opcode with one which does not exist
+        let variants = (0..50)
+            .map(|value| match value {
+                3 => vec![(0, 98)],
+                _ => vec![(9, value)],
+            })
+            .collect::<Vec<_>>();
+        let values = sweep(&program, &variants, |computer| computer.peek(0).unwrap());
+        assert!(matches!(values[3], Err(Error::UnknownOpcode(98))));
+        assert_eq!(values[4].as_ref().unwrap(), &((4 + 40) * 50));
+
+        let want = |target| {
+            move |computer: &Computer| (computer.peek(0).unwrap() >= target).then_some(target)
+        };
+        assert_eq!(sweep_find(&program, &variants, want(2000)), Some((0, 2000)));
+        assert_eq!(
+            sweep_find(&program, &variants, want(4000)),
+            Some((40, 4000))
+        );
+        assert_eq!(sweep_find(&program, &variants, want(5000)), None);
+    }
+
+    fn amplify(program: &[Word], phases: [Word; 5], recycle: bool) -> Word {
+        let mut circuit = Circuit::new();
+        let amps = phases.map(|phase| {
+            let amp = circuit.add_node(Computer::new(program));
+            circuit.seed(amp, [phase]);
+            amp
+        });
+        if recycle {
+            circuit.ring(&amps);
+        } else {
+            circuit.chain(&amps);
+        }
+        circuit.seed(amps[0], [0]);
+        let outputs = circuit.run().unwrap();
+        *outputs[amps[4]].last().unwrap()
+    }
+
+    #[test]
+    fn circuit_line() {
+        let program = [
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(amplify(&program, [4, 3, 2, 1, 0], false), 43210);
+    }
+
+    #[test]
+    fn circuit_ring() {
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        assert_eq!(amplify(&program, [9, 8, 7, 6, 5], true), 139629729);
+    }
+
+    #[test]
+    fn circuit_fan_out_fan_in() {
+        // echo one input
+        let echo = [3, 0, 4, 0, 99];
+        // sum two inputs
+        let sum = [3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+
+        let mut circuit = Circuit::new();
+        let source = circuit.add_node(Computer::new(echo));
+        let left = circuit.add_node(Computer::new(echo));
+        let right = circuit.add_node(Computer::new(echo));
+        let sink = circuit.add_node(Computer::new(sum));
+        circuit.connect(source, left);
+        circuit.connect(source, right);
+        circuit.connect(left, sink);
+        circuit.connect(right, sink);
+        circuit.seed(source, [21]);
+
+        let outputs = circuit.run().unwrap();
+        assert_eq!(outputs[left], [21]);
+        assert_eq!(outputs[right], [21]);
+        assert_eq!(outputs[sink], [42]);
+    }
+
+    #[test]
+    fn circuit_deadlock() {
+        // each computer wants two inputs before producing any output
+        let program = [3, 9, 3, 10, 4, 9, 4, 10, 99, 0, 0];
+
+        let mut circuit = Circuit::new();
+        let a = circuit.add_node(Computer::new(program));
+        let b = circuit.add_node(Computer::new(program));
+        circuit.ring(&[a, b]);
+        circuit.seed(a, [1]);
+
+        let err = circuit.run().unwrap_err();
+        assert!(matches!(err, Error::Deadlock(blocked) if blocked == [(a, 2), (b, 0)]));
+    }
+
+    #[test]
+    fn queued_input_survives_a_faulting_store() {
+        // `in #0` cannot store its input
+        let mut computer = Computer::<0>::new([103, 0, 99]);
+        let mut input = std::collections::VecDeque::from([7]);
+        let err = computer.step_io(&mut input, &mut Vec::new()).unwrap_err();
+        assert!(matches!(err, Error::ImmediateWrite));
+        assert_eq!(input, [7]);
+    }
+
+    #[test]
+    fn scheduler_interleaving() {
+        // add one to each of two inputs, outputting each sum, then halt
+        let relay = assemble(
+            "
+            loop:   in [x]
+                    add [x], #1, [x]
+                    out [x]
+                    add [n], #-1, [n]
+                    jt [n], #loop
+                    halt
+            x:      data 0
+            n:      data 2
+            ",
+        )
+        .unwrap();
+
+        // every output, in the order observed, from a ring of three relays
+        let trace = |quantum| {
+            let mut scheduler = Scheduler::<0>::with_quantum(quantum);
+            let ring = [0, 1, 2].map(|_| scheduler.spawn(Computer::new(relay.clone())));
+            scheduler.send(ring[0], [0, 100]);
+            scheduler.send(ring[1], [10]);
+            let mut trace = Vec::new();
+            while !scheduler.is_halted() {
+                scheduler.round().unwrap();
+                for (idx, &task) in ring.iter().enumerate() {
+                    let outputs = scheduler.take_outputs(task);
+                    scheduler.send(ring[(idx + 1) % ring.len()], outputs.iter().copied());
+                    trace.extend(outputs.into_iter().map(|out| (task, out)));
+                }
+            }
+            trace
+        };
+
+        // each relay's whole run fits in one turn
+        assert_eq!(
+            trace(1000),
+            [(0, 1), (0, 101), (1, 11), (1, 2), (2, 12), (2, 3)]
+        );
+        // each turn is one pass around the loop
+        assert_eq!(
+            trace(5),
+            [(0, 1), (1, 11), (0, 101), (1, 2), (2, 12), (2, 3)]
+        );
+    }
+
+    #[test]
+    fn network_routes_packets_to_devices() {
+        use std::ops::ControlFlow;
+
+        #[derive(Default)]
+        struct Collector(Vec<Packet>);
+
+        impl Device for Collector {
+            fn receive(&mut self, packet: Packet, _send: &mut Vec<Packet>) -> ControlFlow<()> {
+                self.0.push(packet);
+                ControlFlow::Continue(())
+            }
+
+            fn idle(&mut self, _send: &mut Vec<Packet>) -> ControlFlow<()> {
+                ControlFlow::Break(())
+            }
+        }
+
+        // send `(255, addr, 2 * addr)`, then poll for input forever
+        let program = Memory::from([
+            3, 100, 104, 255, 4, 100, 1002, 100, 2, 101, 4, 101, 3, 102, 1105, 1, 12,
+        ]);
+
+        let mut collector = Collector::default();
+        let mut network = Network::new(&program, 3);
+        network.attach(255, &mut collector);
+        network.run().unwrap();
+
+        let expect = (0..3)
+            .map(|addr| Packet {
+                addr: 255,
+                x: addr,
+                y: 2 * addr,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(collector.0, expect);
+    }
+
+    #[test]
+    fn network_polls_with_minus_one() {
+        use std::ops::ControlFlow;
+
+        #[derive(Default)]
+        struct Collector(Vec<Packet>);
+
+        impl Device for Collector {
+            fn receive(&mut self, packet: Packet, _send: &mut Vec<Packet>) -> ControlFlow<()> {
+                self.0.push(packet);
+                ControlFlow::Continue(())
+            }
+        }
+
+        // send `(255, addr, input)` for the first input after the address, then halt
+        let program = assemble(
+            "
+                    in [addr]
+                    in [x]
+                    out #255
+                    out [addr]
+                    out [x]
+                    halt
+            addr:   data 0
+            x:      data 0
+            ",
+        )
+        .unwrap();
+
+        let mut collector = Collector::default();
+        let mut network = Network::new(&program, 3);
+        network.attach(255, &mut collector);
+        network.run().unwrap();
+
+        let expect = (0..3)
+            .map(|addr| Packet {
+                addr: 255,
+                x: addr,
+                y: -1,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(collector.0, expect);
+    }
+
+    #[test]
+    fn network_wakes_when_idle() {
+        use std::ops::ControlFlow;
+
+        /// Wakes computer 1 the first time the network is idle, and collects what it sends.
+        #[derive(Default)]
+        struct Waker {
+            idle_calls: usize,
+            received: Vec<Packet>,
+        }
+
+        impl Device for Waker {
+            fn receive(&mut self, packet: Packet, _send: &mut Vec<Packet>) -> ControlFlow<()> {
+                self.received.push(packet);
+                ControlFlow::Continue(())
+            }
+
+            fn idle(&mut self, send: &mut Vec<Packet>) -> ControlFlow<()> {
+                self.idle_calls += 1;
+                if self.idle_calls == 1 {
+                    send.push(Packet {
+                        addr: 1,
+                        x: 5,
+                        y: 6,
+                    });
+                }
+                ControlFlow::Continue(())
+            }
+        }
+
+        // forward every packet received to 255
+        let program = assemble(
+            "
+                    in [x]
+            poll:   in [x]
+                    eq [x], #-1, [empty]
+                    jt [empty], #poll
+                    in [y]
+                    out #255
+                    out [x]
+                    out [y]
+                    jt #1, #poll
+            x:      data 0
+            y:      data 0
+            empty:  data 0
+            ",
+        )
+        .unwrap();
+
+        let mut waker = Waker::default();
+        let mut network = Network::new(&program, 3);
+        network.attach(255, &mut waker);
+        let err = network.run().unwrap_err();
+
+        assert!(matches!(err, Error::NetworkIdle));
+        assert_eq!(waker.idle_calls, 2);
+        assert_eq!(
+            waker.received,
+            [Packet {
+                addr: 255,
+                x: 5,
+                y: 6
+            }]
+        );
+    }
+
+    #[test]
+    fn network_with_halted_computers_goes_idle() {
+        // computer 0 halts at once; the others poll forever
+        let program = assemble(
+            "
+                    in [x]
+                    jt [x], #poll
+                    halt
+            poll:   in [x]
+                    jt #1, #poll
+            x:      data 0
+            ",
+        )
+        .unwrap();
+
+        let mut network = Network::new(&program, 3);
+        assert!(matches!(network.run(), Err(Error::NetworkIdle)));
+    }
+
+    #[test]
+    fn network_device_ping_pong() {
+        use std::ops::ControlFlow;
+
+        /// Returns each packet to the other device with `x` counted down, stopping at zero.
+        struct Bouncer {
+            other: Word,
+            received: usize,
+        }
+
+        impl Device for Bouncer {
+            fn receive(&mut self, packet: Packet, send: &mut Vec<Packet>) -> ControlFlow<()> {
+                self.received += 1;
+                if packet.x == 0 {
+                    return ControlFlow::Break(());
+                }
+                send.push(Packet {
+                    addr: self.other,
+                    x: packet.x - 1,
+                    ..packet
+                });
+                ControlFlow::Continue(())
+            }
+        }
+
+        // far more bounces than would fit on the stack if routing recursed
+        let program = Memory::from([104, 300, 104, 1_000_000, 104, 0, 99]);
+
+        let mut ping = Bouncer {
+            other: 301,
+            received: 0,
+        };
+        let mut pong = Bouncer {
+            other: 300,
+            received: 0,
+        };
+        let mut network = Network::new(&program, 1);
+        network.attach(300, &mut ping);
+        network.attach(301, &mut pong);
+        network.run().unwrap();
+
+        assert_eq!(ping.received, 500_001);
+        assert_eq!(pong.received, 500_000);
+    }
+
+    /// Print "Hi", then echo one line of input, then output a non-ASCII answer.
+    const ASCII_ECHO: [Word; 20] = [
+        104, 72, 104, 105, 104, 10, 3, 100, 4, 100, 1008, 100, 10, 101, 1006, 101, 6, 104, 1000, 99,
+    ];
+
+    #[test]
+    fn ascii_lines_and_answers() {
+        let mut ascii = Ascii::new(Computer::<0>::new(ASCII_ECHO));
+        assert_eq!(ascii.run().unwrap(), Status::AwaitingInput);
+        assert_eq!(ascii.take_lines(), ["Hi"]);
+
+        ascii.send_line("ok").unwrap();
+        assert_eq!(ascii.run().unwrap(), Status::Halted);
+        assert_eq!(ascii.take_lines(), ["ok"]);
+        assert_eq!(ascii.take_answers(), [1000]);
+
+        assert!(matches!(ascii.send_line("é"), Err(Error::NonAscii('é'))));
+    }
+
+    #[test]
+    fn ascii_interactive_session() {
+        let mut ascii = Ascii::new(Computer::<0>::new(ASCII_ECHO));
+        let mut output = Vec::new();
+        let status = ascii.interact(b"hello\n".as_slice(), &mut output).unwrap();
+        assert_eq!(status, Status::Halted);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Hi\nhello\nanswer: 1000\n"
+        );
+    }
+
+    #[test]
+    fn disassemble_listing() {
+        let memory = Memory::from([1002, 4, 3, 4, 109, -2, 21101, 1, 2, 3, 99, 7]);
+        let listing = disassemble(&memory)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            listing,
+            [
+                "     0: mul [4], #3, [4]",
+                "     4: arb #-2",
+                "     6: add #1, #2, [rb+3]",
+                "    10: halt",
+                "    11: data 7",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_program() {
+        let memory: Memory = " 1, 9,10 ,3,\n2,3,11,0,99,30,40,50,\n".parse().unwrap();
+        assert_eq!(
+            memory.into_inner(),
+            [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]
+        );
+        assert!("".parse::<Memory>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_several_programs() {
+        let text = "1,0,0,0,99\r\n\n  104, -3, 99  \n";
+        let programs = Memory::from_reader(text.as_bytes()).unwrap();
+        assert_eq!(programs.len(), 2);
+        assert_eq!(programs[0].clone().into_inner(), [1, 0, 0, 0, 99]);
+        assert_eq!(programs[1].clone().into_inner(), [104, -3, 99]);
+    }
+
+    #[test]
+    fn parse_error_position() {
+        let text = "1,0,0,0,99\n1,2,\n3, x4,99\n";
+        let err = "1,2,\n3, x4,99".parse::<Memory>().unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 2, column: 4, token } if token == "x4"));
+
+        let err = Memory::from_reader(text.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 3, column: 4, token } if token == "x4"));
+
+        let err = "1,,2".parse::<Memory>().unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 1, column: 3, token } if token.is_empty()));
+    }
+
+    #[test]
+    fn assemble_countdown() {
+        let program = assemble(
+            "
+            start:  in [count]      ; how far to count
+            loop:   out [count]
+                    add [count], #-1, [count]
+                    jt [count], #loop
+                    halt
+            count:  data 0
+            ",
+        )
+        .unwrap();
+
+        let mut computer = Computer::<0>::new(program);
+        computer.provide_input([3]);
+        let out = computer.collect_outputs::<Vec<_>>().unwrap();
+        assert_eq!(out, [3, 2, 1]);
+    }
+
+    #[test]
+    fn assembly_round_trips_through_disassembly() {
+        let program = Memory::from([109, 7, 21101, 3, 4, -1, 99, 2, 5, 6, 0, 1105, 1, -4]);
+        let source = disassemble(&program)
+            .iter()
+            .map(|line| line.to_string().split_once(": ").unwrap().1.to_owned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(assemble(&source).unwrap(), program);
+
+        let err = assemble("add #1, #2\n").unwrap_err();
+        assert!(matches!(err, Error::Assemble { line: 1, .. }));
+        let err = assemble("halt\njt #1, #nowhere").unwrap_err();
+        assert!(matches!(err, Error::Assemble { line: 2, message } if message.contains("nowhere")));
+    }
+
+    #[test]
+    fn assembly_constants() {
+        let program = assemble(
+            "
+            size = end - table
+            top = size + base
+            base = 100
+                    out #top
+            table:  data 1, 2, 3
+            end:
+            ",
+        );
+        // `top` refers to `base`, which is defined below it
+        assert!(matches!(program, Err(Error::Assemble { line: 3, .. })));
+
+        let program = assemble(
+            "
+            base = 100
+            size = end - table
+            top = size + base
+                    out #top
+            table:  data 1, 2, 3
+            end:
+            ",
+        )
+        .unwrap();
+        assert_eq!(program, Memory::from([104, 103, 1, 2, 3]));
+    }
+
+    #[test]
+    fn optimize_removes_redundant_code() {
+        let program = assemble(
+            "
+                    add [x], #0, [x]        ; a move to itself
+                    jt #1, #skip            ; a jump to a jump
+            skip:   jt #1, #never           ; which lands on the next instruction
+            never:  jf #1, #0               ; never taken
+                    add #2, #3, [y]         ; overwritten before it is read
+                    mul [k], #10, [y]       ; `k` is never written
+            print:  out [y]
+                    halt
+            x:      data 5
+            y:      data 0
+            k:      data 7
+            ",
+        )
+        .unwrap();
+        let optimized = optimize::optimize(&program);
+        assert_eq!(
+            optimized.program,
+            Memory::from([1101, 70, 0, 7, 4, 7, 99, 0])
+        );
+        assert_eq!(optimized.relocate(21), 4);
+
+        let mut computer = Computer::<0>::new(optimized.program);
+        assert_eq!(computer.collect_outputs::<Vec<_>>().unwrap(), [70]);
+    }
+
+    #[test]
+    fn optimize_keeps_self_modifying_code_in_place() {
+        let program = assemble(
+            "
+                    add #99, #0, [patch]    ; replaces the output with a halt
+                    jt #1, #next
+            next:   jf #1, #0
+            patch:  out #1
+                    halt
+            ",
+        )
+        .unwrap();
+        assert_eq!(optimize::optimize(&program).program, program);
+    }
+
+    #[test]
+    fn symbolic_closed_form() {
+        // day02's shape: the noun and verb first serve as addresses, then as values
+        let program = Memory::from([1, 0, 0, 3, 1, 1, 2, 3, 2, 3, 13, 0, 99, 5]);
+        let mut executor = symbolic::Executor::new(&program);
+        executor.make_symbol(1, "noun");
+        executor.make_symbol(2, "verb");
+        let paths = executor.explore();
+        assert_eq!(paths.len(), 1);
+        assert!(matches!(paths[0].outcome(), symbolic::Outcome::Halted));
+
+        let result = paths[0].memory(0);
+        assert_eq!(result.to_string(), "5 * noun + 5 * verb");
+        let solution = result.solve(60, &[("noun", 0..100), ("verb", 0..100)]);
+        assert_eq!(solution, Some(vec![0, 12]));
+
+        let mut computer = Computer::<0>::new(program);
+        computer.patch(1, 0).unwrap();
+        computer.patch(2, 12).unwrap();
+        computer.run().unwrap();
+        assert_eq!(computer.peek(0).unwrap(), 60);
+    }
+
+    #[test]
+    fn symbolic_solve_wraps() {
+        use symbolic::Expr;
+
+        let x = |a| Expr::symbol("x") * Expr::constant(a);
+        let all = [("x", 0..Word::MAX)];
+
+        // 3 * 0x5555...5555 wraps around to -1
+        let solution = x(3).solve(-1, &all);
+        assert_eq!(solution, Some(vec![0x5555_5555_5555_5555]));
+        assert_eq!(0x5555_5555_5555_5555_i64.wrapping_mul(3), -1);
+
+        // 4 * x only ever has its low two bits clear, and repeats every 2^62
+        assert_eq!(x(4).solve(Word::MIN, &all), Some(vec![1 << 61]));
+        assert_eq!(
+            x(4).solve(Word::MIN, &[("x", (1 << 61) + 1..Word::MAX)]),
+            Some(vec![3 << 61])
+        );
+        assert_eq!(x(4).solve(2, &all), None);
+
+        // plain integer solutions are still found, and ranges respected
+        assert_eq!((x(5) + Expr::constant(7)).solve(57, &all), Some(vec![10]));
+        assert_eq!((x(5) + Expr::constant(7)).solve(57, &[("x", 0..10)]), None);
+    }
+
+    #[test]
+    fn symbolic_branches_fork() {
+        let program = assemble(
+            "
+                    in [x]
+                    lt [x], #5, [x]
+                    jt [x], #small
+                    out #0
+                    halt
+            small:  out #1
+                    halt
+            x:      data 0
+            ",
+        )
+        .unwrap();
+        let paths = symbolic::Executor::new(&program).explore();
+        let summary = paths
+            .iter()
+            .map(|path| {
+                let constraints = path.constraints().iter().map(ToString::to_string);
+                let output = path.output().iter().map(ToString::to_string);
+                (constraints.collect::<Vec<_>>(), output.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (vec!["(input0 < 5) != 0".to_owned()], vec!["1".to_owned()]),
+                (vec!["(input0 < 5) == 0".to_owned()], vec!["0".to_owned()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn link_modules() {
+        let main = assemble_object(
+            "main",
+            "
+                    extern double
+                    in [value]
+                    add #back, #0, [return]
+                    jt #1, #double
+            back:   out [value]
+                    halt
+            value:  data 0
+            return: data 0
+                    export value, return
+            ",
+        )
+        .unwrap();
+        let double = assemble_object(
+            "double",
+            "
+                    extern value, return
+                    export double
+            double: mul [value], #2, [value]
+                    jt #1, [return]
+            ",
+        )
+        .unwrap();
+
+        // objects survive being stored as text
+        let double = double.to_string().parse::<link::Object>().unwrap();
+
+        let linked = link::link(&[main, double]).unwrap();
+        assert_eq!(linked.map.symbols["double"], (14, "double".to_owned()));
+        assert_eq!(linked.map.modules[1], ("double".to_owned(), 14, 7));
+
+        let mut computer = Computer::<0>::new(linked.program);
+        computer.provide_input([21]);
+        let out = computer.collect_outputs::<Vec<_>>().unwrap();
+        assert_eq!(out, [42]);
+
+        let lonely = assemble_object(
+            "lonely",
+            "extern missing
+jt #1, #missing
+",
+        )
+        .unwrap();
+        let err = link::link(&[lonely]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "link error: `lonely` refers to undefined symbol `missing`"
+        );
+    }
+
+    #[test]
+    fn faults_name_their_source() {
+        let source = "
+            start:  arb #1
+            loop:   add #1, #1, [-1]    ; faults
+                    halt
+        ";
+        let (program, map) = assemble_with_source_map("prog.asm", source).unwrap();
+        assert_eq!(map.describe(4), "4 (prog.asm:3, loop+2)");
+        assert_eq!(map.to_string().parse::<SourceMap>().unwrap(), map);
+
+        let mut computer = Computer::<0>::new(program.clone());
+        computer.set_source_map(map.clone());
+        let err = computer.run().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed to convert `Int` value (-1) to `usize` for indexing at 2 (prog.asm:3, loop)"
+        );
+
+        let listing = annotated_listing(&program, &map);
+        assert_eq!(listing[0], "start:");
+        assert_eq!(listing[2], "loop:");
+        assert!(listing[3].ends_with("; prog.asm:3"), "{}", listing[3]);
+    }
+
+    #[test]
+    fn memory_mapped_devices() {
+        struct Clock(Word);
+        impl mmio::Device for Clock {
+            fn read(&mut self, _offset: usize) -> Word {
+                self.0 += 1;
+                self.0
+            }
+        }
+
+        struct Rng(u64);
+        impl mmio::Device for Rng {
+            fn read(&mut self, _offset: usize) -> Word {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                (self.0 % 100) as Word
+            }
+        }
+
+        struct Framebuffer(Vec<Word>);
+        impl mmio::Device for Framebuffer {
+            fn write(&mut self, offset: usize, value: Word) {
+                self.0[offset] = value;
+            }
+        }
+
+        let program = assemble(
+            "
+                arb #1000
+                add [100], #0, [200]    ; clock, in position mode
+                add [100], #0, [201]
+                add [rb+1], #0, [202]   ; rng, in relative mode
+                add [rb+1], #0, [203]
+                add #7, #0, [rb+12]     ; framebuffer, in both modes
+                mul [201], #10, [1010]
+                in [rb+13]
+                halt
+            ",
+        )
+        .unwrap();
+        let mut computer = Computer::<0>::new(program);
+        computer.attach(100, 1, Clock(0)).unwrap();
+        let rng = computer.attach(1001, 1, Rng(1)).unwrap();
+        let screen = computer.attach(1010, 4, Framebuffer(vec![0; 4])).unwrap();
+        assert!(matches!(
+            computer.attach(1012, 8, Clock(0)),
+            Err(Error::DeviceOverlap {
+                start: 1012,
+                end: 1020
+            })
+        ));
+        assert!(matches!(
+            computer.attach(1010, 0, Clock(0)),
+            Err(Error::DeviceOverlap {
+                start: 1010,
+                end: 1010
+            })
+        ));
+
+        computer.provide_input([5]);
+        computer.run().unwrap();
+
+        let mut reference = Rng(1);
+        let expect = [
+            mmio::Device::read(&mut reference, 0),
+            mmio::Device::read(&mut reference, 0),
+        ];
+        assert_eq!(rng.lock().unwrap().0, reference.0);
+        assert_eq!(computer.peek(200).unwrap(), 1);
+        assert_eq!(computer.peek(201).unwrap(), 2);
+        assert_eq!(computer.peek(202).unwrap(), expect[0]);
+        assert_eq!(computer.peek(203).unwrap(), expect[1]);
+        assert_eq!(screen.lock().unwrap().0, [20, 0, 7, 5]);
+        // the memory underneath a device is untouched
+        assert_eq!(computer.peek(1012).unwrap(), 0);
+
+        assert!(computer.detach(1010));
+        assert!(!computer.detach(1010));
+    }
+
+    #[test]
+    fn faulting_instructions_store_nothing_to_devices() {
+        #[derive(Default)]
+        struct Recorder(Vec<(usize, Word)>);
+        impl mmio::Device for Recorder {
+            fn write(&mut self, offset: usize, value: Word) {
+                self.0.push((offset, value));
+            }
+        }
+
+        let program = assemble(
+            "
+                in [100]
+                add #1, #2, [101]
+                halt
+            ",
+        )
+        .unwrap();
+        let mut computer = Computer::<0>::new(program);
+        let recorder = computer.attach(100, 2, Recorder::default()).unwrap();
+        computer.protect(101..102, Protection::ReadOnly);
+
+        let mut input = std::collections::VecDeque::new();
+        let mut output = Vec::new();
+        assert!(matches!(
+            computer.step_io(&mut input, &mut output),
+            Err(Error::AwaitingInput)
+        ));
+        input.push_back(7);
+        computer.step_io(&mut input, &mut output).unwrap();
+        assert!(matches!(
+            computer.step_io(&mut input, &mut output),
+            Err(Error::WriteProtected { addr: 101, .. })
+        ));
+        assert!(matches!(
+            computer.step_io(&mut input, &mut output),
+            Err(Error::WriteProtected { addr: 101, .. })
+        ));
+        assert_eq!(recorder.lock().unwrap().0, [(0, 7)]);
+    }
+
+    #[test]
+    fn protection_and_self_modification() {
+        let program = assemble(
+            "
+                        add #1101, #0, [next]   ; rewrites the next instruction's opcode
+                next:   add #1, #1, [result]
+                        halt
+                result: data 0
+            ",
+        )
+        .unwrap();
+
+        let mut computer = Computer::<0>::new(program.clone());
+        computer.track_self_modification();
+        computer.run().unwrap();
+        assert_eq!(computer.peek(9).unwrap(), 2);
+        assert_eq!(computer.self_modification(), [4]);
+
+        let mut computer = Computer::<0>::new(program.clone());
+        computer.protect(0..9, Protection::ReadOnly);
+        assert!(matches!(
+            computer.run(),
+            Err(Error::WriteProtected { ip: 0, addr: 4 })
+        ));
+
+        let mut computer = Computer::<0>::new(program);
+        computer.protect(7..10, Protection::NoExecute);
+        let err = computer.run().unwrap_err();
+        assert!(matches!(err, Error::ExecuteProtected { ip: 4, addr: 7 }));
+        assert!(err.is_fault());
+    }
+
+    #[test]
+    fn self_modification_counts_only_completed_stores_to_memory() {
+        struct Sink;
+        impl mmio::Device for Sink {}
+
+        // the instruction at 0 stores over itself, but into a device
+        let mut computer = Computer::<0>::new(assemble("add #1, #0, [0]\nhalt").unwrap());
+        computer.track_self_modification();
+        computer.attach(0, 1, Sink).unwrap();
+        computer.run().unwrap();
+        assert_eq!(computer.self_modification(), []);
+
+        // a store beyond the memory limit fails without being recorded
+        let mut computer = Computer::<0>::new(assemble("add #1, #0, [1099511627776]").unwrap());
+        computer.track_self_modification();
+        assert!(matches!(
+            computer.run(),
+            Err(Error::MemoryExhausted {
+                idx: 1_099_511_627_776,
+                ..
+            })
+        ));
+        assert_eq!(computer.self_modification(), []);
+    }
+
+    #[test]
+    fn coverage_merges_runs() {
+        let program = assemble(
+            "
+                        in [n]
+                loop:   jf [n], #done
+                        add [n], #-1, [n]
+                        jt #1, #loop
+                done:   halt
+                n:      data 0
+            ",
+        )
+        .unwrap();
+        let run = |computer: &mut Computer<0>, n: Word| {
+            let mut input = std::collections::VecDeque::from([n]);
+            computer.reset_from(&program);
+            loop {
+                match computer.step_io(&mut input, &mut Vec::new()) {
+                    Ok(()) => {}
+                    Err(Error::Halt(_)) => break,
+                    Err(err) => panic!("{err}"),
+                }
+            }
+        };
+
+        let mut computer = Computer::<0>::new(program.clone());
+        computer.record_coverage();
+        run(&mut computer, 0);
+        let mut coverage = computer.take_coverage().unwrap();
+        assert_eq!(coverage.hits(5), 0);
+        assert!(coverage.listing(&program)[2].starts_with("   ##### |      5: add"));
+
+        computer.record_coverage();
+        run(&mut computer, 1);
+        run(&mut computer, 1);
+        coverage.merge(computer.coverage().unwrap());
+        assert_eq!(coverage.executed().collect::<Vec<_>>(), [0, 2, 5, 9, 12]);
+        assert_eq!(coverage.hits(2), 5);
+        assert_eq!(coverage.branch(2), [3, 2]);
+        assert_eq!(coverage.to_string().parse::<Coverage>().unwrap(), coverage);
+
+        let listing = coverage.listing(&program);
+        assert!(
+            listing[1].ends_with("  ; taken 3, not taken 2"),
+            "{}",
+            listing[1]
+        );
+        assert!(!listing[3].contains("taken"), "{}", listing[3]);
+        assert_eq!(listing[5], "       - |     13: data 0");
+        assert_eq!(
+            coverage.lcov(&program, None, "listing"),
+            "TN:\nSF:listing\nBRDA:2,2,0,3\nBRDA:2,2,1,2\nBRF:2\nBRH:2\n\
+             DA:1,3\nDA:2,5\nDA:3,2\nDA:4,2\nDA:5,3\nLF:5\nLH:5\nend_of_record\n"
+        );
+    }
 }
@@ -0,0 +1,238 @@
+//! Relocatable objects, and the linker which combines them into a program.
+//!
+//! An [`Object`] is a module of code, assembled as if it began at address 0. Its relocations
+//! record which words depend on where it is placed: those holding the address of one of its
+//! own labels, and those holding the address of a symbol which another module exports.
+//! [`link`] lays modules out one after another, in the order given, so execution begins
+//! with the first; then it patches every relocated word.
+//!
+//! Objects can be stored as text:
+//!
+//! ```text
+//! module double
+//! words 1002,0,2,0,105,1,0
+//! export double 0
+//! relocate 1 value
+//! relocate 3 value
+//! relocate 6 return
+//! label double 0
+//! span 0 4 3 double
+//! span 4 3 4 double
+//! ```
+//!
+//! The `span` and `label` lines are the object's [`SourceMap`].
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::{
+    error::{Error, Result},
+    memory::Memory,
+    source_map::SourceMap,
+    Word,
+};
+
+/// A word which must be adjusted once its module is placed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Where the word is, from the start of its module.
+    pub offset: usize,
+    /// The symbol whose address is added to the word; if `None`, the module's own address.
+    pub symbol: Option<String>,
+}
+
+/// A module of code, ready to be linked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    pub name: String,
+    pub words: Vec<Word>,
+    /// The offset of each symbol which other modules may refer to.
+    pub exports: BTreeMap<String, usize>,
+    pub relocations: Vec<Relocation>,
+    /// Where the words came from, from the start of the module.
+    pub source_map: SourceMap,
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "module {}", self.name)?;
+        let words = self
+            .words
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        writeln!(f, "words {}", words.join(","))?;
+        for (name, offset) in &self.exports {
+            writeln!(f, "export {name} {offset}")?;
+        }
+        for relocation in &self.relocations {
+            match &relocation.symbol {
+                Some(symbol) => writeln!(f, "relocate {} {symbol}", relocation.offset)?,
+                None => writeln!(f, "relocate {}", relocation.offset)?,
+            }
+        }
+        write!(f, "{}", self.source_map)
+    }
+}
+
+impl FromStr for Object {
+    type Err = Error;
+
+    /// Parse an object in the form which it displays as.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut object = Object {
+            name: String::new(),
+            words: Vec::new(),
+            exports: BTreeMap::new(),
+            relocations: Vec::new(),
+            source_map: SourceMap::default(),
+        };
+        let mut named = false;
+        for (idx, line) in s.lines().enumerate() {
+            let error = |message: &str| Error::Link(format!("object line {}: {message}", idx + 1));
+            let offset = |text: &str| text.parse::<usize>().map_err(|_| error("invalid offset"));
+            if object
+                .source_map
+                .parse_entry(line)
+                .map_err(|message| error(&message))?
+            {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            match fields.as_slice() {
+                [] => {}
+                ["module", name] if !named => {
+                    object.name = (*name).to_owned();
+                    named = true;
+                }
+                ["words"] => {}
+                ["words", words] => {
+                    let memory: Memory = words.parse()?;
+                    object.words.extend(memory.into_inner());
+                }
+                ["export", name, at] => {
+                    object.exports.insert((*name).to_owned(), offset(at)?);
+                }
+                ["relocate", at] => object.relocations.push(Relocation {
+                    offset: offset(at)?,
+                    symbol: None,
+                }),
+                ["relocate", at, symbol] => object.relocations.push(Relocation {
+                    offset: offset(at)?,
+                    symbol: Some((*symbol).to_owned()),
+                }),
+                _ => return Err(error(&format!("unexpected {line:?}"))),
+            }
+        }
+        if !named {
+            return Err(Error::Link("object has no `module` line".into()));
+        }
+        Ok(object)
+    }
+}
+
+/// Where each module and symbol ended up in a linked program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Map {
+    /// Each module's name, address, and size, in address order.
+    pub modules: Vec<(String, usize, usize)>,
+    /// Each exported symbol's address, and the module which exports it.
+    pub symbols: BTreeMap<String, (usize, String)>,
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .modules
+            .iter()
+            .map(|(name, ..)| name.len())
+            .chain(self.symbols.keys().map(String::len))
+            .max()
+            .unwrap_or_default();
+        writeln!(f, "modules:")?;
+        for (name, addr, size) in &self.modules {
+            writeln!(f, "  {name:<width$}  {addr:>6}  size {size}")?;
+        }
+        writeln!(f, "symbols:")?;
+        let mut symbols = self.symbols.iter().collect::<Vec<_>>();
+        symbols.sort_by_key(|(name, (addr, _))| (*addr, *name));
+        for (name, (addr, module)) in symbols {
+            writeln!(f, "  {name:<width$}  {addr:>6}  in {module}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A program made by [`link`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Linked {
+    pub program: Memory,
+    pub map: Map,
+    pub source_map: SourceMap,
+}
+
+/// Lay out `objects` in order from address 0, and resolve every relocation.
+pub fn link(objects: &[Object]) -> Result<Linked> {
+    let mut map = Map::default();
+    let mut addr = 0;
+    for object in objects {
+        if map.modules.iter().any(|(name, ..)| *name == object.name) {
+            return Err(Error::Link(format!(
+                "module `{}` is linked more than once",
+                object.name
+            )));
+        }
+        map.modules
+            .push((object.name.clone(), addr, object.words.len()));
+        for (symbol, offset) in &object.exports {
+            if *offset >= object.words.len() {
+                return Err(Error::Link(format!(
+                    "`{}` exports `{symbol}` outside itself, at offset {offset}",
+                    object.name
+                )));
+            }
+            let placed = (addr + offset, object.name.clone());
+            if let Some((_, other)) = map.symbols.insert(symbol.clone(), placed) {
+                return Err(Error::Link(format!(
+                    "symbol `{symbol}` is exported by both `{other}` and `{}`",
+                    object.name
+                )));
+            }
+        }
+        addr += object.words.len();
+    }
+
+    let mut words = Vec::with_capacity(addr);
+    let mut source_map = SourceMap::default();
+    for (object, (_, base, _)) in objects.iter().zip(&map.modules) {
+        source_map.extend(&object.source_map, *base);
+        let start = words.len();
+        words.extend_from_slice(&object.words);
+        for relocation in &object.relocations {
+            let target = match &relocation.symbol {
+                None => *base,
+                Some(symbol) => match map.symbols.get(symbol) {
+                    Some((addr, _)) => *addr,
+                    None => {
+                        return Err(Error::Link(format!(
+                            "`{}` refers to undefined symbol `{symbol}`",
+                            object.name
+                        )))
+                    }
+                },
+            };
+            let Some(word) = words[start..].get_mut(relocation.offset) else {
+                return Err(Error::Link(format!(
+                    "`{}` has a relocation outside itself, at offset {}",
+                    object.name, relocation.offset
+                )));
+            };
+            *word = word.wrapping_add(target as Word);
+        }
+    }
+
+    Ok(Linked {
+        program: words.into(),
+        map,
+        source_map,
+    })
+}
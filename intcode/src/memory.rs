@@ -1,4 +1,4 @@
-use std::ops::{Deref, DerefMut};
+use std::{fmt, io::Read, path::Path, str::FromStr, sync::Arc};
 
 use crate::{
     error::{Error, Result},
@@ -6,35 +6,115 @@ use crate::{
     Word,
 };
 
-const MEMORY_LIMIT: usize = 256 * 1024 * 1024; // 256 Mb
+pub(crate) const MEMORY_LIMIT: usize = 256 * 1024 * 1024; // 256 Mb
 
-#[derive(Debug, PartialEq, Eq, Clone, derive_more::From, derive_more::Into)]
-pub struct Memory(Vec<Word>);
+/// How many words are stored in each page of memory.
+const PAGE_SIZE: usize = 512;
+
+type Page = [Word; PAGE_SIZE];
+
+/// Paged, copy-on-write program memory.
+///
+/// Cloning a `Memory` is cheap: pages are shared between the clones until one of them
+/// writes to a page, at which point only that page is copied.
+///
+/// Words between `len` and the end of the final page are always zero, so pages can be
+/// compared directly.
+#[derive(PartialEq, Eq, Clone, Default)]
+pub struct Memory {
+    pages: Vec<Arc<Page>>,
+    len: usize,
+}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
 
 impl From<&[Word]> for Memory {
     fn from(value: &[Word]) -> Self {
-        Self(value.to_owned())
+        let pages = value
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Arc::new(page)
+            })
+            .collect();
+        Self {
+            pages,
+            len: value.len(),
+        }
+    }
+}
+
+impl From<Vec<Word>> for Memory {
+    fn from(value: Vec<Word>) -> Self {
+        value.as_slice().into()
     }
 }
 
 impl<const N: usize> From<[Word; N]> for Memory {
     fn from(value: [Word; N]) -> Self {
-        Self(value.into())
+        value.as_slice().into()
     }
 }
 
-impl Deref for Memory {
-    type Target = [Word];
+impl From<Memory> for Vec<Word> {
+    fn from(value: Memory) -> Self {
+        value.into_inner()
+    }
+}
+
+impl FromStr for Memory {
+    type Err = Error;
 
-    fn deref(&self) -> &Self::Target {
-        self.0.as_slice()
+    /// Parse a single comma-separated program.
+    ///
+    /// Whitespace, including newlines, is permitted around each word, and a trailing comma is ignored.
+    fn from_str(s: &str) -> Result<Self> {
+        parse_program(s, 0)
     }
 }
 
-impl DerefMut for Memory {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.0.as_mut_slice()
+/// Parse comma-separated words from `text`, reporting errors as if `text` began on line `first_line + 1`.
+fn parse_program(text: &str, first_line: usize) -> Result<Memory> {
+    let segments = text.split(',').collect::<Vec<_>>();
+    let n_segments = segments.len();
+    let mut words = Vec::with_capacity(n_segments);
+    let mut segment_start = 0;
+
+    for (idx, segment) in segments.into_iter().enumerate() {
+        let token = segment.trim();
+        let token_start = segment_start + (segment.len() - segment.trim_start().len());
+        segment_start += segment.len() + 1;
+
+        // an empty program, or a trailing comma
+        if token.is_empty() && idx + 1 == n_segments {
+            continue;
+        }
+
+        let word = token.parse().map_err(|_| {
+            let before = &text[..token_start];
+            let line = first_line + before.matches('\n').count() + 1;
+            let column = before
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .count()
+                + 1;
+            Error::Parse {
+                line,
+                column,
+                token: token.to_owned(),
+            }
+        })?;
+        words.push(word);
     }
+
+    Ok(words.into())
 }
 
 impl MemIdx<usize> for Memory {
@@ -46,7 +126,8 @@ impl MemIdx<usize> for Memory {
     #[inline]
     fn ix_mut(&mut self, idx: usize) -> Result<&mut Word> {
         self.ensure_capacity(idx)?;
-        Ok(&mut self.0[idx])
+        let page = Arc::make_mut(&mut self.pages[idx / PAGE_SIZE]);
+        Ok(&mut page[idx % PAGE_SIZE])
     }
 }
 
@@ -60,14 +141,68 @@ impl MemIdx<Word> for Memory {
     #[inline]
     fn ix_mut(&mut self, idx: Word) -> Result<&mut Word> {
         let idx: usize = idx.try_into().map_err(|_| Error::IndexFailed(idx))?;
-        self.ensure_capacity(idx)?;
         self.ix_mut(idx)
     }
 }
 
 impl Memory {
+    /// Parse every program in `reader`: each non-blank line is a comma-separated program.
+    pub fn from_reader(mut reader: impl Read) -> Result<Vec<Self>> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| parse_program(line, idx))
+            .collect()
+    }
+
+    /// Parse every program in the file at `path`: each non-blank line is a comma-separated program.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Vec<Self>> {
+        Self::from_reader(std::fs::File::open(path)?)
+    }
+
     pub fn into_inner(self) -> Vec<Word> {
-        self.0
+        self.iter().collect()
+    }
+
+    /// The number of words of memory which have been initialized or written.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Overwrite this memory with the contents of `other`.
+    ///
+    /// Pages which are already shared with `other` are left alone; other pages owned
+    /// solely by `self` are overwritten in place, so that no allocation is necessary.
+    pub fn copy_from(&mut self, other: &Memory) {
+        self.pages.truncate(other.pages.len());
+        for (idx, page) in other.pages.iter().enumerate() {
+            let Some(own) = self.pages.get_mut(idx) else {
+                self.pages.push(page.clone());
+                continue;
+            };
+            if Arc::ptr_eq(own, page) {
+                continue;
+            }
+            match Arc::get_mut(own) {
+                Some(own) => own.copy_from_slice(page.as_slice()),
+                None => *own = page.clone(),
+            }
+        }
+        self.len = other.len;
+    }
+
+    /// Iterate over the words of memory, in address order.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = Word> {
+        self.pages
+            .iter()
+            .flat_map(|page| page.iter().copied())
+            .take(self.len)
     }
 
     fn get(&self, idx: usize) -> Result<Word> {
@@ -77,19 +212,26 @@ impl Memory {
                 len: MEMORY_LIMIT,
             });
         }
-        Ok(self.0.get(idx).copied().unwrap_or_default())
+        if idx >= self.len {
+            return Ok(Word::default());
+        }
+        Ok(self.pages[idx / PAGE_SIZE][idx % PAGE_SIZE])
     }
 
     fn ensure_capacity(&mut self, idx: usize) -> Result<()> {
-        if idx >= self.len() {
+        if idx >= self.len {
             if idx > MEMORY_LIMIT {
                 return Err(Error::MemoryExhausted {
                     idx,
                     len: MEMORY_LIMIT,
                 });
             }
-            // eprintln!("reallocating from {} to {}", self.0.len(), idx + 1);
-            self.0.resize(idx + 1, Word::default());
+            // new pages are zeroed, so only the page count has to change
+            let n_pages = idx / PAGE_SIZE + 1;
+            if n_pages > self.pages.len() {
+                self.pages.resize_with(n_pages, || Arc::new([0; PAGE_SIZE]));
+            }
+            self.len = idx + 1;
         }
         Ok(())
     }
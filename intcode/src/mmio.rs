@@ -0,0 +1,136 @@
+//! Memory-mapped devices.
+//!
+//! A [`Device`] is attached to a [`Computer`] over a range of addresses. While it is
+//! attached, every operand which reads a word in that range, in any parameter mode, asks
+//! the device for it instead of memory; and every instruction which stores to the range
+//! hands the stored word to the device once the instruction has completed; an instruction
+//! which faults hands it nothing. The words of memory underneath a device are left untouched.
+//!
+//! Only operands are mapped: instructions are always fetched from memory, and
+//! [`Computer::peek`] and [`Computer::patch`] bypass devices. Devices belong to a computer
+//! rather than to its [`Memory`](crate::Memory), which is a plain snapshot that can be shared
+//! and compared; so inspecting memory, as [`sweep`](crate::sweep)'s predicates do, never
+//! reads a device and so never changes one.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    error::{Error, Result},
+    Computer, Word,
+};
+
+/// Something which can be attached to a range of a computer's memory.
+///
+/// `offset` is always relative to the start of the device's range.
+pub trait Device: Send {
+    /// Produce the word at `offset`, for an instruction which reads it.
+    fn read(&mut self, _offset: usize) -> Word {
+        0
+    }
+
+    /// Accept a word which an instruction stored at `offset`.
+    fn write(&mut self, _offset: usize, _value: Word) {}
+}
+
+type Shared = Arc<Mutex<dyn Device>>;
+
+/// The devices attached to one computer.
+///
+/// Cloning the registry shares the devices themselves.
+#[derive(Clone, Default)]
+pub(crate) struct Devices {
+    /// Each device's start address, with the length of its range.
+    mappings: BTreeMap<usize, (usize, Shared)>,
+    /// The address which the current instruction is storing to, if it is mapped.
+    pending: Option<usize>,
+    /// Where that instruction stores its result.
+    latch: Word,
+}
+
+impl Devices {
+    fn attach(&mut self, start: usize, len: usize, device: Shared) -> Result<()> {
+        let end = start.saturating_add(len);
+        let before = self.mappings.range(..end).next_back();
+        // an empty range has nothing before its end to overlap, but would still replace
+        // a device at the same start
+        if self.mappings.contains_key(&start)
+            || matches!(before, Some((other, (other_len, _))) if other + other_len > start)
+        {
+            return Err(Error::DeviceOverlap { start, end });
+        }
+        self.mappings.insert(start, (len, device));
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// The device mapped at `addr`, and `addr`'s offset within it.
+    fn find(&mut self, addr: usize) -> Option<(&mut Shared, usize)> {
+        if self.mappings.is_empty() {
+            return None;
+        }
+        let (start, (len, device)) = self.mappings.range_mut(..=addr).next_back()?;
+        let offset = addr - start;
+        (offset < *len).then_some((device, offset))
+    }
+
+    pub(crate) fn read(&mut self, addr: usize) -> Option<Word> {
+        let (device, offset) = self.find(addr)?;
+        Some(lock(device).read(offset))
+    }
+
+    /// Where to store a word for `addr`, if a device is mapped there.
+    pub(crate) fn latch(&mut self, addr: usize) -> Option<&mut Word> {
+        self.find(addr)?;
+        self.pending = Some(addr);
+        self.latch = 0;
+        Some(&mut self.latch)
+    }
+
+    /// Forget any word which an instruction began to store, without delivering it.
+    ///
+    /// Called whenever an instruction fails, so that its store is never delivered.
+    pub(crate) fn reset(&mut self) {
+        self.pending = None;
+    }
+
+    /// Deliver the word stored by the instruction which just completed, if it was mapped.
+    pub(crate) fn flush(&mut self) {
+        let Some(addr) = self.pending.take() else {
+            return;
+        };
+        let value = self.latch;
+        if let Some((device, offset)) = self.find(addr) {
+            lock(device).write(offset, value);
+        }
+    }
+}
+
+fn lock(device: &Shared) -> MutexGuard<'_, dyn 'static + Device> {
+    device.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
+    /// Attach `device` to the `len` addresses from `start`.
+    ///
+    /// Returns a handle through which the device can still be inspected while it is attached.
+    /// Fails if the range overlaps a device which is already attached.
+    pub fn attach<D>(&mut self, start: usize, len: usize, device: D) -> Result<Arc<Mutex<D>>>
+    where
+        D: 'static + Device,
+    {
+        let device = Arc::new(Mutex::new(device));
+        self.devices.attach(start, len, device.clone())?;
+        Ok(device)
+    }
+
+    /// Detach the device whose range starts at `start`, returning whether there was one.
+    pub fn detach(&mut self, start: usize) -> bool {
+        self.devices.mappings.remove(&start).is_some()
+    }
+}
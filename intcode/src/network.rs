@@ -0,0 +1,173 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::ControlFlow,
+};
+
+use crate::{
+    error::{Error, Result},
+    io::Status,
+    memory::Memory,
+    scheduler::Scheduler,
+    Computer, Word,
+};
+
+/// A message sent across a [`Network`].
+///
+/// On the wire, a packet is three consecutive outputs: `addr`, `x`, `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Packet {
+    /// The destination address.
+    pub addr: Word,
+    pub x: Word,
+    pub y: Word,
+}
+
+/// Something other than a computer, attached to a [`Network`] at a special address.
+pub trait Device {
+    /// Handle a packet addressed to this device.
+    ///
+    /// Packets pushed to `send` are routed once this returns. Returning `Break` stops the network
+    /// at once, and any packets still to be routed are dropped.
+    fn receive(&mut self, packet: Packet, send: &mut Vec<Packet>) -> ControlFlow<()>;
+
+    /// Called whenever the network is idle.
+    ///
+    /// Packets pushed to `send` are routed once this returns. Returning `Break` stops the network.
+    fn idle(&mut self, _send: &mut Vec<Packet>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+/// A packet-switched network of computers running the same program.
+///
+/// Each computer is assigned an address on startup, which it receives as its first input.
+/// After that, each packet it receives arrives as two inputs: `x` and `y`. When a computer
+/// wants input but none is queued, it receives `-1`.
+///
+/// All computers are time-sliced on the current thread, so network simulations are reproducible.
+pub struct Network<'a> {
+    scheduler: Scheduler,
+    /// Outputs which do not yet form a complete packet, per computer.
+    partial: Vec<Vec<Word>>,
+    devices: BTreeMap<Word, &'a mut dyn Device>,
+}
+
+impl<'a> Network<'a> {
+    /// The network is considered idle after this many consecutive rounds in which every computer
+    /// wanted input, none had any queued, and none sent anything.
+    const IDLE_ROUNDS: usize = 2;
+
+    /// Boot `size` computers running `program`, with addresses `0..size`.
+    pub fn new(program: &Memory, size: usize) -> Self {
+        let mut scheduler = Scheduler::new();
+        for addr in 0..size {
+            let computer = scheduler.spawn(Computer::new(program.clone()));
+            scheduler.send(computer, [addr as Word]);
+        }
+        Self {
+            scheduler,
+            partial: vec![Vec::new(); size],
+            devices: BTreeMap::new(),
+        }
+    }
+
+    /// Attach a device at `addr`.
+    ///
+    /// Devices take precedence over computers with the same address.
+    pub fn attach(&mut self, addr: Word, device: &'a mut dyn Device) {
+        self.devices.insert(addr, device);
+    }
+
+    /// Send each packet to its destination.
+    ///
+    /// Packets which devices send in response are delivered before any later packets,
+    /// however long a chain of devices they pass through. Returns `true` as soon as a
+    /// device stops the network; any undelivered packets are then dropped.
+    fn route(&mut self, packets: impl IntoIterator<Item = Packet>) -> Result<bool> {
+        let mut queue = packets.into_iter().collect::<VecDeque<_>>();
+        let mut send = Vec::new();
+        while let Some(packet) = queue.pop_front() {
+            if let Some(device) = self.devices.get_mut(&packet.addr) {
+                if device.receive(packet, &mut send).is_break() {
+                    return Ok(true);
+                }
+                for packet in send.drain(..).rev() {
+                    queue.push_front(packet);
+                }
+            } else {
+                let computer = usize::try_from(packet.addr)
+                    .ok()
+                    .filter(|&computer| computer < self.scheduler.len())
+                    .ok_or(Error::UnknownAddress(packet.addr))?;
+                self.scheduler.send(computer, [packet.x, packet.y]);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Run the network until a device stops it or every computer halts.
+    ///
+    /// The network is idle once every computer has either halted or been waiting for input,
+    /// with none to give it, for a few rounds. If no device then sends anything in response,
+    /// this returns [`Error::NetworkIdle`], as nothing further can happen.
+    pub fn run(&mut self) -> Result<()> {
+        let mut idle_rounds = 0;
+
+        while !self.scheduler.is_halted() {
+            let mut idle = true;
+            for computer in 0..self.scheduler.len() {
+                match self.scheduler.status(computer) {
+                    // a halted computer will never send anything
+                    Some(Status::Halted) => {}
+                    Some(Status::AwaitingInput) if self.scheduler.pending(computer) == 0 => {
+                        self.scheduler.send(computer, [-1]);
+                    }
+                    _ => idle = false,
+                }
+            }
+
+            self.scheduler.round()?;
+
+            let mut packets = Vec::new();
+            for (computer, partial) in self.partial.iter_mut().enumerate() {
+                let outputs = self.scheduler.take_outputs(computer);
+                idle &= outputs.is_empty();
+                partial.extend(outputs);
+
+                packets.extend(partial.chunks_exact(3).map(|chunk| Packet {
+                    addr: chunk[0],
+                    x: chunk[1],
+                    y: chunk[2],
+                }));
+                partial.drain(..partial.len() - partial.len() % 3);
+            }
+
+            if self.route(packets)? {
+                return Ok(());
+            }
+
+            idle_rounds = if idle { idle_rounds + 1 } else { 0 };
+            if idle_rounds < Self::IDLE_ROUNDS {
+                continue;
+            }
+
+            let mut send = Vec::new();
+            let mut stop = false;
+            for device in self.devices.values_mut() {
+                stop |= device.idle(&mut send).is_break();
+            }
+            if stop {
+                return Ok(());
+            }
+            if send.is_empty() {
+                return Err(Error::NetworkIdle);
+            }
+            if self.route(send)? {
+                return Ok(());
+            }
+            idle_rounds = 0;
+        }
+
+        Ok(())
+    }
+}
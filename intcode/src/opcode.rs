@@ -27,6 +27,35 @@ impl Opcode {
             Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 3,
         }
     }
+
+    /// Whether this opcode's final parameter is written to.
+    pub const fn writes(self) -> bool {
+        matches!(
+            self,
+            Opcode::Add | Opcode::Multiply | Opcode::Input | Opcode::LessThan | Opcode::Equals
+        )
+    }
+
+    /// Whether this opcode may transfer control somewhere other than the next instruction.
+    pub const fn jumps(self) -> bool {
+        matches!(self, Opcode::JumpIfTrue | Opcode::JumpIfFalse)
+    }
+
+    /// Short name of this opcode, as used in disassembly.
+    pub const fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Multiply => "mul",
+            Opcode::Input => "in",
+            Opcode::Output => "out",
+            Opcode::JumpIfTrue => "jt",
+            Opcode::JumpIfFalse => "jf",
+            Opcode::LessThan => "lt",
+            Opcode::Equals => "eq",
+            Opcode::RelativeBaseOffset => "arb",
+            Opcode::Halt => "halt",
+        }
+    }
 }
 
 impl TryFrom<Word> for Opcode {
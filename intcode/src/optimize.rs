@@ -0,0 +1,416 @@
+//! Peephole optimization of Intcode programs.
+//!
+//! [`optimize`] finds the code in a program by following its control flow from address 0,
+//! rewrites instructions which provably do nothing or compute a constant, and lays the
+//! program out again without the instructions it removed, relocating every address which
+//! referred to a word that moved.
+//!
+//! The rewrites are:
+//!
+//! - a position-mode operand which reads a word the program never writes becomes an immediate
+//! - arithmetic and comparisons of immediates become moves of the result
+//! - a conditional jump which is never taken is removed
+//! - a jump to an unconditional jump is redirected to the final target
+//! - a jump to the instruction which follows it anyway is removed
+//! - a move of a word to itself is removed
+//! - a store is removed if the same block overwrites it before anything reads it
+//! - a word which is neither code nor referred to by any address is removed
+//!
+//! Intcode does not distinguish addresses from other numbers, so relocation relies on
+//! recognizing them. The addresses are taken to be position-mode operands, immediate jump
+//! targets, and return addresses: immediates copied into memory whose value is the address
+//! just after an unconditional jump, as compilers store them before calling a function.
+//! Otherwise, relative-mode operands are taken to address a stack beyond the end of the
+//! program. Both hold for the programs the puzzles provide and for those `intcode-lang`
+//! compiles.
+//!
+//! Where the relative base is the same every time an instruction runs, its relative-mode
+//! operands are treated like position-mode ones.
+//!
+//! Self-modifying programs keep their layout: if the program reads or writes any of its own
+//! instructions as data, addresses the program by relative mode, or takes a jump target from
+//! memory by position, nothing is removed or moved, and the instructions it touches are left
+//! exactly as they are.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::{
+    disasm::{Decoded, Operand},
+    flow::{as_move, immediate_target, move_source, position, reads, taken, write, Code},
+    mem_idx::MemIdx as _,
+    memory::Memory,
+    opcode::Opcode,
+    parameter_mode::ParameterMode,
+    Word,
+};
+
+/// A program rewritten by [`optimize`].
+#[derive(Debug, Clone)]
+pub struct Optimized {
+    pub program: Memory,
+    /// The new address of each word of the original program.
+    relocations: Vec<usize>,
+}
+
+impl Optimized {
+    /// The address in the optimized program which corresponds to `addr` in the original.
+    ///
+    /// The address of a removed instruction corresponds to whatever followed it.
+    /// Addresses beyond the end of the original program are unchanged.
+    pub fn relocate(&self, addr: usize) -> usize {
+        self.relocations.get(addr).copied().unwrap_or(addr)
+    }
+
+    fn unchanged(program: &Memory) -> Self {
+        Self {
+            program: program.clone(),
+            relocations: (0..program.len()).collect(),
+        }
+    }
+}
+
+fn immediate(value: Word) -> Operand {
+    Operand {
+        mode: ParameterMode::Immediate,
+        value,
+    }
+}
+
+/// Whether reading `operand` certainly succeeds.
+fn reads_safely(operand: &Operand, len: usize) -> bool {
+    operand.mode == ParameterMode::Immediate || position(operand, len).is_some()
+}
+
+/// One instruction of the program being optimized.
+struct Slot {
+    original: Decoded,
+    /// The instruction as rewritten so far, or `None` once it has been removed.
+    current: Option<Decoded>,
+    /// Whether the program touches this instruction's words as data.
+    pinned: bool,
+}
+
+struct Optimizer<'a> {
+    program: &'a Memory,
+    slots: BTreeMap<usize, Slot>,
+    leaders: BTreeSet<usize>,
+    /// Addresses within the program which it might write.
+    written: HashSet<usize>,
+    /// Whether instructions may be removed, moving everything after them.
+    movable: bool,
+}
+
+impl<'a> Optimizer<'a> {
+    fn new(program: &'a Memory, code: &Code) -> Self {
+        let len = program.len();
+        let mut written = HashSet::new();
+        let mut accessed = HashSet::new();
+        let mut movable = true;
+        for decoded in code.instructions.values() {
+            accessed.extend(reads(decoded).iter().filter_map(|op| position(op, len)));
+            if let Some(addr) = write(decoded).and_then(|op| position(op, len)) {
+                written.insert(addr);
+                accessed.insert(addr);
+            }
+            if let [_, target] = decoded.operands.as_slice() {
+                movable &= !(decoded.opcode.jumps() && target.mode == ParameterMode::Position);
+            }
+        }
+
+        // relative-mode operands which provably address the program, rather than the stack
+        for (addr, base) in code.relative_bases(program, &written) {
+            let Some(base) = base else {
+                continue;
+            };
+            let decoded = &code.instructions[&addr];
+            for (idx, operand) in decoded.operands.iter().enumerate() {
+                let Some(target) = (operand.mode == ParameterMode::Relative)
+                    .then(|| usize::try_from(base.wrapping_add(operand.value)).ok())
+                    .flatten()
+                    .filter(|&target| target < len)
+                else {
+                    continue;
+                };
+                movable = false;
+                accessed.insert(target);
+                if decoded.opcode.writes() && idx + 1 == decoded.operands.len() {
+                    written.insert(target);
+                }
+            }
+        }
+
+        let mut slots = BTreeMap::new();
+        for (&addr, decoded) in &code.instructions {
+            let pinned = (addr..decoded.next_addr()).any(|word| accessed.contains(&word));
+            movable &= !pinned;
+            slots.insert(
+                addr,
+                Slot {
+                    original: decoded.clone(),
+                    current: Some(decoded.clone()),
+                    pinned,
+                },
+            );
+        }
+
+        Self {
+            program,
+            slots,
+            leaders: code.leaders.clone(),
+            written,
+            movable,
+        }
+    }
+
+    fn current(&self, addr: usize) -> Option<&Decoded> {
+        self.slots.get(&addr)?.current.as_ref()
+    }
+
+    /// Where execution arriving at `addr` really continues, once removed instructions are skipped.
+    fn resolve(&self, mut addr: usize) -> usize {
+        while let Some(Slot { current: None, .. }) = self.slots.get(&addr) {
+            addr = self.slots[&addr].original.next_addr();
+        }
+        addr
+    }
+
+    /// Rewrite the instruction at `addr`, returning whether anything changed.
+    fn rewrite(&mut self, addr: usize) -> bool {
+        let Some(mut decoded) = self.current(addr).cloned() else {
+            return false;
+        };
+        let len = self.program.len();
+
+        // constant operands
+        let n_reads = reads(&decoded).len();
+        for operand in &mut decoded.operands[..n_reads] {
+            if let Some(source) = position(operand, len).filter(|a| !self.written.contains(a)) {
+                if !self.slots.contains_key(&source) {
+                    *operand = immediate(self.program.ix(source).unwrap_or_default());
+                }
+            }
+        }
+
+        // constant folding
+        if let [a, b, out] = decoded.operands.as_slice() {
+            if a.mode == ParameterMode::Immediate && b.mode == ParameterMode::Immediate {
+                let value = match decoded.opcode {
+                    Opcode::Add => a.value.wrapping_add(b.value),
+                    Opcode::Multiply => a.value.wrapping_mul(b.value),
+                    Opcode::LessThan => Word::from(a.value < b.value),
+                    Opcode::Equals => Word::from(a.value == b.value),
+                    opcode => unreachable!("{opcode:?} does not have three operands"),
+                };
+                decoded = Decoded {
+                    addr,
+                    opcode: Opcode::Add,
+                    operands: vec![immediate(value), immediate(0), *out],
+                };
+            }
+        }
+
+        // jump threading
+        if let Some(mut target) = immediate_target(&decoded) {
+            let mut seen = HashSet::from([addr]);
+            while let Some(next) = self
+                .slots
+                .get(&self.resolve(target))
+                .filter(|slot| !slot.pinned)
+                .and_then(|slot| slot.current.as_ref())
+                .filter(|next| taken(next) == Some(true))
+                .and_then(immediate_target)
+            {
+                if !seen.insert(next) {
+                    // an infinite loop; leave it be
+                    break;
+                }
+                target = next;
+            }
+            decoded.operands[1] = immediate(target as Word);
+        }
+
+        let changed = self.current(addr) != Some(&decoded);
+        self.slots.get_mut(&addr).unwrap().current = Some(decoded);
+        changed
+    }
+
+    /// Whether the instruction at `addr` can be removed without changing the program's behavior.
+    fn removable(&self, addr: usize) -> bool {
+        let Some(decoded) = self.current(addr) else {
+            return false;
+        };
+        let len = self.program.len();
+
+        if decoded.opcode.jumps() {
+            if taken(decoded) == Some(false) {
+                return true;
+            }
+            // a jump to the next instruction, which cannot fault while testing its condition
+            return immediate_target(decoded).is_some_and(|target| {
+                self.resolve(target) == self.resolve(decoded.next_addr())
+                    && reads_safely(&decoded.operands[0], len)
+            });
+        }
+
+        if let Some((source, destination)) = as_move(decoded) {
+            if source == destination && position(source, len).is_some() {
+                return true;
+            }
+        }
+
+        self.dead_store(decoded)
+    }
+
+    /// Whether `decoded` stores a value which is overwritten before anything can read it.
+    fn dead_store(&self, decoded: &Decoded) -> bool {
+        let len = self.program.len();
+        let Some(stored) = write(decoded).and_then(|op| position(op, len)) else {
+            return false;
+        };
+        if decoded.opcode == Opcode::Input || !reads(decoded).iter().all(|op| reads_safely(op, len))
+        {
+            return false;
+        }
+
+        let mut addr = decoded.next_addr();
+        while !self.leaders.contains(&addr) {
+            let Some(slot) = self.slots.get(&addr) else {
+                return false;
+            };
+            addr = slot.original.next_addr();
+            let Some(next) = &slot.current else {
+                continue;
+            };
+            if reads(next)
+                .iter()
+                .any(|op| position(op, len) == Some(stored))
+            {
+                return false;
+            }
+            if write(next).and_then(|op| position(op, len)) == Some(stored) {
+                return true;
+            }
+            if next.opcode.jumps() || next.opcode == Opcode::Halt {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn run(&mut self) {
+        let addrs = self
+            .slots
+            .iter()
+            .filter(|(_, slot)| !slot.pinned)
+            .map(|(&addr, _)| addr)
+            .collect::<Vec<_>>();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &addr in &addrs {
+                changed |= self.rewrite(addr);
+                if self.movable && self.removable(addr) {
+                    self.slots.get_mut(&addr).unwrap().current = None;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Lay out the rewritten program, relocating addresses.
+    fn finish(self, code: &Code) -> Optimized {
+        let len = self.program.len();
+        let return_points = &code.return_points;
+
+        // Words which are not code are only data if some address refers to them.
+        let mut referenced = return_points
+            .iter()
+            .chain(&code.undecodable)
+            .copied()
+            .collect::<HashSet<_>>();
+        for decoded in self.slots.values().filter_map(|slot| slot.current.as_ref()) {
+            referenced.extend(
+                decoded
+                    .operands
+                    .iter()
+                    .filter_map(|operand| position(operand, len)),
+            );
+            referenced.extend(immediate_target(decoded));
+        }
+
+        let mut words = Vec::with_capacity(len);
+        let mut relocations = Vec::with_capacity(len);
+        let mut placed = Vec::new();
+        let mut addr = 0;
+        while addr < len {
+            match self.slots.get(&addr) {
+                Some(slot) => {
+                    let size = slot.original.size();
+                    match &slot.current {
+                        Some(decoded) => {
+                            relocations.extend(words.len()..words.len() + size);
+                            placed.push((words.len(), decoded));
+                            words.extend(decoded.words());
+                        }
+                        // whatever referred to a removed instruction now refers to what follows it
+                        None => relocations.extend(std::iter::repeat_n(words.len(), size)),
+                    }
+                    addr += size;
+                }
+                None => {
+                    relocations.push(words.len());
+                    if !self.movable || referenced.contains(&addr) {
+                        words.push(self.program.ix(addr).unwrap_or_default());
+                    }
+                    addr += 1;
+                }
+            }
+        }
+
+        let relocate = |operand: &mut Operand| {
+            if let Some(addr) = usize::try_from(operand.value).ok().filter(|&a| a < len) {
+                operand.value = relocations[addr] as Word;
+            }
+        };
+        for (new_addr, decoded) in placed {
+            let mut decoded = decoded.clone();
+            let return_address = move_source(&decoded).filter(|&source| {
+                let source = decoded.operands[source];
+                source.mode == ParameterMode::Immediate
+                    && usize::try_from(source.value).is_ok_and(|a| return_points.contains(&a))
+            });
+            let is_jump = decoded.opcode.jumps();
+            for (idx, operand) in decoded.operands.iter_mut().enumerate() {
+                let is_address = match operand.mode {
+                    ParameterMode::Position => true,
+                    ParameterMode::Immediate => {
+                        (is_jump && idx == 1) || return_address == Some(idx)
+                    }
+                    ParameterMode::Relative => false,
+                };
+                if is_address {
+                    relocate(operand);
+                }
+            }
+            words.splice(new_addr..new_addr + decoded.size(), decoded.words());
+        }
+
+        Optimized {
+            program: words.into(),
+            relocations,
+        }
+    }
+}
+
+/// Optimize `program`, as described in the [module documentation](self).
+pub fn optimize(program: &Memory) -> Optimized {
+    let code = Code::new(program);
+    if code.overlaps() {
+        // some words are executed as parts of different instructions; rewriting
+        // one instruction would change another
+        return Optimized::unchanged(program);
+    }
+    let mut optimizer = Optimizer::new(program, &code);
+    optimizer.run();
+    optimizer.finish(&code)
+}
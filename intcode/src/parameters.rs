@@ -1,20 +1,20 @@
 use crate::{
-    error::Result, instruction::ParameterModes, mem_idx::MemIdx as _,
-    parameter_mode::ParameterMode, Computer, Error, Word,
+    error::Result, instruction::ParameterModes, parameter_mode::ParameterMode, Computer, Error,
+    Word,
 };
 
 fn pick_param<const CB: usize, const PC: usize>(
-    computer: &Computer<CB>,
+    computer: &mut Computer<CB>,
     modes: ParameterModes,
     raw: [Word; PC],
     idx: usize,
 ) -> Result<Word> {
     let param = match modes[idx] {
-        ParameterMode::Position => computer.memory.ix(raw[idx])?,
+        ParameterMode::Position => computer.operand(raw[idx])?,
         ParameterMode::Immediate => raw[idx],
         ParameterMode::Relative => {
-            let addr = raw[idx] + computer.relative_base;
-            computer.memory.ix(addr)?
+            let addr = raw[idx].wrapping_add(computer.relative_base);
+            computer.operand(addr)?
         }
     };
     Ok(param)
@@ -27,10 +27,10 @@ fn pick_param_mut<const CB: usize, const PC: usize>(
     idx: usize,
 ) -> Result<&mut Word> {
     let param = match modes[idx] {
-        ParameterMode::Position => computer.memory.ix_mut(raw[idx])?,
+        ParameterMode::Position => computer.operand_mut(raw[idx])?,
         ParameterMode::Relative => {
-            let addr = raw[idx] + computer.relative_base;
-            computer.memory.ix_mut(addr)?
+            let addr = raw[idx].wrapping_add(computer.relative_base);
+            computer.operand_mut(addr)?
         }
         ParameterMode::Immediate => return Err(Error::ImmediateWrite),
     };
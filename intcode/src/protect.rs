@@ -0,0 +1,125 @@
+//! Opt-in protection of memory from being written or executed.
+//!
+//! Protection is checked as each instruction is interpreted. Code compiled by
+//! [`aot`](crate::aot) is not run while any range is protected; the program is
+//! interpreted instead.
+
+use std::ops::Range;
+
+use crate::{
+    error::{Error, Result},
+    Computer,
+};
+
+/// What a protected range of memory forbids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protection {
+    /// Instructions may not store to the range.
+    ReadOnly,
+    /// No part of an executed instruction may lie in the range.
+    NoExecute,
+}
+
+/// The protected ranges of one computer, and which addresses it has executed and written.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Guard {
+    read_only: Vec<Range<usize>>,
+    no_execute: Vec<Range<usize>>,
+    /// Whether `executed` and `written` are being recorded.
+    tracking: bool,
+    executed: Vec<bool>,
+    written: Vec<bool>,
+}
+
+fn mark(marks: &mut Vec<bool>, addrs: Range<usize>) {
+    if marks.len() < addrs.end {
+        marks.resize(addrs.end, false);
+    }
+    marks[addrs].fill(true);
+}
+
+impl Guard {
+    pub(crate) fn is_active(&self) -> bool {
+        self.tracking || !self.read_only.is_empty() || !self.no_execute.is_empty()
+    }
+
+    /// Forget which addresses have been executed and written.
+    pub(crate) fn reset(&mut self) {
+        self.executed.clear();
+        self.written.clear();
+    }
+
+    /// Check that the instruction at `ip`, of `len` words, may be executed.
+    pub(crate) fn execute(&mut self, ip: usize, len: usize) -> Result<()> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        let words = ip..ip + len;
+        if let Some(addr) = words.clone().find(|addr| {
+            self.no_execute
+                .iter()
+                .any(|protected| protected.contains(addr))
+        }) {
+            return Err(Error::ExecuteProtected { ip, addr });
+        }
+        if self.tracking {
+            mark(&mut self.executed, words);
+        }
+        Ok(())
+    }
+
+    /// Check that the instruction at `ip` may store to `addr`.
+    pub(crate) fn write(&self, ip: usize, addr: usize) -> Result<()> {
+        if self
+            .read_only
+            .iter()
+            .any(|protected| protected.contains(&addr))
+        {
+            return Err(Error::WriteProtected { ip, addr });
+        }
+        Ok(())
+    }
+
+    /// Record that an instruction has stored to `addr` in memory.
+    pub(crate) fn written(&mut self, addr: usize) {
+        if self.tracking {
+            mark(&mut self.written, addr..addr + 1);
+        }
+    }
+}
+
+impl<const CHANNEL_BUFFER: usize> Computer<CHANNEL_BUFFER> {
+    /// Forbid instructions from storing to, or executing, the addresses in `range`.
+    ///
+    /// Violations fault with [`Error::WriteProtected`] or [`Error::ExecuteProtected`].
+    /// [`patch`](Self::patch) is not restricted.
+    pub fn protect(&mut self, range: Range<usize>, protection: Protection) {
+        match protection {
+            Protection::ReadOnly => self.guard.read_only.push(range),
+            Protection::NoExecute => self.guard.no_execute.push(range),
+        }
+    }
+
+    /// Start recording which addresses are executed and written, for
+    /// [`self_modification`](Self::self_modification).
+    pub fn track_self_modification(&mut self) {
+        self.guard.tracking = true;
+    }
+
+    /// Every address which has been both executed and written, in either order,
+    /// since [`track_self_modification`](Self::track_self_modification) was called, or the
+    /// computer was last [reset](Self::reset_from).
+    ///
+    /// Only stores which reach memory count: not those to [devices](crate::mmio), nor
+    /// those which fault.
+    pub fn self_modification(&self) -> Vec<usize> {
+        self.guard
+            .executed
+            .iter()
+            .zip(&self.guard.written)
+            .enumerate()
+            .filter(|(_, (executed, written))| **executed && **written)
+            .map(|(addr, _)| addr)
+            .collect()
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use crate::{
+    error::{Error, Result},
+    io::{Queues, Status},
+    Computer, Word,
+};
+
+/// Identifies a task within a [`Scheduler`].
+pub type TaskId = usize;
+
+struct Task<const CB: usize> {
+    computer: Computer<CB>,
+    inbox: VecDeque<Word>,
+    outbox: Vec<Word>,
+    status: Option<Status>,
+}
+
+impl<const CB: usize> Task<CB> {
+    fn runnable(&self) -> bool {
+        match self.status {
+            None | Some(Status::Preempted) => true,
+            Some(Status::AwaitingInput) => !self.inbox.is_empty(),
+            Some(Status::Halted) => false,
+        }
+    }
+}
+
+/// Time-slice any number of computers on the current thread.
+///
+/// Each task has an inbox and an outbox in place of its computer's channels. Each
+/// [`round`][Self::round] gives every runnable task a turn, in the order they were spawned;
+/// a turn ends when the task halts, awaits an input which is not in its inbox, or has
+/// executed `quantum` instructions.
+///
+/// Scheduling does not depend on timing, so a given set of programs and inputs always
+/// produces the same results.
+///
+/// ```rust,ignore
+/// let mut scheduler = Scheduler::<0>::new();
+/// let task = scheduler.spawn(Computer::new(program));
+/// scheduler.send(task, [1, 2, 3]);
+/// while !scheduler.is_halted() {
+///     scheduler.round()?;
+///     for output in scheduler.take_outputs(task) {
+///         // ...
+///     }
+/// }
+/// ```
+pub struct Scheduler<const CHANNEL_BUFFER: usize = 0> {
+    tasks: Vec<Task<CHANNEL_BUFFER>>,
+    quantum: usize,
+}
+
+impl<const CHANNEL_BUFFER: usize> Default for Scheduler<CHANNEL_BUFFER> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CHANNEL_BUFFER: usize> Scheduler<CHANNEL_BUFFER> {
+    /// How many instructions a task may execute per turn, by default.
+    pub const DEFAULT_QUANTUM: usize = 1000;
+
+    pub fn new() -> Self {
+        Self::with_quantum(Self::DEFAULT_QUANTUM)
+    }
+
+    pub fn with_quantum(quantum: usize) -> Self {
+        assert!(quantum > 0, "quantum must be positive");
+        Self {
+            tasks: Vec::new(),
+            quantum,
+        }
+    }
+
+    /// Add a computer to the scheduler.
+    pub fn spawn(&mut self, computer: Computer<CHANNEL_BUFFER>) -> TaskId {
+        self.tasks.push(Task {
+            computer,
+            inbox: VecDeque::new(),
+            outbox: Vec::new(),
+            status: None,
+        });
+        self.tasks.len() - 1
+    }
+
+    /// How many tasks have been spawned.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Append inputs to the inbox of `task`.
+    pub fn send(&mut self, task: TaskId, inputs: impl IntoIterator<Item = Word>) {
+        self.tasks[task].inbox.extend(inputs);
+    }
+
+    /// How many inputs are waiting in the inbox of `task`.
+    pub fn pending(&self, task: TaskId) -> usize {
+        self.tasks[task].inbox.len()
+    }
+
+    /// Take all outputs produced by `task` since the last time they were taken.
+    pub fn take_outputs(&mut self, task: TaskId) -> Vec<Word> {
+        std::mem::take(&mut self.tasks[task].outbox)
+    }
+
+    /// Why `task` ended its most recent turn, or `None` if it has not yet run.
+    pub fn status(&self, task: TaskId) -> Option<Status> {
+        self.tasks[task].status
+    }
+
+    pub fn computer(&self, task: TaskId) -> &Computer<CHANNEL_BUFFER> {
+        &self.tasks[task].computer
+    }
+
+    /// `true` when every task has halted.
+    pub fn is_halted(&self) -> bool {
+        self.tasks
+            .iter()
+            .all(|task| task.status == Some(Status::Halted))
+    }
+
+    /// Give every runnable task one turn.
+    ///
+    /// Returns the total number of instructions executed.
+    ///
+    /// If some task has not halted but no task can run, then no further progress is possible
+    /// unless more inputs are sent. In that case, this returns [`Error::Deadlock`] naming the
+    /// instruction pointer of each task which has not halted.
+    pub fn round(&mut self) -> Result<usize> {
+        if self.is_halted() {
+            return Ok(0);
+        }
+
+        let mut steps = 0;
+        for task in self.tasks.iter_mut().filter(|task| task.runnable()) {
+            let (status, task_steps) = task.computer.run_queued(
+                &mut Queues {
+                    input: &mut task.inbox,
+                    output: &mut task.outbox,
+                },
+                self.quantum,
+            )?;
+            task.status = Some(status);
+            steps += task_steps;
+        }
+
+        if steps == 0 {
+            let blocked = self
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, task)| task.status != Some(Status::Halted))
+                .map(|(id, task)| (id, task.computer.instruction_pointer))
+                .collect();
+            return Err(Error::Deadlock(blocked));
+        }
+
+        Ok(steps)
+    }
+
+    /// Run rounds until every task has halted.
+    ///
+    /// Outputs accumulate in each task's outbox; nothing is routed between tasks.
+    pub fn run(&mut self) -> Result<()> {
+        while !self.is_halted() {
+            self.round()?;
+        }
+        Ok(())
+    }
+}
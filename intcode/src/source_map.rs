@@ -0,0 +1,163 @@
+//! Where each address of an assembled program came from.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::error::{Error, Result};
+
+/// A line of assembly source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Maps addresses to the source lines which produced them, and to labels.
+///
+/// A source map can be stored as text, one entry per line: `span START SIZE LINE FILE`
+/// for each instruction or `data` directive, and `label NAME ADDR` for each label.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// Each span's start, with its size and where it came from.
+    spans: BTreeMap<usize, (usize, Location)>,
+    /// The first label defined at each address.
+    labels: BTreeMap<usize, String>,
+}
+
+impl SourceMap {
+    /// Record that the `size` words from `start` came from `location`.
+    pub fn add_span(&mut self, start: usize, size: usize, location: Location) {
+        if size > 0 {
+            self.spans.insert(start, (size, location));
+        }
+    }
+
+    pub fn add_label(&mut self, name: &str, addr: usize) {
+        self.labels.entry(addr).or_insert_with(|| name.to_owned());
+    }
+
+    /// Add everything in `other`, with its addresses moved up by `base`.
+    pub fn extend(&mut self, other: &SourceMap, base: usize) {
+        for (start, (size, location)) in &other.spans {
+            self.add_span(base + start, *size, location.clone());
+        }
+        for (addr, name) in &other.labels {
+            self.add_label(name, base + addr);
+        }
+    }
+
+    /// The source line which produced the word at `addr`.
+    pub fn location(&self, addr: usize) -> Option<&Location> {
+        let (start, (size, location)) = self.spans.range(..=addr).next_back()?;
+        (addr < start + size).then_some(location)
+    }
+
+    /// The label at or most closely before `addr`, with how far `addr` is past it.
+    pub fn label(&self, addr: usize) -> Option<(&str, usize)> {
+        let (start, name) = self.labels.range(..=addr).next_back()?;
+        Some((name, addr - start))
+    }
+
+    /// The labels, in address order.
+    pub fn labels(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.labels
+            .iter()
+            .map(|(addr, name)| (*addr, name.as_str()))
+    }
+
+    /// Where `addr` is in the source, such as `prog.asm:12, loop+2`, if the map knows.
+    pub fn annotation(&self, addr: usize) -> Option<String> {
+        let label = self.label(addr).map(|(name, offset)| match offset {
+            0 => name.to_owned(),
+            _ => format!("{name}+{offset}"),
+        });
+        match (self.location(addr), label) {
+            (Some(location), Some(label)) => Some(format!("{location}, {label}")),
+            (Some(location), None) => Some(location.to_string()),
+            (None, label) => label,
+        }
+    }
+
+    /// `addr`, followed by its [annotation](Self::annotation) in parentheses if it has one.
+    pub fn describe(&self, addr: usize) -> String {
+        match self.annotation(addr) {
+            Some(annotation) => format!("{addr} ({annotation})"),
+            None => addr.to_string(),
+        }
+    }
+
+    /// Parse one line of a source map, returning whether it was an entry.
+    pub(crate) fn parse_entry(&mut self, line: &str) -> Result<bool, String> {
+        let number = |text: &str| {
+            text.parse::<usize>()
+                .map_err(|_| format!("invalid number {text:?}"))
+        };
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("span") => {
+                let mut numbers = [0; 3];
+                for number_field in &mut numbers {
+                    *number_field = number(fields.next().unwrap_or_default())?;
+                }
+                let [start, size, line_no] = numbers;
+                let file = fields.collect::<Vec<_>>().join(" ");
+                if file.is_empty() {
+                    return Err("span has no file".into());
+                }
+                self.add_span(
+                    start,
+                    size,
+                    Location {
+                        file,
+                        line: line_no,
+                    },
+                );
+                Ok(true)
+            }
+            Some("label") => match (fields.next(), fields.next(), fields.next()) {
+                (Some(name), Some(addr), None) => {
+                    self.add_label(name, number(addr)?);
+                    Ok(true)
+                }
+                _ => Err("expected `label NAME ADDR`".into()),
+            },
+            _ => Ok(false),
+        }
+    }
+}
+
+impl fmt::Display for SourceMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (addr, name) in &self.labels {
+            writeln!(f, "label {name} {addr}")?;
+        }
+        for (start, (size, location)) in &self.spans {
+            writeln!(f, "span {start} {size} {} {}", location.line, location.file)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SourceMap {
+    type Err = Error;
+
+    /// Parse a source map in the form which it displays as; blank lines are ignored.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut map = Self::default();
+        for (idx, line) in s.lines().enumerate() {
+            let error = |message: String| Error::SourceMap {
+                line: idx + 1,
+                message,
+            };
+            if !map.parse_entry(line).map_err(error)? && !line.trim().is_empty() {
+                return Err(error(format!("unexpected {line:?}")));
+            }
+        }
+        Ok(map)
+    }
+}
@@ -0,0 +1,157 @@
+//! Running many variants of one program in parallel.
+//!
+//! Each worker thread reuses a single [`Computer`], resetting it in place between variants,
+//! and claims variants in batches; so beyond starting the workers, running a variant costs
+//! little more than interpreting it. The speedup over running the variants one at a time
+//! therefore comes from the worker threads alone, and is at most the number of cores.
+
+use std::{
+    num::NonZeroUsize,
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{error::Result, memory::Memory, Computer, Word};
+
+/// Reset `computer` to `program` with `variant`'s patches applied, and run it until it halts.
+fn run_variant(computer: &mut Computer, program: &Memory, variant: &[(usize, Word)]) -> Result<()> {
+    computer.reset_from(program);
+    for &(addr, value) in variant {
+        computer.patch(addr, value)?;
+    }
+    computer.run_to_halt()
+}
+
+/// How many worker threads to use for `n_variants` variants.
+fn n_workers(n_variants: usize) -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(n_variants)
+}
+
+/// How many variants each worker claims at once, for `n_variants` variants over `n_workers`.
+///
+/// Small enough that the workers finish at about the same time, and that [`sweep_find`]
+/// does little work past a match.
+fn batch_size(n_variants: usize, n_workers: usize) -> usize {
+    (n_variants / (n_workers * 16)).clamp(1, 64)
+}
+
+/// Claim the next batch of variants, if any are left.
+fn claim(next: &AtomicUsize, batch: usize, n_variants: usize) -> Option<Range<usize>> {
+    let start = next.fetch_add(batch, Ordering::Relaxed);
+    (start < n_variants).then(|| start..n_variants.min(start + batch))
+}
+
+/// Run `program` once for each variant, in parallel.
+///
+/// Each variant is a list of `(addr, value)` patches applied to `program` before it runs.
+/// When the run halts, `inspect` examines the computer. The result for each variant is what
+/// `inspect` returned, or the error which stopped the run; results are returned in the
+/// same order as `variants`, and a fault in one variant does not affect the others.
+///
+/// Each worker thread reuses a single [`Computer`] for all the variants it handles, so there
+/// is no per-variant allocation beyond what the program itself requires. Running a variant
+/// then costs little more than interpreting it, so the speedup over running each variant
+/// alone comes from the worker threads, and is at most the number of cores.
+///
+/// Nothing is connected to the computers' I/O: an `Input` instruction will time out.
+pub fn sweep<Variant, T, F>(program: &Memory, variants: &[Variant], inspect: F) -> Vec<Result<T>>
+where
+    Variant: Sync + AsRef<[(usize, Word)]>,
+    F: Sync + Fn(&Computer) -> T,
+    T: Send,
+{
+    let next = AtomicUsize::new(0);
+    let n_workers = n_workers(variants.len());
+    let batch = batch_size(variants.len(), n_workers);
+
+    std::thread::scope(|scope| {
+        let workers = (0..n_workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut computer = Computer::<0>::new(program.clone());
+                    let mut results = Vec::with_capacity(variants.len() / n_workers + batch);
+                    while let Some(batch) = claim(&next, batch, variants.len()) {
+                        for idx in batch {
+                            let result =
+                                run_variant(&mut computer, program, variants[idx].as_ref())
+                                    .map(|()| inspect(&computer));
+                            results.push((idx, result));
+                        }
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut results = Vec::with_capacity(variants.len());
+        for worker in workers {
+            let worker_results = worker
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            results.extend(worker_results);
+        }
+        results.sort_unstable_by_key(|(idx, _)| *idx);
+        results.into_iter().map(|(_, result)| result).collect()
+    })
+}
+
+/// Run `program` for each variant, in parallel, until `inspect` accepts one.
+///
+/// Returns the index of the first variant, in order, for which `inspect` returns `Some`,
+/// along with what it returned. Variants whose runs fault are skipped.
+///
+/// Variants are run as by [`sweep`], but once a match is found, no later variant is started:
+/// only those before it still need to be ruled out.
+pub fn sweep_find<Variant, T, F>(
+    program: &Memory,
+    variants: &[Variant],
+    inspect: F,
+) -> Option<(usize, T)>
+where
+    Variant: Sync + AsRef<[(usize, Word)]>,
+    F: Sync + Fn(&Computer) -> Option<T>,
+    T: Send,
+{
+    let next = AtomicUsize::new(0);
+    let found = AtomicUsize::new(usize::MAX);
+    let n_workers = n_workers(variants.len());
+    let batch = batch_size(variants.len(), n_workers);
+
+    std::thread::scope(|scope| {
+        let workers = (0..n_workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut computer = Computer::<0>::new(program.clone());
+                    while let Some(batch) = claim(&next, batch, variants.len()) {
+                        for idx in batch {
+                            if idx > found.load(Ordering::Relaxed) {
+                                return None;
+                            }
+                            if run_variant(&mut computer, program, variants[idx].as_ref()).is_err()
+                            {
+                                continue;
+                            }
+                            if let Some(result) = inspect(&computer) {
+                                // each worker takes variants in increasing order, so this is its first
+                                found.fetch_min(idx, Ordering::Relaxed);
+                                return Some((idx, result));
+                            }
+                        }
+                    }
+                    None
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .filter_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .min_by_key(|(idx, _)| *idx)
+    })
+}
@@ -0,0 +1,605 @@
+//! Symbolic execution: running a program when some of its memory, or its input, is unknown.
+//!
+//! An [`Executor`] runs a program in which chosen memory cells hold named symbols, and in
+//! which input not provided up front reads as fresh symbols `input0`, `input1`, and so on.
+//! Arithmetic on symbols builds [`Expr`]essions. A conditional jump on an expression forks
+//! execution, and each [`Path`] records the [`Constraint`]s under which it was followed.
+//!
+//! Execution needs concrete values in some places: opcodes, the targets of taken jumps, the
+//! relative base, and the addresses of writes. A path which would need a symbol there stops
+//! with [`Outcome::Unsupported`]. Reading through a symbolic address is allowed, and yields
+//! an opaque `mem[...]` term standing for whatever that cell held at the time; day02's
+//! programs do this, and overwrite the result before it matters.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    ops::{Add, Mul, Range},
+};
+
+use crate::{
+    error::Error,
+    instruction::Instruction,
+    mem_idx::MemIdx as _,
+    memory::{Memory, MEMORY_LIMIT},
+    opcode::Opcode,
+    parameter_mode::ParameterMode,
+    Word,
+};
+
+/// Any path still running after this many instructions stops with [`Outcome::StepLimit`].
+const STEP_LIMIT: usize = 1_000_000;
+
+/// Once this many paths exist, any further fork stops with [`Outcome::PathLimit`].
+const PATH_LIMIT: usize = 1_000;
+
+/// A factor of a term which arithmetic cannot look inside.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Atom {
+    Symbol(String),
+    /// `1` if the first is less than the second, otherwise `0`.
+    LessThan(Box<Expr>, Box<Expr>),
+    /// `1` if the two are equal, otherwise `0`.
+    Equals(Box<Expr>, Box<Expr>),
+    /// The value of memory at an address which was not known.
+    Read(Box<Expr>),
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Atom::Symbol(name) => write!(f, "{name}"),
+            Atom::LessThan(a, b) => write!(f, "({a} < {b})"),
+            Atom::Equals(a, b) => write!(f, "({a} == {b})"),
+            Atom::Read(addr) => write!(f, "mem[{addr}]"),
+        }
+    }
+}
+
+/// A symbolic value: a polynomial, with wrapping coefficients, over [`Atom`]s.
+///
+/// Expressions are kept in a canonical form, so structurally equal expressions are equal as
+/// values, and an expression without atoms is a constant.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Expr {
+    /// Each monomial, as its sorted atoms, with its nonzero coefficient.
+    terms: BTreeMap<Vec<Atom>, Word>,
+}
+
+impl Expr {
+    pub fn constant(value: Word) -> Self {
+        let mut terms = BTreeMap::new();
+        if value != 0 {
+            terms.insert(Vec::new(), value);
+        }
+        Self { terms }
+    }
+
+    pub fn symbol(name: impl Into<String>) -> Self {
+        Self::atom(Atom::Symbol(name.into()))
+    }
+
+    fn atom(atom: Atom) -> Self {
+        Self {
+            terms: BTreeMap::from([(vec![atom], 1)]),
+        }
+    }
+
+    /// The value of this expression, if it does not depend on any atom.
+    pub fn as_constant(&self) -> Option<Word> {
+        match self.terms.iter().next() {
+            None => Some(0),
+            Some((monomial, &coefficient)) if monomial.is_empty() && self.terms.len() == 1 => {
+                Some(coefficient)
+            }
+            Some(_) => None,
+        }
+    }
+
+    pub fn less_than(&self, other: &Self) -> Self {
+        match (self.as_constant(), other.as_constant()) {
+            (Some(a), Some(b)) => Self::constant(Word::from(a < b)),
+            _ if self == other => Self::constant(0),
+            _ => Self::atom(Atom::LessThan(
+                Box::new(self.clone()),
+                Box::new(other.clone()),
+            )),
+        }
+    }
+
+    pub fn equals(&self, other: &Self) -> Self {
+        match (self.as_constant(), other.as_constant()) {
+            (Some(a), Some(b)) => Self::constant(Word::from(a == b)),
+            _ if self == other => Self::constant(1),
+            _ => Self::atom(Atom::Equals(
+                Box::new(self.clone()),
+                Box::new(other.clone()),
+            )),
+        }
+    }
+
+    fn read(addr: Self) -> Self {
+        Self::atom(Atom::Read(Box::new(addr)))
+    }
+
+    /// The highest number of atoms multiplied together in any term.
+    pub fn degree(&self) -> usize {
+        self.terms.keys().map(Vec::len).max().unwrap_or_default()
+    }
+
+    /// Whether this expression depends on a read of memory at an address which was not known.
+    ///
+    /// Such a read stays opaque even once its address is known, as what memory held there at
+    /// the time is not.
+    pub fn reads_memory(&self) -> bool {
+        self.terms.keys().flatten().any(|atom| match atom {
+            Atom::Symbol(_) => false,
+            Atom::LessThan(a, b) | Atom::Equals(a, b) => a.reads_memory() || b.reads_memory(),
+            Atom::Read(_) => true,
+        })
+    }
+
+    /// Replace each symbol for which `values` has a value, then simplify.
+    pub fn substitute(&self, values: &impl Fn(&str) -> Option<Word>) -> Self {
+        let mut result = Self::default();
+        for (monomial, &coefficient) in &self.terms {
+            let mut term = Self::constant(coefficient);
+            for atom in monomial {
+                let factor = match atom {
+                    Atom::Symbol(name) => match values(name) {
+                        Some(value) => Self::constant(value),
+                        None => Self::atom(atom.clone()),
+                    },
+                    Atom::LessThan(a, b) => a.substitute(values).less_than(&b.substitute(values)),
+                    Atom::Equals(a, b) => a.substitute(values).equals(&b.substitute(values)),
+                    Atom::Read(addr) => Self::read(addr.substitute(values)),
+                };
+                term = term * factor;
+            }
+            result = result + term;
+        }
+        result
+    }
+
+    /// The value of this expression given `values` for its symbols, if they determine it.
+    pub fn evaluate(&self, values: &impl Fn(&str) -> Option<Word>) -> Option<Word> {
+        self.substitute(values).as_constant()
+    }
+
+    /// If this expression is `a * name + c` for a nonzero `a`, then `(a, c)`.
+    fn linear_in(&self, name: &str) -> Option<(Word, Word)> {
+        let mut linear = (0, 0);
+        for (monomial, &coefficient) in &self.terms {
+            match monomial.as_slice() {
+                [] => linear.1 = coefficient,
+                [Atom::Symbol(symbol)] if symbol == name => linear.0 = coefficient,
+                _ => return None,
+            }
+        }
+        (linear.0 != 0).then_some(linear)
+    }
+
+    /// Find values for the symbols in `ranges`, in order, for which this expression is `target`.
+    ///
+    /// Every symbol but the last is tried in turn over its range. If what remains is linear in
+    /// the last symbol, it is solved for directly, in the same wrapping arithmetic as the
+    /// interpreter; otherwise each value in its range is tried.
+    pub fn solve(&self, target: Word, ranges: &[(&str, Range<Word>)]) -> Option<Vec<Word>> {
+        let ((name, range), rest) = ranges.split_first()?;
+        if rest.is_empty() {
+            let is_solution = |value: &Word| {
+                self.evaluate(&|symbol| (symbol == *name).then_some(*value)) == Some(target)
+            };
+            let value = match self.linear_in(name) {
+                Some((a, c)) => solve_linear(a, target.wrapping_sub(c), range),
+                None => range.clone().find(is_solution),
+            };
+            return value.map(|value| vec![value]);
+        }
+        range.clone().find_map(|value| {
+            let rest_solution = self
+                .substitute(&|symbol| (symbol == *name).then_some(value))
+                .solve(target, rest)?;
+            Some(std::iter::once(value).chain(rest_solution).collect())
+        })
+    }
+}
+
+/// The first `x` in `range` for which `a * x == b` in wrapping arithmetic, for a nonzero `a`.
+///
+/// Writing `a` as `2^k` times an odd number, there are solutions only if `b` is a multiple
+/// of `2^k`, and then they are `2^(64 - k)` apart.
+fn solve_linear(a: Word, b: Word, range: &Range<Word>) -> Option<Word> {
+    let (a, b) = (a as u64, b as u64);
+    let k = a.trailing_zeros();
+    if b.trailing_zeros() < k {
+        return None;
+    }
+    let odd = a >> k;
+    // an odd number is its own inverse modulo 8, and each step doubles the bits which are right
+    let mut inverse = odd;
+    for _ in 0..5 {
+        inverse = inverse.wrapping_mul(2_u64.wrapping_sub(odd.wrapping_mul(inverse)));
+    }
+    let mask = u64::MAX >> k;
+    let solution = (b >> k).wrapping_mul(inverse) & mask;
+    let offset = solution.wrapping_sub(range.start as u64) & mask;
+    let value = range.start.checked_add_unsigned(offset)?;
+    range.contains(&value).then_some(value)
+}
+
+impl From<Word> for Expr {
+    fn from(value: Word) -> Self {
+        Self::constant(value)
+    }
+}
+
+impl Add for Expr {
+    type Output = Expr;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        for (monomial, coefficient) in rhs.terms {
+            let sum = self.terms.entry(monomial).or_default();
+            *sum = sum.wrapping_add(coefficient);
+        }
+        self.terms.retain(|_, coefficient| *coefficient != 0);
+        self
+    }
+}
+
+impl Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut product = BTreeMap::<Vec<Atom>, Word>::new();
+        for (a, a_coefficient) in &self.terms {
+            for (b, b_coefficient) in &rhs.terms {
+                let mut monomial = a.iter().chain(b).cloned().collect::<Vec<_>>();
+                monomial.sort();
+                let sum = product.entry(monomial).or_default();
+                *sum = sum.wrapping_add(a_coefficient.wrapping_mul(*b_coefficient));
+            }
+        }
+        product.retain(|_, coefficient| *coefficient != 0);
+        Self { terms: product }
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Terms of higher degree come first, and the constant last.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        let mut terms = self.terms.iter().collect::<Vec<_>>();
+        terms.sort_by_key(|(monomial, _)| std::cmp::Reverse(monomial.len()));
+        for (idx, (monomial, &coefficient)) in terms.into_iter().enumerate() {
+            let magnitude = coefficient.unsigned_abs();
+            match (idx, coefficient < 0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            if monomial.is_empty() || magnitude != 1 {
+                write!(f, "{magnitude}")?;
+                if !monomial.is_empty() {
+                    write!(f, " * ")?;
+                }
+            }
+            for (idx, atom) in monomial.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, " * ")?;
+                }
+                write!(f, "{atom}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A condition on which a path depends: that `condition` is nonzero, if `holds`, or zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    pub condition: Expr,
+    pub holds: bool,
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = if self.holds { "!=" } else { "==" };
+        write!(f, "{} {op} 0", self.condition)
+    }
+}
+
+/// How a path ended.
+#[derive(Debug)]
+pub enum Outcome {
+    Halted,
+    /// The program faulted, exactly as it would have on a [`Computer`](crate::Computer).
+    Fault(Error),
+    /// Execution needed a concrete value where it had only an expression.
+    Unsupported(String),
+    StepLimit,
+    PathLimit,
+}
+
+/// The state of one path of execution.
+#[derive(Debug, Clone, Default)]
+struct State {
+    /// Every cell below `len` not stored here is zero.
+    memory: BTreeMap<usize, Expr>,
+    len: usize,
+    instruction_pointer: usize,
+    relative_base: Word,
+    input: VecDeque<Word>,
+    inputs_read: usize,
+    output: Vec<Expr>,
+    constraints: Vec<Constraint>,
+    steps: usize,
+}
+
+/// What a single instruction did.
+enum Step {
+    Continue,
+    /// Execution forked; this path follows one way, and the returned state the other.
+    Fork(State),
+}
+
+impl State {
+    fn read(&self, addr: usize) -> Result<Expr, Outcome> {
+        if addr > MEMORY_LIMIT {
+            return Err(Outcome::Fault(Error::MemoryExhausted {
+                idx: addr,
+                len: MEMORY_LIMIT,
+            }));
+        }
+        Ok(self.memory.get(&addr).cloned().unwrap_or_default())
+    }
+
+    fn write(&mut self, addr: usize, value: Expr) -> Result<(), Outcome> {
+        if addr > MEMORY_LIMIT {
+            return Err(Outcome::Fault(Error::MemoryExhausted {
+                idx: addr,
+                len: MEMORY_LIMIT,
+            }));
+        }
+        self.len = self.len.max(addr + 1);
+        if value.as_constant() == Some(0) {
+            self.memory.remove(&addr);
+        } else {
+            self.memory.insert(addr, value);
+        }
+        Ok(())
+    }
+
+    /// The address which parameter `raw` refers to in `mode`, if it refers to one and it is
+    /// known; otherwise the expression for that address.
+    fn address(&self, mode: ParameterMode, raw: &Expr) -> Result<Option<usize>, Expr> {
+        let offset = match mode {
+            ParameterMode::Immediate => return Ok(None),
+            ParameterMode::Position => raw.clone(),
+            ParameterMode::Relative => Expr::constant(self.relative_base) + raw.clone(),
+        };
+        let Some(addr) = offset.as_constant() else {
+            return Err(offset);
+        };
+        Ok(Some(addr.try_into().map_err(|_| Expr::constant(addr))?))
+    }
+
+    fn load(&self, mode: ParameterMode, raw: &Expr) -> Result<Expr, Outcome> {
+        match self.address(mode, raw) {
+            Ok(None) => Ok(raw.clone()),
+            Ok(Some(addr)) => self.read(addr),
+            Err(addr) => match addr.as_constant() {
+                Some(addr) => Err(Outcome::Fault(Error::IndexFailed(addr))),
+                None => Ok(Expr::read(addr)),
+            },
+        }
+    }
+
+    fn store(&mut self, mode: ParameterMode, raw: &Expr, value: Expr) -> Result<(), Outcome> {
+        match self.address(mode, raw) {
+            Ok(None) => Err(Outcome::Fault(Error::ImmediateWrite)),
+            Ok(Some(addr)) => self.write(addr, value),
+            Err(addr) => match addr.as_constant() {
+                Some(addr) => Err(Outcome::Fault(Error::IndexFailed(addr))),
+                None => Err(Outcome::Unsupported(format!("write to mem[{addr}]"))),
+            },
+        }
+    }
+
+    fn step(&mut self, may_fork: bool) -> Result<Step, Outcome> {
+        let ip = self.instruction_pointer;
+        let word = self.read(ip)?;
+        let Some(word) = word.as_constant() else {
+            return Err(Outcome::Unsupported(format!("instruction {word} at {ip}")));
+        };
+        let instruction = Instruction::try_from(word).map_err(Outcome::Fault)?;
+        let n_params = instruction.opcode.parameter_count();
+        if ip + 1 + n_params > self.len {
+            return Err(Outcome::Fault(Error::MemoryExhausted {
+                idx: ip + 1 + n_params,
+                len: self.len,
+            }));
+        }
+        let raw = (0..n_params)
+            .map(|idx| self.read(ip + 1 + idx))
+            .collect::<Result<Vec<_>, _>>()?;
+        let modes = instruction.modes;
+        let param = |idx: usize| self.load(modes[idx], &raw[idx]);
+
+        let mut next_ip = ip + 1 + n_params;
+        let mut step = Step::Continue;
+        match instruction.opcode {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => {
+                let (a, b) = (param(0)?, param(1)?);
+                let value = match instruction.opcode {
+                    Opcode::Add => a + b,
+                    Opcode::Multiply => a * b,
+                    Opcode::LessThan => a.less_than(&b),
+                    _ => a.equals(&b),
+                };
+                self.store(modes[2], &raw[2], value)?;
+            }
+            Opcode::Input => {
+                let value = match self.input.pop_front() {
+                    Some(value) => Expr::constant(value),
+                    None => Expr::symbol(format!("input{}", self.inputs_read)),
+                };
+                self.inputs_read += 1;
+                self.store(modes[0], &raw[0], value)?;
+            }
+            Opcode::Output => {
+                let value = param(0)?;
+                self.output.push(value);
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let (test, target) = (param(0)?, param(1)?);
+                let when_nonzero = instruction.opcode == Opcode::JumpIfTrue;
+                let known = match test.as_constant() {
+                    Some(value) => Some(value != 0),
+                    None => self
+                        .constraints
+                        .iter()
+                        .find(|constraint| constraint.condition == test)
+                        .map(|constraint| constraint.holds),
+                };
+                let nonzero = match known {
+                    Some(nonzero) => nonzero,
+                    None if !may_fork => return Err(Outcome::PathLimit),
+                    None => {
+                        // the fork retries this jump, knowing the test to be zero
+                        let mut other = self.clone();
+                        other.constraints.push(Constraint {
+                            condition: test.clone(),
+                            holds: false,
+                        });
+                        step = Step::Fork(other);
+                        self.constraints.push(Constraint {
+                            condition: test,
+                            holds: true,
+                        });
+                        true
+                    }
+                };
+                if nonzero == when_nonzero {
+                    self.jump(&target)?;
+                    next_ip = self.instruction_pointer;
+                }
+            }
+            Opcode::RelativeBaseOffset => {
+                let adjust = param(0)?;
+                let Some(adjust) = adjust.as_constant() else {
+                    return Err(Outcome::Unsupported(format!(
+                        "relative base offset {adjust}"
+                    )));
+                };
+                self.relative_base = self.relative_base.wrapping_add(adjust);
+            }
+            Opcode::Halt => return Err(Outcome::Halted),
+        }
+        self.instruction_pointer = next_ip;
+        self.steps += 1;
+        Ok(step)
+    }
+
+    fn jump(&mut self, target: &Expr) -> Result<(), Outcome> {
+        let Some(target) = target.as_constant() else {
+            return Err(Outcome::Unsupported(format!("jump to {target}")));
+        };
+        self.instruction_pointer = target
+            .try_into()
+            .map_err(|_| Outcome::Fault(Error::IndexFailed(target)))?;
+        Ok(())
+    }
+}
+
+/// One way execution can go, and where it ended up.
+#[derive(Debug)]
+pub struct Path {
+    state: State,
+    outcome: Outcome,
+}
+
+impl Path {
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+
+    /// The value of memory at `addr` when the path ended.
+    pub fn memory(&self, addr: usize) -> Expr {
+        self.state.memory.get(&addr).cloned().unwrap_or_default()
+    }
+
+    pub fn output(&self) -> &[Expr] {
+        &self.state.output
+    }
+
+    /// The conditions under which execution follows this path, in the order it met them.
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.state.constraints
+    }
+
+    /// Where the path stopped.
+    pub fn instruction_pointer(&self) -> usize {
+        self.state.instruction_pointer
+    }
+}
+
+/// Explores the paths a program can take when parts of it are symbolic.
+pub struct Executor {
+    initial: State,
+}
+
+impl Executor {
+    pub fn new(program: &Memory) -> Self {
+        let memory = (0..program.len())
+            .filter_map(|addr| {
+                let word = program.ix(addr).unwrap_or_default();
+                (word != 0).then(|| (addr, Expr::constant(word)))
+            })
+            .collect();
+        Self {
+            initial: State {
+                memory,
+                len: program.len(),
+                ..State::default()
+            },
+        }
+    }
+
+    /// Make the cell at `addr` hold the symbol `name`, returning that symbol.
+    pub fn make_symbol(&mut self, addr: usize, name: &str) -> Expr {
+        let symbol = Expr::symbol(name);
+        self.initial.len = self.initial.len.max(addr + 1);
+        self.initial.memory.insert(addr, symbol.clone());
+        symbol
+    }
+
+    /// Provide concrete inputs, which are read before any symbolic ones.
+    pub fn provide_input(&mut self, inputs: impl IntoIterator<Item = Word>) {
+        self.initial.input.extend(inputs);
+    }
+
+    /// Follow every path from the start of the program until it stops, depth first.
+    pub fn explore(&self) -> Vec<Path> {
+        let mut finished = Vec::new();
+        let mut pending = vec![self.initial.clone()];
+        while let Some(mut state) = pending.pop() {
+            let outcome = loop {
+                if state.steps >= STEP_LIMIT {
+                    break Outcome::StepLimit;
+                }
+                let may_fork = finished.len() + pending.len() + 1 < PATH_LIMIT;
+                match state.step(may_fork) {
+                    Ok(Step::Continue) => {}
+                    Ok(Step::Fork(other)) => pending.push(other),
+                    Err(outcome) => break outcome,
+                }
+            };
+            finished.push(Path { state, outcome });
+        }
+        finished
+    }
+}
@@ -0,0 +1,200 @@
+//! Golden tests for ahead-of-time translation.
+//!
+//! Each case's program is translated, and must match its checked-in translation in `tests/aot`
+//! exactly; run with `INTCODE_BLESS=1` to regenerate them. The checked-in translations are also
+//! compiled into this test, and must behave exactly like the interpreter.
+
+use std::{collections::VecDeque, path::Path};
+
+use intcode::{
+    aot::{translate, Compiled},
+    Computer, Error, Memory, Protection, Word,
+};
+
+#[allow(clippy::all)]
+mod quine {
+    include!("aot/quine.rs");
+}
+
+#[allow(clippy::all)]
+mod compare_to_8 {
+    include!("aot/compare_to_8.rs");
+}
+
+#[allow(clippy::all)]
+mod call_return {
+    include!("aot/call_return.rs");
+}
+
+#[allow(clippy::all)]
+mod self_modifying {
+    include!("aot/self_modifying.rs");
+}
+
+struct Case {
+    name: &'static str,
+    program: &'static str,
+    compiled: Compiled,
+    inputs: &'static [&'static [Word]],
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "quine",
+        program: "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+        compiled: quine::COMPILED,
+        inputs: &[&[]],
+    },
+    Case {
+        name: "compare_to_8",
+        program: "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        compiled: compare_to_8::COMPILED,
+        inputs: &[&[7], &[8], &[9], &[]],
+    },
+    Case {
+        name: "call_return",
+        program: "109,100,3,50,21101,11,0,0,1105,1,14,4,50,99,102,2,50,50,2106,0,0",
+        compiled: call_return::COMPILED,
+        inputs: &[&[5], &[-3]],
+    },
+    Case {
+        name: "self_modifying",
+        program: "1101,0,42,5,104,0,99",
+        compiled: self_modifying::COMPILED,
+        inputs: &[&[]],
+    },
+];
+
+/// Run `computer` to completion on the interpreter.
+fn interpret(
+    computer: &mut Computer,
+    input: &mut VecDeque<Word>,
+    output: &mut Vec<Word>,
+) -> Result<(), Error> {
+    loop {
+        match computer.step_io(input, output) {
+            Ok(()) => {}
+            Err(Error::Halt(_)) => return Ok(()),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Run `program` both ways, asserting that everything observable is identical.
+fn assert_equivalent(name: &str, compiled: Compiled, program: Memory, inputs: &[Word]) {
+    let mut interpreted = Computer::<0>::new(program.clone());
+    let mut interpreted_input = inputs.iter().copied().collect();
+    let mut interpreted_output = Vec::new();
+    let interpreted_result = interpret(
+        &mut interpreted,
+        &mut interpreted_input,
+        &mut interpreted_output,
+    );
+
+    let mut computer = Computer::<0>::new(program);
+    let mut input = inputs.iter().copied().collect::<VecDeque<_>>();
+    let mut output = Vec::new();
+    let result = compiled.run(&mut computer, &mut input, &mut output);
+
+    let context = format!("{name} with inputs {inputs:?}");
+    assert_eq!(
+        format!("{result:?}"),
+        format!("{interpreted_result:?}"),
+        "{context}"
+    );
+    assert_eq!(output, interpreted_output, "{context}");
+    assert_eq!(input, interpreted_input, "{context}");
+    assert_eq!(
+        computer.instruction_pointer(),
+        interpreted.instruction_pointer(),
+        "{context}"
+    );
+    assert_eq!(
+        computer.relative_base(),
+        interpreted.relative_base(),
+        "{context}"
+    );
+    assert_eq!(computer.memory(), interpreted.memory(), "{context}");
+}
+
+#[test]
+fn translations_are_current() {
+    let bless = std::env::var_os("INTCODE_BLESS").is_some();
+    for case in CASES {
+        let program: Memory = case.program.parse().unwrap();
+        let translation = translate(&program);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("aot")
+            .join(format!("{}.rs", case.name));
+        if bless {
+            std::fs::write(&path, translation).unwrap();
+        } else {
+            let expected = std::fs::read_to_string(&path).unwrap();
+            assert!(
+                translation == expected,
+                "{}: translation has changed; rerun with INTCODE_BLESS=1 and review the diff",
+                case.name
+            );
+        }
+    }
+}
+
+#[test]
+fn compiled_matches_interpreter() {
+    for case in CASES {
+        let program: Memory = case.program.parse().unwrap();
+        assert!(case.compiled.is_for(&program), "{}", case.name);
+        for inputs in case.inputs {
+            assert_equivalent(case.name, case.compiled, program.clone(), inputs);
+        }
+    }
+}
+
+#[test]
+fn patched_code_is_interpreted() {
+    let case = &CASES[3];
+    let mut program: Memory = case.program.parse().unwrap();
+    let mut computer = Computer::<0>::new(program);
+    computer.patch(2, 7).unwrap();
+    program = computer.into_memory().into();
+    assert_equivalent(case.name, case.compiled, program, &[]);
+}
+
+#[test]
+fn instrumented_computers_are_interpreted() {
+    let load = |case: &Case| Computer::<0>::new(case.program.parse::<Memory>().unwrap());
+
+    // coverage
+    let case = &CASES[1];
+    let mut interpreted = load(case);
+    interpreted.record_coverage();
+    interpret(&mut interpreted, &mut VecDeque::from([8]), &mut Vec::new()).unwrap();
+    let mut computer = load(case);
+    computer.record_coverage();
+    case.compiled
+        .run(&mut computer, &mut VecDeque::from([8]), &mut Vec::new())
+        .unwrap();
+    assert_eq!(computer.coverage(), interpreted.coverage());
+
+    // execute protection
+    let case = &CASES[0];
+    let mut computer = load(case);
+    computer.protect(2..3, Protection::NoExecute);
+    let err = case
+        .compiled
+        .run(&mut computer, &mut VecDeque::new(), &mut Vec::new())
+        .unwrap_err();
+    assert!(matches!(err, Error::ExecuteProtected { ip: 2, addr: 2 }));
+
+    // self-modification tracking
+    let case = &CASES[3];
+    let mut computer = load(case);
+    computer.track_self_modification();
+    let mut output = Vec::new();
+    case.compiled
+        .run(&mut computer, &mut VecDeque::new(), &mut output)
+        .unwrap();
+    assert_eq!(output, [42]);
+    assert_eq!(computer.self_modification(), [5]);
+}
@@ -0,0 +1,83 @@
+// Generated by `intcode::aot::translate`; do not edit.
+
+pub const PROGRAM: &[::intcode::Word] = &[
+    109, 100, 3, 50, 21101, 11, 0, 0, 1105, 1, 14, 4, 50, 99, 102, 2,
+    50, 50, 2106, 0, 0,
+];
+
+const BLOCKS: &[(usize, usize)] = &[
+    (0, 11),
+    (11, 14),
+    (14, 21),
+];
+
+pub const COMPILED: ::intcode::aot::Compiled = ::intcode::aot::Compiled {
+    program: PROGRAM,
+    entry: run,
+};
+
+pub fn run(
+    computer: &mut ::intcode::Computer,
+    input: &mut ::std::collections::VecDeque<::intcode::Word>,
+    output: &mut ::std::vec::Vec<::intcode::Word>,
+) -> ::std::result::Result<(), ::intcode::Error> {
+    let mut ctx = ::intcode::aot::Context::new(computer, input, output, PROGRAM, BLOCKS);
+    loop {
+        match ctx.ip() {
+            0 if ctx.is_clean(0, 11) => {
+                // 0: arb #100
+                ctx.at(0);
+                let value = 100;
+                ctx.adjust_relative_base(value);
+                // 2: in [50]
+                ctx.at(2);
+                ctx.input(50)?;
+                // 4: add #11, #0, [rb+0]
+                ctx.at(4);
+                let value = ::intcode::Word::wrapping_add(11, 0);
+                if ctx.write_relative(0, value)? {
+                    ctx.at(8);
+                    continue;
+                }
+                // 8: jt #1, #14
+                ctx.at(8);
+                let test = 1;
+                let target = 14;
+                if test != 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(11);
+                }
+            }
+            11 if ctx.is_clean(11, 14) => {
+                // 11: out [50]
+                ctx.at(11);
+                let value = ctx.read(50)?;
+                ctx.output(value);
+                // 13: halt
+                ctx.at(13);
+                return Ok(());
+            }
+            14 if ctx.is_clean(14, 21) => {
+                // 14: mul #2, [50], [50]
+                ctx.at(14);
+                let value = ::intcode::Word::wrapping_mul(2, ctx.read(50)?);
+                ctx.write(50, value)?;
+                // 18: jf #0, [rb+0]
+                ctx.at(18);
+                let test = 0;
+                let target = ctx.read_relative(0)?;
+                if test == 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(21);
+                }
+            }
+            _ => {
+                if ctx.interpret()? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// Generated by `intcode::aot::translate`; do not edit.
+
+pub const PROGRAM: &[::intcode::Word] = &[
+    3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31,
+    1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104,
+    999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99,
+];
+
+const BLOCKS: &[(usize, usize)] = &[
+    (0, 9),
+    (9, 16),
+    (16, 19),
+    (22, 31),
+    (31, 36),
+    (36, 45),
+    (46, 47),
+];
+
+pub const COMPILED: ::intcode::aot::Compiled = ::intcode::aot::Compiled {
+    program: PROGRAM,
+    entry: run,
+};
+
+pub fn run(
+    computer: &mut ::intcode::Computer,
+    input: &mut ::std::collections::VecDeque<::intcode::Word>,
+    output: &mut ::std::vec::Vec<::intcode::Word>,
+) -> ::std::result::Result<(), ::intcode::Error> {
+    let mut ctx = ::intcode::aot::Context::new(computer, input, output, PROGRAM, BLOCKS);
+    loop {
+        match ctx.ip() {
+            0 if ctx.is_clean(0, 9) => {
+                // 0: in [21]
+                ctx.at(0);
+                ctx.input(21)?;
+                // 2: eq [21], #8, [20]
+                ctx.at(2);
+                let a = ctx.read(21)?;
+                let b = 8;
+                let value = ::intcode::Word::from(a == b);
+                ctx.write(20, value)?;
+                // 6: jt [20], #22
+                ctx.at(6);
+                let test = ctx.read(20)?;
+                let target = 22;
+                if test != 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(9);
+                }
+            }
+            9 if ctx.is_clean(9, 16) => {
+                // 9: lt #8, [21], [20]
+                ctx.at(9);
+                let a = 8;
+                let b = ctx.read(21)?;
+                let value = ::intcode::Word::from(a < b);
+                ctx.write(20, value)?;
+                // 13: jf [20], #31
+                ctx.at(13);
+                let test = ctx.read(20)?;
+                let target = 31;
+                if test == 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(16);
+                }
+            }
+            16 if ctx.is_clean(16, 19) => {
+                // 16: jf #0, #36
+                ctx.at(16);
+                let test = 0;
+                let target = 36;
+                if test == 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(19);
+                }
+            }
+            22 if ctx.is_clean(22, 31) => {
+                // 22: mul [21], #125, [20]
+                ctx.at(22);
+                let value = ::intcode::Word::wrapping_mul(ctx.read(21)?, 125);
+                ctx.write(20, value)?;
+                // 26: out [20]
+                ctx.at(26);
+                let value = ctx.read(20)?;
+                ctx.output(value);
+                // 28: jt #1, #46
+                ctx.at(28);
+                let test = 1;
+                let target = 46;
+                if test != 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(31);
+                }
+            }
+            31 if ctx.is_clean(31, 36) => {
+                // 31: out #999
+                ctx.at(31);
+                let value = 999;
+                ctx.output(value);
+                // 33: jt #1, #46
+                ctx.at(33);
+                let test = 1;
+                let target = 46;
+                if test != 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(36);
+                }
+            }
+            36 if ctx.is_clean(36, 45) => {
+                // 36: add #1000, #1, [20]
+                ctx.at(36);
+                let value = ::intcode::Word::wrapping_add(1000, 1);
+                ctx.write(20, value)?;
+                // 40: out [20]
+                ctx.at(40);
+                let value = ctx.read(20)?;
+                ctx.output(value);
+                // 42: jt #1, #46
+                ctx.at(42);
+                let test = 1;
+                let target = 46;
+                if test != 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(45);
+                }
+            }
+            46 if ctx.is_clean(46, 47) => {
+                // 46: halt
+                ctx.at(46);
+                return Ok(());
+            }
+            _ => {
+                if ctx.interpret()? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
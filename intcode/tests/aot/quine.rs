@@ -0,0 +1,66 @@
+// Generated by `intcode::aot::translate`; do not edit.
+
+pub const PROGRAM: &[::intcode::Word] = &[
+    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+];
+
+const BLOCKS: &[(usize, usize)] = &[
+    (0, 15),
+    (15, 16),
+];
+
+pub const COMPILED: ::intcode::aot::Compiled = ::intcode::aot::Compiled {
+    program: PROGRAM,
+    entry: run,
+};
+
+pub fn run(
+    computer: &mut ::intcode::Computer,
+    input: &mut ::std::collections::VecDeque<::intcode::Word>,
+    output: &mut ::std::vec::Vec<::intcode::Word>,
+) -> ::std::result::Result<(), ::intcode::Error> {
+    let mut ctx = ::intcode::aot::Context::new(computer, input, output, PROGRAM, BLOCKS);
+    loop {
+        match ctx.ip() {
+            0 if ctx.is_clean(0, 15) => {
+                // 0: arb #1
+                ctx.at(0);
+                let value = 1;
+                ctx.adjust_relative_base(value);
+                // 2: out [rb-1]
+                ctx.at(2);
+                let value = ctx.read_relative(-1)?;
+                ctx.output(value);
+                // 4: add [100], #1, [100]
+                ctx.at(4);
+                let value = ::intcode::Word::wrapping_add(ctx.read(100)?, 1);
+                ctx.write(100, value)?;
+                // 8: eq [100], #16, [101]
+                ctx.at(8);
+                let a = ctx.read(100)?;
+                let b = 16;
+                let value = ::intcode::Word::from(a == b);
+                ctx.write(101, value)?;
+                // 12: jf [101], #0
+                ctx.at(12);
+                let test = ctx.read(101)?;
+                let target = 0;
+                if test == 0 {
+                    ctx.jump(target)?;
+                } else {
+                    ctx.at(15);
+                }
+            }
+            15 if ctx.is_clean(15, 16) => {
+                // 15: halt
+                ctx.at(15);
+                return Ok(());
+            }
+            _ => {
+                if ctx.interpret()? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
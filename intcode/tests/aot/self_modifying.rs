@@ -0,0 +1,47 @@
+// Generated by `intcode::aot::translate`; do not edit.
+
+pub const PROGRAM: &[::intcode::Word] = &[
+    1101, 0, 42, 5, 104, 0, 99,
+];
+
+const BLOCKS: &[(usize, usize)] = &[
+    (0, 7),
+];
+
+pub const COMPILED: ::intcode::aot::Compiled = ::intcode::aot::Compiled {
+    program: PROGRAM,
+    entry: run,
+};
+
+pub fn run(
+    computer: &mut ::intcode::Computer,
+    input: &mut ::std::collections::VecDeque<::intcode::Word>,
+    output: &mut ::std::vec::Vec<::intcode::Word>,
+) -> ::std::result::Result<(), ::intcode::Error> {
+    let mut ctx = ::intcode::aot::Context::new(computer, input, output, PROGRAM, BLOCKS);
+    loop {
+        match ctx.ip() {
+            0 if ctx.is_clean(0, 7) => {
+                // 0: add #0, #42, [5]
+                ctx.at(0);
+                let value = ::intcode::Word::wrapping_add(0, 42);
+                if ctx.write(5, value)? {
+                    ctx.at(4);
+                    continue;
+                }
+                // 4: out #0
+                ctx.at(4);
+                let value = 0;
+                ctx.output(value);
+                // 6: halt
+                ctx.at(6);
+                return Ok(());
+            }
+            _ => {
+                if ctx.interpret()? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Data-driven conformance suite for the interpreter.
+//!
+//! Each case in `tests/conformance` is a group of files sharing a name:
+//!
+//! - `NAME.program`: the program; required
+//! - `NAME.input`: comma-separated inputs; none if absent
+//! - `NAME.output`: the expected comma-separated outputs; unchecked if absent
+//! - `NAME.memory`: the expected final memory; unchecked if absent
+//! - `NAME.fault`: the name of the expected `Error` variant; if absent, the program must halt
+//!
+//! Outputs and memory are checked whether or not the program faults.
+
+use std::{collections::VecDeque, fs, path::Path};
+
+use intcode::Computer;
+use intcode_test_support::{case_file, read_words, run, run_suite, Outcome, STEP_LIMIT};
+
+/// Run the case at `base` (the path without an extension), describing each way in which it failed.
+fn run_case(base: &Path) -> Vec<String> {
+    let program = read_words(&base.with_extension("program"));
+    let mut input: VecDeque<_> = case_file(base, "input")
+        .map(|path| read_words(&path))
+        .unwrap_or_default()
+        .into();
+    let mut output = Vec::new();
+    let mut computer = Computer::<0>::new(program);
+    let outcome = run(&mut computer, &mut input, &mut output, STEP_LIMIT);
+
+    let mut failures = Vec::new();
+    let expected_fault =
+        case_file(base, "fault").map(|path| fs::read_to_string(path).unwrap().trim().to_owned());
+    match (&outcome, &expected_fault) {
+        (Outcome::Halted, None) => {}
+        (Outcome::StepLimit, None) => {
+            failures.push(format!("did not halt within {STEP_LIMIT} steps"))
+        }
+        (Outcome::Fault(fault), None) => failures.push(format!("unexpected fault: {fault}")),
+        (Outcome::Fault(fault), Some(expected)) if outcome.name() != *expected => {
+            failures.push(format!("expected fault {expected}, but got {fault:?}"))
+        }
+        (Outcome::Fault(_), Some(_)) => {}
+        (_, Some(expected)) => failures.push(format!(
+            "expected fault {expected}, but got {}",
+            outcome.name()
+        )),
+    }
+
+    if let Some(path) = case_file(base, "output") {
+        let expected = read_words(&path);
+        if output != expected {
+            failures.push(format!("expected output {expected:?}, but got {output:?}"));
+        }
+    }
+
+    if let Some(path) = case_file(base, "memory") {
+        let expected = read_words(&path);
+        let memory = computer.memory().iter().collect::<Vec<_>>();
+        if memory != expected {
+            failures.push(format!("expected memory {expected:?}, but got {memory:?}"));
+        }
+    }
+
+    failures
+}
+
+#[test]
+fn conformance() {
+    run_suite(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("conformance"),
+        &["program", "input", "output", "memory", "fault"],
+        "program",
+        run_case,
+    );
+}
@@ -0,0 +1,59 @@
+//! Golden tests for decompilation.
+//!
+//! Each `NAME.asm` in `tests/decompile` is assembled and decompiled, and the pseudocode must
+//! match `NAME.txt` exactly; run with `INTCODE_BLESS=1` to regenerate them.
+
+use std::{fs, path::Path};
+
+use intcode::{assemble, decompile::decompile, Memory};
+
+#[test]
+fn decompilations_are_current() {
+    let bless = std::env::var_os("INTCODE_BLESS").is_some();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("decompile");
+    let mut sources = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "asm"))
+        .collect::<Vec<_>>();
+    sources.sort();
+    assert!(!sources.is_empty(), "no cases found");
+
+    for source in &sources {
+        let name = source.file_stem().unwrap().to_string_lossy();
+        let program = assemble(&fs::read_to_string(source).unwrap())
+            .unwrap_or_else(|err| panic!("{name}: {err}"));
+        let pseudocode = decompile(&program);
+        let path = source.with_extension("txt");
+        if bless {
+            fs::write(&path, pseudocode).unwrap();
+        } else {
+            let expected = fs::read_to_string(&path).unwrap();
+            assert!(
+                pseudocode == expected,
+                "{name}: decompilation has changed; rerun with INTCODE_BLESS=1 and review the diff"
+            );
+        }
+    }
+}
+
+#[test]
+fn every_conformance_program_decompiles() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("conformance");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "program") {
+            let program: Memory = fs::read_to_string(&path).unwrap().parse().unwrap();
+            let pseudocode = decompile(&program);
+            assert!(
+                pseudocode.contains("void start()"),
+                "{}: no entry point in\n{pseudocode}",
+                path.display()
+            );
+        }
+    }
+}
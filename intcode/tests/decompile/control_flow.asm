@@ -0,0 +1,98 @@
+; compiled from intcode-lang/tests/programs/control_flow.ic
+        arb #.stack
+        add #.exit, #0, [rb]
+        jt #1, #fn.main
+.exit:
+        halt
+
+; fn classify(n)
+fn.classify:
+        lt [rb+1], #0, [rb+2]
+        jf [rb+2], #fn.classify.1
+        add #-1, #0, [.return]
+        jf #0, [rb]
+        jt #1, #fn.classify.2
+fn.classify.1:
+        eq [rb+1], #0, [rb+2]
+        jf [rb+2], #fn.classify.3
+        add #0, #0, [.return]
+        jf #0, [rb]
+        jt #1, #fn.classify.4
+fn.classify.3:
+        add #1, #0, [.return]
+        jf #0, [rb]
+fn.classify.4:
+fn.classify.2:
+        add #0, #0, [.return]
+        jf #0, [rb]
+fn.classify.frame = 3
+
+; fn gcd(a, b)
+fn.gcd:
+fn.gcd.1:
+        eq [rb+1], [rb+2], [rb+3]
+        eq [rb+3], #0, [rb+3]
+        jf [rb+3], #fn.gcd.2
+        lt [rb+2], [rb+1], [rb+3]
+        jf [rb+3], #fn.gcd.3
+        mul [rb+2], #-1, [rb+3]
+        add [rb+1], [rb+3], [rb+3]
+        add [rb+3], #0, [rb+1]
+        jt #1, #fn.gcd.4
+fn.gcd.3:
+        mul [rb+1], #-1, [rb+3]
+        add [rb+2], [rb+3], [rb+3]
+        add [rb+3], #0, [rb+2]
+fn.gcd.4:
+        jt #1, #fn.gcd.1
+fn.gcd.2:
+        add [rb+1], #0, [.return]
+        jf #0, [rb]
+        add #0, #0, [.return]
+        jf #0, [rb]
+fn.gcd.frame = 4
+
+; fn main()
+fn.main:
+        add #fn.main.1, #0, [rb+fn.main.frame]
+        add #-5, #0, [rb+fn.main.frame+1]
+        arb #fn.main.frame
+        jt #1, #fn.classify
+fn.main.1:
+        arb #-fn.main.frame
+        add [.return], #0, [rb+1]
+        out [rb+1]
+        add #fn.main.2, #0, [rb+fn.main.frame]
+        add #0, #0, [rb+fn.main.frame+1]
+        arb #fn.main.frame
+        jt #1, #fn.classify
+fn.main.2:
+        arb #-fn.main.frame
+        add [.return], #0, [rb+1]
+        out [rb+1]
+        add #fn.main.3, #0, [rb+fn.main.frame]
+        add #5, #0, [rb+fn.main.frame+1]
+        arb #fn.main.frame
+        jt #1, #fn.classify
+fn.main.3:
+        arb #-fn.main.frame
+        add [.return], #0, [rb+1]
+        out [rb+1]
+        in [rb+1]
+        in [rb+2]
+        add #fn.main.4, #0, [rb+fn.main.frame]
+        add [rb+1], #0, [rb+fn.main.frame+1]
+        add [rb+2], #0, [rb+fn.main.frame+2]
+        arb #fn.main.frame
+        jt #1, #fn.gcd
+fn.main.4:
+        arb #-fn.main.frame
+        add [.return], #0, [rb+3]
+        out [rb+3]
+        add #0, #0, [.return]
+        jf #0, [rb]
+fn.main.frame = 4
+
+.return:
+        data 0
+.stack:
@@ -0,0 +1,7 @@
+; count down from the input to 1
+start:  in [count]
+loop:   out [count]
+        add [count], #-1, [count]
+        jt [count], #loop
+        halt
+count:  data 0
@@ -0,0 +1,60 @@
+; compiled from intcode-lang/tests/programs/factorial.ic
+        arb #.stack
+        add #.exit, #0, [rb]
+        jt #1, #fn.main
+.exit:
+        halt
+
+; fn factorial(n)
+fn.factorial:
+        add [global.calls], #1, [rb+2]
+        add [rb+2], #0, [global.calls]
+        lt #1, [rb+1], [rb+2]
+        eq [rb+2], #0, [rb+2]
+        jf [rb+2], #fn.factorial.1
+        add #1, #0, [.return]
+        jf #0, [rb]
+fn.factorial.1:
+        add [rb+1], #-1, [rb+2]
+        add #fn.factorial.2, #0, [rb+fn.factorial.frame]
+        add [rb+2], #0, [rb+fn.factorial.frame+1]
+        arb #fn.factorial.frame
+        jt #1, #fn.factorial
+fn.factorial.2:
+        arb #-fn.factorial.frame
+        add [.return], #0, [rb+3]
+        mul [rb+1], [rb+3], [rb+4]
+        add [rb+4], #0, [.return]
+        jf #0, [rb]
+        add #0, #0, [.return]
+        jf #0, [rb]
+fn.factorial.frame = 5
+
+; fn main()
+fn.main:
+        in [rb+1]
+fn.main.1:
+        lt #0, [rb+1], [rb+2]
+        jf [rb+2], #fn.main.2
+        add #fn.main.3, #0, [rb+fn.main.frame]
+        add [rb+1], #0, [rb+fn.main.frame+1]
+        arb #fn.main.frame
+        jt #1, #fn.factorial
+fn.main.3:
+        arb #-fn.main.frame
+        add [.return], #0, [rb+2]
+        out [rb+2]
+        add [rb+1], #-1, [rb+2]
+        add [rb+2], #0, [rb+1]
+        jt #1, #fn.main.1
+fn.main.2:
+        out [global.calls]
+        add #0, #0, [.return]
+        jf #0, [rb]
+fn.main.frame = 3
+
+global.calls:
+        data 0
+.return:
+        data 0
+.stack:
@@ -0,0 +1,12 @@
+; output the larger of two inputs
+        in [a]
+        in [b]
+        lt [a], [b], [t]
+        jf [t], #first
+        out [b]
+        jt #1, #done
+first:  out [a]
+done:   halt
+a:      data 0
+b:      data 0
+t:      data 0
@@ -0,0 +1,132 @@
+//! Differential testing: random programs must behave identically on the real interpreter
+//! and on the [reference] interpreter.
+//!
+//! Each case runs both interpreters in lockstep for a bounded number of steps, comparing
+//! the outcome of every step, and finally the outputs, memory, and registers.
+//!
+//! When a case fails, proptest shrinks it to a minimal program and records its seed in
+//! `regressions.txt`, so that it is replayed by every later run. Once the bug is fixed,
+//! copy the minimized program into `tests/conformance` as a named regression case.
+//!
+//! Set `PROPTEST_CASES` to run more or fewer cases than the default.
+
+mod reference;
+
+use std::collections::VecDeque;
+
+use intcode::{Computer, Error, Word};
+use proptest::{prelude::*, test_runner::FileFailurePersistence};
+use reference::{Fault, Machine, Step};
+
+/// Each case stops after this many steps, whether or not it has halted.
+const FUEL: usize = 1000;
+
+/// A case ends early, before the real interpreter takes any step which would grow memory
+/// beyond this many words.
+const MAX_LEN: usize = 1 << 16;
+
+/// Most random programs fault within a few steps, so it takes many cases to exercise much.
+const DEFAULT_CASES: u32 = 1024;
+
+fn config() -> ProptestConfig {
+    ProptestConfig {
+        cases: std::env::var("PROPTEST_CASES")
+            .ok()
+            .and_then(|cases| cases.parse().ok())
+            .unwrap_or(DEFAULT_CASES),
+        failure_persistence: Some(Box::new(FileFailurePersistence::Direct(
+            "tests/differential/regressions.txt",
+        ))),
+        ..ProptestConfig::default()
+    }
+}
+
+/// An instruction word: usually well-formed, occasionally with an unknown opcode, an unknown mode,
+/// or more mode digits than any instruction has parameters.
+fn instruction() -> impl Strategy<Value = Word> {
+    let opcode = prop_oneof![
+        8 => prop::sample::select(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 99]),
+        1 => 0..100 as Word,
+    ];
+    let mode = prop_oneof![8 => 0..3 as Word, 1 => 0..10 as Word];
+    let extra = prop_oneof![8 => Just(0), 1 => 0..10 as Word];
+    (opcode, [mode.clone(), mode.clone(), mode], extra).prop_map(|(opcode, modes, extra)| {
+        opcode + 100 * modes[0] + 1000 * modes[1] + 10_000 * modes[2] + 1_000_000 * extra
+    })
+}
+
+/// Any word: mostly instructions and small values, which are plausible addresses and offsets.
+fn word() -> impl Strategy<Value = Word> {
+    prop_oneof![
+        4 => instruction(),
+        4 => -8..72 as Word,
+        1 => any::<Word>(),
+    ]
+}
+
+fn program() -> impl Strategy<Value = Vec<Word>> {
+    prop::collection::vec(word(), 1..64)
+}
+
+fn inputs() -> impl Strategy<Value = Vec<Word>> {
+    prop::collection::vec(prop_oneof![4 => -8..72 as Word, 1 => any::<Word>()], 0..8)
+}
+
+/// The outcome of a real step, in the reference interpreter's terms.
+fn real_step(computer: &mut Computer, input: &mut VecDeque<Word>, output: &mut Vec<Word>) -> Step {
+    let err = match computer.step_io(input, output) {
+        Ok(()) => return Step::Continue,
+        Err(Error::Halt(_)) => return Step::Halt,
+        Err(err) => err,
+    };
+    Step::Fault(match err {
+        Error::UnknownOpcode(_) => Fault::UnknownOpcode,
+        Error::UnknownParameterMode(_) => Fault::UnknownParameterMode,
+        Error::TooManyParameterModes(_) => Fault::TooManyParameterModes,
+        Error::MemoryExhausted { .. } => Fault::MemoryExhausted,
+        Error::IndexFailed(_) => Fault::IndexFailed,
+        Error::ImmediateWrite => Fault::ImmediateWrite,
+        Error::AwaitingInput => Fault::AwaitingInput,
+        err => panic!("stepping with queues should never produce {err:?}"),
+    })
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[test]
+    fn matches_reference(program in program(), inputs in inputs()) {
+        let mut computer = Computer::<0>::new(program.as_slice());
+        let mut input = inputs.iter().copied().collect::<VecDeque<_>>();
+        let mut output = Vec::new();
+        let mut machine = Machine::new(&program, &inputs);
+
+        for step in 0..FUEL {
+            let ip = machine.ip;
+            let expected = machine.step();
+            if machine.len > MAX_LEN {
+                // don't make the real interpreter allocate all that; the states have diverged,
+                // so there is nothing more to compare
+                return Ok(());
+            }
+            let actual = real_step(&mut computer, &mut input, &mut output);
+            prop_assert_eq!(
+                expected,
+                actual,
+                "step {} at ip {}",
+                step,
+                ip
+            );
+            if expected != Step::Continue {
+                break;
+            }
+        }
+
+        prop_assert_eq!(&output, &machine.output);
+        prop_assert_eq!(&input, &machine.input);
+        prop_assert_eq!(computer.instruction_pointer(), machine.ip);
+        prop_assert_eq!(computer.relative_base(), machine.relative_base);
+        prop_assert_eq!(computer.memory().len(), machine.len);
+        prop_assert_eq!(computer.memory().iter().collect::<Vec<_>>(), machine.memory());
+    }
+}
@@ -0,0 +1,200 @@
+//! A deliberately simple Intcode interpreter, used as an oracle for the real one.
+//!
+//! Nothing here is optimized: memory is a sparse map, and every instruction is decoded
+//! from scratch, digit by digit. Where the puzzle text leaves behavior unspecified,
+//! this follows the real interpreter's documented choices:
+//!
+//! - arithmetic wraps on overflow
+//! - instructions whose parameters run past the end of memory fault
+//! - `Input` only consumes its input once it has been stored
+
+use std::collections::{BTreeMap, VecDeque};
+
+use intcode::Word;
+
+/// Addresses above this fault; matches the real interpreter's limit.
+const MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+
+/// Why an instruction failed; named after the corresponding `intcode::Error` variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    UnknownOpcode,
+    UnknownParameterMode,
+    TooManyParameterModes,
+    MemoryExhausted,
+    IndexFailed,
+    ImmediateWrite,
+    AwaitingInput,
+}
+
+/// The outcome of a single step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Continue,
+    Halt,
+    Fault(Fault),
+}
+
+pub struct Machine {
+    memory: BTreeMap<usize, Word>,
+    pub len: usize,
+    pub ip: usize,
+    pub relative_base: Word,
+    pub input: VecDeque<Word>,
+    pub output: Vec<Word>,
+}
+
+impl Machine {
+    pub fn new(program: &[Word], input: &[Word]) -> Self {
+        Self {
+            memory: program.iter().copied().enumerate().collect(),
+            len: program.len(),
+            ip: 0,
+            relative_base: 0,
+            input: input.iter().copied().collect(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Every word from address 0 up to the highest address initialized or written.
+    pub fn memory(&self) -> Vec<Word> {
+        (0..self.len)
+            .map(|addr| self.memory.get(&addr).copied().unwrap_or_default())
+            .collect()
+    }
+
+    /// Execute one instruction. On a fault, nothing has changed.
+    pub fn step(&mut self) -> Step {
+        match self.try_step() {
+            Ok(true) => Step::Halt,
+            Ok(false) => Step::Continue,
+            Err(fault) => Step::Fault(fault),
+        }
+    }
+
+    fn try_step(&mut self) -> Result<bool, Fault> {
+        let word = self.load(self.ip)?;
+
+        let opcode = word % 100;
+        let n_params = match opcode {
+            1 | 2 | 7 | 8 => 3,
+            5 | 6 => 2,
+            3 | 4 | 9 => 1,
+            99 => 0,
+            _ => return Err(Fault::UnknownOpcode),
+        };
+
+        let mut modes = [0; 4];
+        let mut digits = word / 100;
+        for mode in &mut modes {
+            *mode = digits % 10;
+            if !(0..=2).contains(mode) {
+                return Err(Fault::UnknownParameterMode);
+            }
+            digits /= 10;
+        }
+        if digits != 0 {
+            return Err(Fault::TooManyParameterModes);
+        }
+
+        if opcode == 99 {
+            return Ok(true);
+        }
+
+        let input = if opcode == 3 {
+            Some(*self.input.front().ok_or(Fault::AwaitingInput)?)
+        } else {
+            None
+        };
+
+        if self.ip + 1 + n_params > self.len {
+            return Err(Fault::MemoryExhausted);
+        }
+        let raw = |idx: usize| {
+            self.memory
+                .get(&(self.ip + 1 + idx))
+                .copied()
+                .unwrap_or_default()
+        };
+        let read = |idx: usize| match modes[idx] {
+            0 => self.read(raw(idx)),
+            1 => Ok(raw(idx)),
+            _ => self.read(raw(idx).wrapping_add(self.relative_base)),
+        };
+        let address = |idx: usize| match modes[idx] {
+            0 => to_address(raw(idx)),
+            1 => Err(Fault::ImmediateWrite),
+            _ => to_address(raw(idx).wrapping_add(self.relative_base)),
+        };
+
+        let mut next_ip = self.ip + 1 + n_params;
+        match opcode {
+            1 => {
+                let value = read(0)?.wrapping_add(read(1)?);
+                let addr = address(2)?;
+                self.store(addr, value);
+            }
+            2 => {
+                let value = read(0)?.wrapping_mul(read(1)?);
+                let addr = address(2)?;
+                self.store(addr, value);
+            }
+            3 => {
+                let addr = address(0)?;
+                self.store(addr, input.unwrap());
+                self.input.pop_front();
+            }
+            4 => {
+                let value = read(0)?;
+                self.output.push(value);
+            }
+            5 | 6 => {
+                let (test, target) = (read(0)?, read(1)?);
+                if (test != 0) == (opcode == 5) {
+                    next_ip = usize::try_from(target).map_err(|_| Fault::IndexFailed)?;
+                }
+            }
+            7 => {
+                let value = Word::from(read(0)? < read(1)?);
+                let addr = address(2)?;
+                self.store(addr, value);
+            }
+            8 => {
+                let value = Word::from(read(0)? == read(1)?);
+                let addr = address(2)?;
+                self.store(addr, value);
+            }
+            9 => {
+                self.relative_base = self.relative_base.wrapping_add(read(0)?);
+            }
+            _ => unreachable!("opcodes were checked above"),
+        }
+
+        self.ip = next_ip;
+        Ok(false)
+    }
+
+    fn load(&self, addr: usize) -> Result<Word, Fault> {
+        if addr > MEMORY_LIMIT {
+            return Err(Fault::MemoryExhausted);
+        }
+        Ok(self.memory.get(&addr).copied().unwrap_or_default())
+    }
+
+    fn read(&self, addr: Word) -> Result<Word, Fault> {
+        self.load(to_address(addr)?)
+    }
+
+    fn store(&mut self, addr: usize, value: Word) {
+        self.memory.insert(addr, value);
+        self.len = self.len.max(addr + 1);
+    }
+}
+
+fn to_address(addr: Word) -> Result<usize, Fault> {
+    let addr = usize::try_from(addr).map_err(|_| Fault::IndexFailed)?;
+    if addr > MEMORY_LIMIT {
+        return Err(Fault::MemoryExhausted);
+    }
+    Ok(addr)
+}
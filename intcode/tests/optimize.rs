@@ -0,0 +1,57 @@
+//! Every conformance program must behave the same once optimized: the same outputs,
+//! the same inputs consumed, and the same outcome.
+
+use std::{collections::VecDeque, path::Path};
+
+use intcode::{optimize::optimize, Computer, Memory, Word};
+use intcode_test_support::{case_file, read_words, run, run_suite, STEP_LIMIT};
+
+#[derive(Debug, PartialEq, Eq)]
+struct Behavior {
+    output: Vec<Word>,
+    unread: VecDeque<Word>,
+    /// How the program stopped: `Halt`, the name of a fault, or `StepLimit`.
+    outcome: String,
+}
+
+fn behavior(program: Memory, input: &[Word]) -> Behavior {
+    let mut computer = Computer::<0>::new(program);
+    let mut input = input.iter().copied().collect::<VecDeque<_>>();
+    let mut output = Vec::new();
+    let outcome = run(&mut computer, &mut input, &mut output, STEP_LIMIT).name();
+    Behavior {
+        output,
+        unread: input,
+        outcome,
+    }
+}
+
+/// Optimize the case at `base` (the path without an extension), describing how its behavior
+/// changed, if it did.
+fn run_case(base: &Path) -> Vec<String> {
+    let program = Memory::from(read_words(&base.with_extension("program")));
+    let input = case_file(base, "input")
+        .map(|path| read_words(&path))
+        .unwrap_or_default();
+
+    let optimized = optimize(&program).program;
+    let expected = behavior(program, &input);
+    let actual = behavior(optimized, &input);
+    if expected == actual {
+        Vec::new()
+    } else {
+        vec![format!("expected {expected:?}, but got {actual:?}")]
+    }
+}
+
+#[test]
+fn optimized_conformance_programs_behave_the_same() {
+    run_suite(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("conformance"),
+        &["program", "input", "output", "memory", "fault"],
+        "program",
+        run_case,
+    );
+}
@@ -0,0 +1,83 @@
+//! Finding a day's input file on disk.
+//!
+//! This has no dependencies, so that build scripts which translate inputs ahead of time can
+//! use it as cheaply as the runner does.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable naming a directory containing `dayNN.txt` input files.
+pub const INPUT_DIR_VAR: &str = "INTCODE_INPUT_DIR";
+
+/// No input file could be found.
+#[derive(Debug)]
+pub struct InputNotFound {
+    pub day: u8,
+    /// Each location tried, in order, with why it was not used where that is not obvious.
+    pub tried: Vec<String>,
+}
+
+impl fmt::Display for InputNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no input found for day {}; tried:", self.day)?;
+        for location in &self.tried {
+            write!(f, "\n  {location}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InputNotFound {}
+
+/// Where the input for `day` may be, in order:
+///
+/// - `$INTCODE_INPUT_DIR/dayNN.txt`, if the variable is set
+/// - `inputs/dayNN.txt` in the root of this repository
+pub fn candidates(day: u8) -> Vec<PathBuf> {
+    let file_name = file_name(day);
+    let mut candidates = Vec::new();
+    if let Some(dir) = std::env::var_os(INPUT_DIR_VAR) {
+        candidates.push(PathBuf::from(dir).join(&file_name));
+    }
+    candidates.push(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("inputs")
+            .join(&file_name),
+    );
+    candidates
+}
+
+fn file_name(day: u8) -> String {
+    format!("day{day:02}.txt")
+}
+
+/// Find the input file for `day`.
+///
+/// If `explicit` is set, it is the only candidate. Otherwise, the first of the
+/// [`candidates`] which exists is used.
+pub fn find(explicit: Option<&Path>, day: u8) -> Result<PathBuf, InputNotFound> {
+    let mut tried = Vec::new();
+
+    if let Some(path) = explicit {
+        if path.is_file() {
+            return Ok(path.to_owned());
+        }
+        tried.push(path.display().to_string());
+        return Err(InputNotFound { day, tried });
+    }
+
+    if std::env::var_os(INPUT_DIR_VAR).is_none() {
+        tried.push(format!("${INPUT_DIR_VAR}/{} (not set)", file_name(day)));
+    }
+    for candidate in candidates(day) {
+        if candidate.is_file() {
+            return Ok(candidate);
+        }
+        tried.push(candidate.display().to_string());
+    }
+
+    Err(InputNotFound { day, tried })
+}
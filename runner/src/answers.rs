@@ -0,0 +1,94 @@
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{collections::HashMap, fmt, path::Path, time::Duration};
+
+/// The result of running one part of a day's puzzle on one program.
+#[derive(Debug, Clone, Serialize)]
+pub struct Answer {
+    /// Index of the program within the input file.
+    pub program: usize,
+    pub part: u8,
+    pub answer: String,
+    #[serde(rename = "elapsed_secs", serialize_with = "as_secs")]
+    pub elapsed: Duration,
+    /// The known-good answer, when checking against an answers file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+}
+
+fn as_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+impl Answer {
+    /// `true` when a known-good answer exists and this answer differs from it.
+    pub fn is_regression(&self) -> bool {
+        self.expected
+            .as_ref()
+            .is_some_and(|expected| *expected != self.answer)
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pgm {} pt {}: {} ({:?})",
+            self.program, self.part, self.answer, self.elapsed
+        )?;
+        match &self.expected {
+            Some(expected) if *expected != self.answer => {
+                write!(f, " [REGRESSION: expected {expected}]")
+            }
+            Some(_) => write!(f, " [ok]"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Known-good answers for a single day, indexed by program.
+///
+/// In `answers.toml`, each day is a table; each part is an array with one answer per program:
+///
+/// ```toml
+/// [day02]
+/// part1 = [3500]
+/// part2 = ["1202"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KnownAnswers {
+    #[serde(default)]
+    part1: Vec<toml::Value>,
+    #[serde(default)]
+    part2: Vec<toml::Value>,
+}
+
+impl KnownAnswers {
+    /// Load the known answers for `day` from the answers file at `path`.
+    ///
+    /// Fails if the file has no table for `day`, or a table has a key other than `part1`
+    /// and `part2`.
+    pub fn load(path: &Path, day: u8) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("reading answers from {}", path.display()))?;
+        let mut days: HashMap<String, KnownAnswers> = toml::from_str(&text)
+            .wrap_err_with(|| format!("parsing answers from {}", path.display()))?;
+        let table = format!("day{day:02}");
+        days.remove(&table)
+            .ok_or_else(|| eyre!("no [{table}] table in {}", path.display()))
+    }
+
+    /// The known answer for `part` of `program`, if any.
+    pub fn get(&self, part: u8, program: usize) -> Option<String> {
+        let answers = match part {
+            1 => &self.part1,
+            2 => &self.part2,
+            _ => return None,
+        };
+        answers.get(program).map(|value| match value {
+            toml::Value::String(answer) => answer.clone(),
+            other => other.to_string(),
+        })
+    }
+}
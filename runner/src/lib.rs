@@ -0,0 +1,381 @@
+//! Shared entry point for the day crates.
+//!
+//! A day crate implements [`Day`] in its library, and its `main.rs` is just:
+//!
+//! ```rust,ignore
+//! runner::main!(day02::Day02);
+//! ```
+
+mod answers;
+
+use color_eyre::eyre::eyre;
+use intcode::Memory;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Instant,
+};
+use structopt::StructOpt;
+
+pub use answers::{Answer, KnownAnswers};
+pub use color_eyre::eyre::Result;
+pub use puzzle_input::{InputNotFound, INPUT_DIR_VAR};
+
+#[cfg(feature = "fetch")]
+const YEAR: u32 = 2019;
+
+/// Environment variable holding the website's session cookie, for downloading inputs.
+#[cfg(feature = "fetch")]
+pub const SESSION_VAR: &str = "AOC_SESSION";
+
+/// The puzzle logic for a single day.
+///
+/// Each part is run once for every program in the input file.
+pub trait Day {
+    const DAY: u8;
+
+    type Part1: fmt::Display;
+    type Part2: fmt::Display;
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn part1(program: &Memory) -> Result<Self::Part1, Self::Error>;
+    fn part2(program: &Memory) -> Result<Self::Part2, Self::Error>;
+
+    /// The contents of a file depicting `answer`, in the format which `extension` names,
+    /// for `--render`.
+    ///
+    /// Returns `None` if this day cannot render its answers in that format; by default, in any.
+    fn render(_answer: &Self::Part2, _extension: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct RunArgs {
+    /// input file
+    #[structopt(long, parse(from_os_str))]
+    input: Option<PathBuf>,
+
+    /// skip part 1
+    #[structopt(long)]
+    no_part1: bool,
+
+    /// run part 2
+    #[structopt(long)]
+    part2: bool,
+
+    /// output format: text or json
+    #[structopt(long, default_value = "text")]
+    format: Format,
+
+    /// compare answers against known-good values, failing on any regression or missing value
+    #[structopt(long)]
+    check: bool,
+
+    /// file of known-good answers [default: answers.toml beside the input file]
+    #[structopt(long, parse(from_os_str))]
+    answers: Option<PathBuf>,
+
+    /// write a depiction of each part 2 answer to this file, in the format its extension
+    /// names; with several programs, each file name is suffixed with the program's index
+    #[structopt(long, parse(from_os_str))]
+    render: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format {s:?}; expected `text` or `json`")),
+        }
+    }
+}
+
+/// Find the input file for `day`.
+///
+/// If `explicit` is set, it is the only candidate. Otherwise, try in order:
+///
+/// - each of [`puzzle_input::candidates`]
+/// - downloading the input from the website into the first of those locations, if the
+///   `fetch` feature is enabled
+pub fn resolve_input(explicit: Option<&Path>, day: u8) -> Result<PathBuf, InputNotFound> {
+    let mut err = match puzzle_input::find(explicit, day) {
+        Ok(path) => return Ok(path),
+        Err(err) if explicit.is_some() => return Err(err),
+        Err(err) => err,
+    };
+
+    #[cfg(feature = "fetch")]
+    {
+        let path = puzzle_input::candidates(day).swap_remove(0);
+        match fetch(day, &path) {
+            Ok(()) => return Ok(path),
+            Err(fetch_err) => err
+                .tried
+                .push(format!("download from website ({fetch_err})")),
+        }
+    }
+    #[cfg(not(feature = "fetch"))]
+    err.tried
+        .push("download from website (disabled; enable the `fetch` feature)".into());
+
+    Err(err)
+}
+
+/// Download the input for `day` to `path`, creating its directory if need be.
+///
+/// The website identifies the user by the session cookie in [`SESSION_VAR`].
+#[cfg(feature = "fetch")]
+fn fetch(day: u8, path: &Path) -> Result<()> {
+    let session = std::env::var(SESSION_VAR).map_err(|_| eyre!("${SESSION_VAR} is not set"))?;
+    let input = ureq::get(&format!("https://adventofcode.com/{YEAR}/day/{day}/input"))
+        .set("Cookie", &format!("session={session}"))
+        .call()?
+        .into_string()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, input)?;
+    Ok(())
+}
+
+/// Where `--render` writes the answer for program `idx` of `n_programs`.
+fn render_path(path: &Path, idx: usize, n_programs: usize) -> PathBuf {
+    if n_programs == 1 {
+        return path.to_owned();
+    }
+    let mut name = path.file_stem().unwrap_or_default().to_owned();
+    name.push(format!("-{idx}"));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+/// Write `D`'s depiction of `answer`, for program `idx` of `n_programs`, as `--render` asks.
+fn render<D: Day>(path: &Path, idx: usize, n_programs: usize, answer: &D::Part2) -> Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+    let contents = D::render(answer, extension)
+        .ok_or_else(|| eyre!("day {} cannot render to {:?} files", D::DAY, extension))?;
+    std::fs::write(render_path(path, idx, n_programs), contents)?;
+    Ok(())
+}
+
+/// Run `solve` over every program, timing each run, and passing each answer to `then`
+/// along with its program's index.
+///
+/// In text format, each answer is printed as soon as it is available.
+fn run_part<T: fmt::Display, E>(
+    part: u8,
+    programs: &[Memory],
+    solve: impl Fn(&Memory) -> Result<T, E>,
+    then: impl Fn(usize, &T) -> Result<()>,
+    known: Option<&KnownAnswers>,
+    format: Format,
+) -> Result<Vec<Answer>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut answers = Vec::with_capacity(programs.len());
+    for (idx, program) in programs.iter().enumerate() {
+        let start = Instant::now();
+        let answer = solve(program)?;
+        let elapsed = start.elapsed();
+        then(idx, &answer)?;
+        let answer = Answer {
+            program: idx,
+            part,
+            answer: answer.to_string(),
+            elapsed,
+            expected: known.and_then(|known| known.get(part, idx)),
+        };
+        if format == Format::Text {
+            println!("{answer}");
+        }
+        answers.push(answer);
+    }
+    Ok(answers)
+}
+
+/// Parse arguments, load the input, and run the requested parts of `D`.
+pub fn run<D: Day>() -> Result<()> {
+    color_eyre::install()?;
+    let args = RunArgs::from_args();
+    let input_path = resolve_input(args.input.as_deref(), D::DAY)?;
+    let programs = Memory::from_path(&input_path)?;
+
+    let known = if args.check {
+        let answers_path = args
+            .answers
+            .clone()
+            .unwrap_or_else(|| input_path.with_file_name("answers.toml"));
+        Some(KnownAnswers::load(&answers_path, D::DAY)?)
+    } else {
+        None
+    };
+
+    let mut answers = Vec::new();
+    if !args.no_part1 {
+        answers.extend(run_part(
+            1,
+            &programs,
+            D::part1,
+            |_, _| Ok(()),
+            known.as_ref(),
+            args.format,
+        )?);
+    }
+    if args.part2 {
+        answers.extend(run_part(
+            2,
+            &programs,
+            D::part2,
+            |idx, answer| match &args.render {
+                Some(path) => render::<D>(path, idx, programs.len(), answer),
+                None => Ok(()),
+            },
+            known.as_ref(),
+            args.format,
+        )?);
+    }
+
+    if args.format == Format::Json {
+        println!("{}", serde_json::to_string_pretty(&answers)?);
+    }
+
+    if args.check {
+        check(&answers)?;
+    }
+    Ok(())
+}
+
+/// Fail if any answer differs from its known-good value, or has none.
+fn check(answers: &[Answer]) -> Result<()> {
+    let regressions = answers
+        .iter()
+        .filter(|answer| answer.is_regression())
+        .count();
+    let unknown = answers
+        .iter()
+        .filter(|answer| answer.expected.is_none())
+        .count();
+    match (regressions, unknown) {
+        (0, 0) => Ok(()),
+        (_, 0) => Err(eyre!(
+            "{regressions} answer(s) differ from known-good values"
+        )),
+        _ => Err(eyre!(
+            "{regressions} answer(s) differ from known-good values, and {unknown} have none"
+        )),
+    }
+}
+
+/// Define `fn main` for a day crate, given its [`Day`] implementation.
+#[macro_export]
+macro_rules! main {
+    ($day:ty) => {
+        fn main() -> $crate::Result<()> {
+            $crate::run::<$day>()
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_input_is_the_only_candidate() {
+        let missing = Path::new("/nonexistent/day02.txt");
+        let err = resolve_input(Some(missing), 2).unwrap_err();
+        assert_eq!(err.tried, [missing.display().to_string()]);
+    }
+
+    #[test]
+    fn render_paths_are_suffixed_only_for_several_programs() {
+        let path = Path::new("out/image.png");
+        assert_eq!(render_path(path, 0, 1), path);
+        assert_eq!(render_path(path, 0, 2), Path::new("out/image-0.png"));
+        assert_eq!(render_path(path, 1, 2), Path::new("out/image-1.png"));
+        assert_eq!(render_path(Path::new("image"), 1, 2), Path::new("image-1"));
+    }
+
+    #[test]
+    fn check_fails_without_known_answers() {
+        let answer = Answer {
+            program: 0,
+            part: 1,
+            answer: "3500".into(),
+            elapsed: Default::default(),
+            expected: Some("3500".into()),
+        };
+        assert!(check(std::slice::from_ref(&answer)).is_ok());
+
+        let unknown = Answer {
+            expected: None,
+            ..answer.clone()
+        };
+        let err = check(&[answer, unknown]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "0 answer(s) differ from known-good values, and 1 have none"
+        );
+    }
+
+    #[test]
+    fn answers_files_must_cover_the_day() {
+        let load = |text: &str, day| {
+            let path =
+                std::env::temp_dir().join(format!("answers-{day}-{}.toml", std::process::id()));
+            std::fs::write(&path, text).unwrap();
+            let known = KnownAnswers::load(&path, day);
+            std::fs::remove_file(&path).unwrap();
+            known
+        };
+
+        let missing = load("[day5]\npart1 = [1]\n", 5).unwrap_err();
+        assert!(missing.to_string().starts_with("no [day05] table in "));
+        assert!(load("[day07]\npart3 = [1]\n", 7).is_err());
+    }
+
+    #[test]
+    fn known_answers_compare_as_strings() {
+        let path = std::env::temp_dir().join(format!("answers-{}.toml", std::process::id()));
+        std::fs::write(&path, "[day02]\npart1 = [3500, \"1202\"]\n").unwrap();
+        let known = KnownAnswers::load(&path, 2).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(known.get(1, 0).as_deref(), Some("3500"));
+        assert_eq!(known.get(1, 1).as_deref(), Some("1202"));
+        assert_eq!(known.get(1, 2), None);
+        assert_eq!(known.get(2, 0), None);
+
+        let answer = Answer {
+            program: 0,
+            part: 1,
+            answer: "3500".into(),
+            elapsed: Default::default(),
+            expected: known.get(1, 0),
+        };
+        assert!(!answer.is_regression());
+        assert!(Answer {
+            expected: known.get(1, 1),
+            ..answer
+        }
+        .is_regression());
+    }
+}